@@ -7,11 +7,344 @@
 //! Cargo re-run the build script whenever `memory.x` is changed,
 //! updating `memory.x` ensures a rebuild of the application with the
 //! new memory settings.
+//!
+//! It also turns the product configuration (`config.toml`, or the file named
+//! by `RADIO_CONFIG`) into constants included by `src/config.rs`, and the
+//! command enums in `src/commands.rs` into the command table included by
+//! `src/schema.rs`.
 
+use std::collections::HashMap;
 use std::env;
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Expr, Fields, ItemEnum, Lit, Meta, Token, Type};
+
+/// Product configuration used unless `RADIO_CONFIG` names another file.
+const DEFAULT_CONFIG: &str = "config.toml";
+
+fn float(value: &toml::Value) -> Option<f64> {
+    value
+        .as_float()
+        .or_else(|| value.as_integer().map(|i| i as f64))
+}
+
+/// Generate `config.rs` in `out` from the product configuration.
+fn generate_config(out: &Path) {
+    println!("cargo:rerun-if-env-changed=RADIO_CONFIG");
+    let path = env::var("RADIO_CONFIG").unwrap_or_else(|_| DEFAULT_CONFIG.into());
+    println!("cargo:rerun-if-changed={}", path);
+    let text = std::fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("cannot read configuration {}: {}", path, err));
+    let config: toml::Table = text
+        .parse()
+        .unwrap_or_else(|err| panic!("invalid configuration {}: {}", path, err));
+    let empty = toml::Table::new();
+    let section = |name: &str| {
+        config
+            .get(name)
+            .map(|section| {
+                section
+                    .as_table()
+                    .unwrap_or_else(|| panic!("[{}] must be a table", name))
+            })
+            .unwrap_or(&empty)
+    };
+    let radio = section("radio");
+    let features = section("features");
+
+    // Variant name and FM band in MHz, as in `Region` in src/config.rs.
+    let (region, band) = match radio.get("region").map(|v| v.as_str()) {
+        None | Some(Some("eu")) => ("Eu", (87.5, 108.0)),
+        Some(Some("us")) => ("Us", (87.5, 108.0)),
+        Some(Some("japan")) => ("Japan", (76.0, 95.0)),
+        _ => panic!("radio.region must be eu, us or japan"),
+    };
+    let frequency = match radio.get("frequency") {
+        None => "None".to_string(),
+        Some(value) => match float(value) {
+            Some(mhz) if (band.0..=band.1).contains(&mhz) => format!(
+                "Some(Frequency::from_khz({}))",
+                (mhz * 1000.0).round() as u32
+            ),
+            _ => panic!(
+                "radio.frequency must be in MHz, {} to {} in this region",
+                band.0, band.1
+            ),
+        },
+    };
+    let volume = match radio.get("volume") {
+        None => "None".to_string(),
+        Some(value) => match value.as_integer() {
+            Some(pct @ 0..=100) => format!("Some({})", pct),
+            _ => panic!("radio.volume must be a percentage, 0 to 100"),
+        },
+    };
+    let feature = |name: &str, default: bool| match features.get(name) {
+        None => default,
+        Some(value) => value
+            .as_bool()
+            .unwrap_or_else(|| panic!("features.{} must be true or false", name)),
+    };
+
+    let mut generated = File::create(out.join("config.rs")).unwrap();
+    writeln!(generated, "// Generated by build.rs from {}.", path).unwrap();
+    writeln!(generated, "pub const REGION: Region = Region::{};", region).unwrap();
+    writeln!(
+        generated,
+        "pub const STARTUP_FREQUENCY: Option<Frequency> = {};",
+        frequency
+    )
+    .unwrap();
+    writeln!(
+        generated,
+        "pub const STARTUP_VOLUME_PCT: Option<u8> = {};",
+        volume
+    )
+    .unwrap();
+    let flags = [
+        ("gps", true),
+        ("alarm", true),
+        ("alert", true),
+        ("remote_cli", false),
+    ];
+    for (name, default) in flags {
+        writeln!(
+            generated,
+            "pub const {}: bool = {};",
+            name.to_uppercase(),
+            feature(name, default)
+        )
+        .unwrap();
+    }
+}
+
+/// Origin and length of the `memory.x` region called `name`.
+fn memory_region(memory: &str, name: &str) -> (u64, u64) {
+    let line = memory
+        .lines()
+        .find(|line| line.trim_start().starts_with(name))
+        .unwrap_or_else(|| panic!("memory.x has no {} region", name));
+    let field = |key: &str| {
+        let value = line
+            .split(',')
+            .find_map(|part| part.split_once(key))
+            .and_then(|(_, value)| value.trim().strip_prefix('='))
+            .unwrap_or_else(|| panic!("{} in memory.x has no {}", name, key))
+            .trim();
+        let (digits, scale) = match value.strip_suffix('K') {
+            Some(kib) => (kib, 1024),
+            None => match value.strip_suffix('M') {
+                Some(mib) => (mib, 1024 * 1024),
+                None => (value, 1),
+            },
+        };
+        let number = match digits.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => digits.parse(),
+        };
+        number.unwrap_or_else(|_| panic!("{} {} in memory.x is not a number", name, key)) * scale
+    };
+    (field("ORIGIN"), field("LENGTH"))
+}
+
+/// Generate `build_info.rs` in `out` with the build profile and the memory
+/// sizes from `memory.x`, for `version --size`.
+fn generate_build_info(out: &Path) {
+    let memory = include_str!("memory.x");
+    let mut generated = File::create(out.join("build_info.rs")).unwrap();
+    writeln!(generated, "// Generated by build.rs from memory.x.").unwrap();
+    writeln!(
+        generated,
+        "pub const PROFILE: &str = \"{}\";",
+        env::var("PROFILE").unwrap()
+    )
+    .unwrap();
+    writeln!(
+        generated,
+        "pub const OPT_LEVEL: &str = \"{}\";",
+        env::var("OPT_LEVEL").unwrap()
+    )
+    .unwrap();
+    for name in ["FLASH", "RAM"] {
+        let (origin, length) = memory_region(memory, name);
+        writeln!(
+            generated,
+            "pub const {}: Memory = Memory {{ origin: 0x{:08X}, length: {} }};",
+            name, origin, length
+        )
+        .unwrap();
+    }
+}
+
+/// Doc comment of an item, its lines joined by spaces.
+fn doc(attrs: &[Attribute]) -> String {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(doc) if doc.path.is_ident("doc") => match &doc.value {
+                Expr::Lit(lit) => match &lit.lit {
+                    Lit::Str(text) => Some(text.value().trim().to_string()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    lines.join(" ")
+}
+
+/// Whether one of `attrs` is `#[name(..., flag, ...)]`.
+fn has_flag(attrs: &[Attribute], name: &str, flag: &str) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path().is_ident(name)
+            && attr
+                .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                .is_ok_and(|metas| metas.iter().any(|meta| meta.path().is_ident(flag)))
+    })
+}
+
+/// Command or argument name the CLI derive gives an identifier:
+/// `TuneLock` becomes `tune-lock` and `snooze_time` becomes `snooze-time`.
+fn kebab(ident: &str) -> String {
+    let mut name = String::new();
+    let mut previous: Option<char> = None;
+    for c in ident.chars() {
+        if c == '_' {
+            name.push('-');
+        } else if c.is_ascii_uppercase() {
+            if previous.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit()) {
+                name.push('-');
+            }
+            name.push(c.to_ascii_lowercase());
+        } else {
+            name.push(c);
+        }
+        previous = Some(c);
+    }
+    name
+}
+
+/// Name of the last path segment of `ty`, such as `Option` or `u8`.
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => path.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// `ArgKind` variant of an argument of type `ty`, and whether it may be
+/// omitted.
+fn arg_kind(ty: &Type, help: &str) -> (&'static str, bool) {
+    if let Type::Path(path) = ty
+        && let Some(segment) = path.path.segments.last()
+        && segment.ident == "Option"
+        && let syn::PathArguments::AngleBracketed(args) = &segment.arguments
+        && let Some(syn::GenericArgument::Type(inner)) = args.args.first()
+    {
+        return (arg_kind(inner, help).0, true);
+    }
+    if let Type::Reference(_) = ty {
+        // Frequencies are parsed from text so they can carry decimals.
+        return (if help.contains("MHz") { "Mhz" } else { "Str" }, false);
+    }
+    match type_name(ty).as_deref() {
+        Some("u8") => ("U8", false),
+        Some("u16") => ("U16", false),
+        Some("u32") => ("U32", false),
+        Some("bool") => ("Flag", true),
+        _ => panic!(
+            "commands.rs: unsupported argument type {}",
+            quote::quote!(#ty)
+        ),
+    }
+}
+
+/// Append the `CommandSchema` entries of the variants of `name` to `out`.
+fn schema_entries(enums: &HashMap<String, ItemEnum>, name: &str, indent: usize, out: &mut String) {
+    let item = enums
+        .get(name)
+        .unwrap_or_else(|| panic!("commands.rs: no command enum {}", name));
+    let pad = " ".repeat(indent);
+    for variant in &item.variants {
+        let command = kebab(&variant.ident.to_string());
+        let help = doc(&variant.attrs);
+        let fields: Vec<_> = match &variant.fields {
+            Fields::Named(fields) => fields.named.iter().collect(),
+            Fields::Unit => Vec::new(),
+            Fields::Unnamed(_) => panic!("commands.rs: {} has unnamed fields", variant.ident),
+        };
+        if let [field] = fields.as_slice()
+            && has_flag(&field.attrs, "command", "subcommand")
+        {
+            let subcommand = type_name(&field.ty).unwrap();
+            out.push_str(&format!(
+                "{pad}CommandSchema::group({:?}, {:?}, &[\n",
+                command, help
+            ));
+            schema_entries(enums, &subcommand, indent + 4, out);
+            out.push_str(&format!("{pad}]),\n"));
+        } else if fields.is_empty() {
+            out.push_str(&format!(
+                "{pad}CommandSchema::leaf({:?}, {:?}),\n",
+                command, help
+            ));
+        } else {
+            out.push_str(&format!(
+                "{pad}CommandSchema::with_args({:?}, {:?}, &[\n",
+                command, help
+            ));
+            for field in fields {
+                let ident = field.ident.as_ref().unwrap().to_string();
+                let help = doc(&field.attrs);
+                let (kind, optional) = arg_kind(&field.ty, &help);
+                let name = if has_flag(&field.attrs, "arg", "long") {
+                    format!("--{}", kebab(&ident))
+                } else {
+                    kebab(&ident)
+                };
+                out.push_str(&format!(
+                    "{pad}    Arg::{}({:?}, ArgKind::{}, {:?}),\n",
+                    if optional { "optional" } else { "required" },
+                    name,
+                    kind,
+                    help
+                ));
+            }
+            out.push_str(&format!("{pad}]),\n"));
+        }
+    }
+}
+
+/// Generate `schema.rs` in `out` with the command table described by the
+/// `#[derive(Command)]` enums in `src/commands.rs`, starting at
+/// `BaseCommand`.
+fn generate_schema(out: &Path) {
+    const COMMANDS: &str = "src/commands.rs";
+    println!("cargo:rerun-if-changed={}", COMMANDS);
+    let text = std::fs::read_to_string(COMMANDS).unwrap();
+    let file = syn::parse_file(&text).unwrap_or_else(|err| panic!("{}: {}", COMMANDS, err));
+    let enums: HashMap<String, ItemEnum> = file
+        .items
+        .into_iter()
+        .filter_map(|item| match item {
+            syn::Item::Enum(item) if has_flag(&item.attrs, "derive", "Command") => {
+                Some((item.ident.to_string(), item))
+            }
+            _ => None,
+        })
+        .collect();
+    let mut table = String::new();
+    schema_entries(&enums, "BaseCommand", 4, &mut table);
+    let mut generated = File::create(out.join("schema.rs")).unwrap();
+    writeln!(generated, "// Generated by build.rs from {}.", COMMANDS).unwrap();
+    writeln!(generated, "pub const COMMANDS: &[CommandSchema] = &[").unwrap();
+    write!(generated, "{}", table).unwrap();
+    writeln!(generated, "];").unwrap();
+}
 
 fn main() {
     // Put `memory.x` in our output directory and ensure it's
@@ -29,6 +362,10 @@ fn main() {
     // `memory.x` is changed.
     println!("cargo:rerun-if-changed=memory.x");
 
+    generate_config(out);
+    generate_build_info(out);
+    generate_schema(out);
+
     println!("cargo:rustc-link-arg-bins=--nmagic");
     println!("cargo:rustc-link-arg-bins=-Tlink.x");
 }
@@ -0,0 +1,100 @@
+//! Random console input through the CLI parser.
+//!
+//! Runs the same byte path as the CLI task: [`commands::normalize`], then the
+//! parser with the firmware's buffer sizes. Every parsed command is
+//! formatted, and prompt changes and notifications are interleaved the way
+//! the task does between bytes, so malformed escape sequences and overlong
+//! lines are checked against a prompt being redrawn.
+
+#![no_main]
+
+use core::convert::Infallible;
+use core::fmt::Write as _;
+
+use embedded_cli::cli::CliBuilder;
+use libfuzzer_sys::fuzz_target;
+
+#[allow(dead_code)]
+#[path = "../../src/commands.rs"]
+mod commands;
+
+use commands::BaseCommand;
+
+/// Same sizes as the CLI task.
+const COMMAND_BUFFER: usize = 40;
+const HISTORY_BUFFER: usize = 41;
+
+/// Prompts of the lengths the CLI task shows, with and without the mute
+/// countdown.
+const PROMPTS: [&str; 3] = [
+    "\x1b[1;32mradio-cli \x1b[1;34mFM \x1b[1;33m87.5 MHz\x1b[1;32m)>\x1b[0m ",
+    concat!(
+        "\x1b[1;32mradio-cli \x1b[1;34mAM \x1b[1;33m1602.0 MHz ",
+        "\x1b[1;31mmute 3600s\x1b[1;32m)>\x1b[0m "
+    ),
+    ">",
+];
+
+/// Byte that switches the prompt instead of being typed.
+const SET_PROMPT: u8 = 0xF5;
+/// Byte that prints a notification instead of being typed.
+const NOTIFY: u8 = 0xF6;
+
+/// Console output, discarded.
+struct Sink;
+
+impl embedded_io::ErrorType for Sink {
+    type Error = Infallible;
+}
+
+impl embedded_io::Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut command_buffer = [0; COMMAND_BUFFER];
+    let mut history_buffer = [0; HISTORY_BUFFER];
+    let mut cli = CliBuilder::default()
+        .writer(Sink)
+        .command_buffer(&mut command_buffer[..])
+        .history_buffer(&mut history_buffer[..])
+        .prompt(PROMPTS[0])
+        .build()
+        .ok()
+        .unwrap();
+    let mut prompt = 0;
+    let mut output = String::new();
+
+    for &byte in data {
+        match byte {
+            SET_PROMPT => {
+                prompt = (prompt + 1) % PROMPTS.len();
+                cli.set_prompt(PROMPTS[prompt]).unwrap();
+            }
+            NOTIFY => {
+                cli.write(|writer| {
+                    write!(writer, "Notification: VolumeChanged(30)").ok();
+                    Ok(())
+                })
+                .unwrap();
+            }
+            _ => {
+                cli.process_byte::<BaseCommand<'_>, _>(
+                    commands::normalize(byte),
+                    &mut BaseCommand::handler(|_cli, command| {
+                        output.clear();
+                        write!(output, "{:?}", command).ok();
+                        Ok(())
+                    }),
+                )
+                .unwrap();
+            }
+        }
+    }
+});
@@ -2,6 +2,7 @@ MEMORY
 {
   /* NOTE 1 K = 1 KiBi = 1024 bytes */
   /* These values correspond to the NRF5340 */
-  FLASH : ORIGIN = 0x00000000, LENGTH = 1024K
+  /* The top 128K (0xE0000..) are reserved for data partitions, see src/storage.rs */
+  FLASH : ORIGIN = 0x00000000, LENGTH = 896K
   RAM : ORIGIN = 0x20000000, LENGTH = 256K
 }
@@ -0,0 +1,179 @@
+//! Wake-up alarm.
+//!
+//! [`alarm_task`] compares the RDS wall clock with the time in
+//! [`AlarmSettings`](crate::settings::AlarmSettings) and, once it is reached,
+//! tunes the chosen station and has the radio loop ramp the volume from
+//! silence to the configured level. The receiver runs FM from boot on and the
+//! radio loop has no mode switching yet, so the alarm does not switch it. The alarm then
+//! rings until it is stopped with `alarm stop` or [`RING_TIMEOUT`] passes.
+//! Snoozing, from the CLI or by pressing any front panel button, silences the
+//! radio for the snooze time and starts the ramp again. The radio is driven
+//! through the [`service`], so other controllers cannot retune between the
+//! alarm's requests.
+//!
+//! Without a received clock time the alarm cannot trigger.
+
+use core::cell::Cell;
+
+use embassy_futures::select::{Either, select};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Timer};
+use log::info;
+
+use crate::clock;
+use crate::events::SystemEvent;
+use crate::monitor;
+use crate::service;
+use crate::settings::{self, AlarmSettings};
+
+/// How often the clock is compared with the alarm time.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// A ringing alarm that is neither stopped nor snoozed ends this long after
+/// the ramp, leaving the radio playing.
+pub const RING_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// What the alarm is doing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum State {
+    Idle,
+    /// Volume is ramping up or the radio is playing.
+    Ringing,
+    /// Silenced until the given instant.
+    Snoozed {
+        until: Instant,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Command {
+    Snooze,
+    Stop,
+}
+
+static STATE: Mutex<ThreadModeRawMutex, Cell<State>> = Mutex::new(Cell::new(State::Idle));
+static COMMAND: Signal<ThreadModeRawMutex, Command> = Signal::new();
+
+pub fn state() -> State {
+    STATE.lock(|state| state.get())
+}
+
+fn set_state(value: State) {
+    STATE.lock(|state| state.set(value));
+}
+
+/// Snooze a ringing alarm. Returns `false` if it is not ringing.
+pub fn snooze() -> bool {
+    if state() != State::Ringing {
+        return false;
+    }
+    COMMAND.signal(Command::Snooze);
+    true
+}
+
+/// Stop a ringing or snoozed alarm. Returns `false` if it is idle.
+pub fn stop() -> bool {
+    if state() == State::Idle {
+        return false;
+    }
+    COMMAND.signal(Command::Stop);
+    true
+}
+
+/// Parse a time of day written as `hh:mm`.
+pub fn parse_time(text: &str) -> Option<(u8, u8)> {
+    let (hour, minute) = text.split_once(':')?;
+    let hour: u8 = hour.parse().ok()?;
+    let minute: u8 = minute.parse().ok()?;
+    (hour < 24 && minute < 60).then_some((hour, minute))
+}
+
+/// Wait until `at` unless a command arrives first.
+async fn wait_until(at: Instant) -> Option<Command> {
+    match select(Timer::at(at), COMMAND.wait()).await {
+        Either::First(_) => None,
+        Either::Second(command) => Some(command),
+    }
+}
+
+/// Ramp the volume up from silence, tuning the alarm station first if `tune`.
+///
+/// Failures are reported by the radio loop; the ramp starts regardless, so
+/// the alarm still rings on whatever station is playing.
+async fn start(alarm: &AlarmSettings, ramp: Duration, tune: bool) {
+    let mut session = service::session().await;
+    if tune
+        && let Some(frequency) = alarm.frequency
+        && let Ok(status) = session.tune(frequency).await
+    {
+        info!("Alarm tuned to {} MHz", status.frequency);
+    }
+    let _ = session.set_volume(0).await;
+    let _ = session
+        .request(SystemEvent::RadioVolumeRamp {
+            level: alarm.volume,
+            duration_ms: ramp.as_millis() as u32,
+        })
+        .await;
+}
+
+/// Ring until stopped or timed out, snoozing on request.
+async fn ring(alarm: AlarmSettings) {
+    COMMAND.reset();
+    let ramp = Duration::from_secs(alarm.ramp_s as u64);
+    let mut tune = true;
+    loop {
+        set_state(State::Ringing);
+        start(&alarm, ramp, tune).await;
+        tune = false;
+        let command = wait_until(Instant::now() + ramp + RING_TIMEOUT).await;
+        if command != Some(Command::Snooze) {
+            break;
+        }
+        info!("Alarm snoozed");
+        let _ = service::request(SystemEvent::RadioVolumeSet(0)).await;
+        let until = Instant::now() + Duration::from_secs(alarm.snooze_min as u64 * 60);
+        set_state(State::Snoozed { until });
+        // Further snoozes while snoozed change nothing.
+        let mut stopped = false;
+        while let Some(command) = wait_until(until).await {
+            if command == Command::Stop {
+                stopped = true;
+                break;
+            }
+        }
+        if stopped {
+            break;
+        }
+    }
+    info!("Alarm ended");
+    set_state(State::Idle);
+}
+
+/// Trigger the alarm when the clock reaches the alarm time.
+#[embassy_executor::task]
+pub async fn alarm_task() {
+    let heartbeat = monitor::register("alarm");
+    // Day the alarm last rang, so it triggers once per day.
+    let mut last_day = None;
+    loop {
+        Timer::after(CHECK_INTERVAL).await;
+        heartbeat.beat();
+        let alarm = settings::get().alarm;
+        let Some(local) = clock::local() else {
+            continue;
+        };
+        let minute_of_day = (local.time.0 / 60_000) as u16;
+        let day = (local.year, local.month, local.day);
+        if !alarm.enabled
+            || minute_of_day != alarm.hour as u16 * 60 + alarm.minute as u16
+            || last_day == Some(day)
+        {
+            continue;
+        }
+        last_day = Some(day);
+        info!("Alarm at {:02}:{:02}", alarm.hour, alarm.minute);
+        ring(alarm).await;
+    }
+}
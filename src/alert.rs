@@ -0,0 +1,107 @@
+//! Emergency alert override.
+//!
+//! Broadcasters flag alarm announcements with RDS programme type 31. When
+//! [`AlertSettings`](crate::settings::AlertSettings) enable the override and
+//! the tuned station switches to that type, the radio loop calls [`raise`],
+//! which latches the alert. [`alert_task`] then unmutes the radio, sets the
+//! alert volume and sounds an alert tone every [`REMIND_INTERVAL`] until the
+//! alert is acknowledged with `alert ack` or a front panel button press. The
+//! tone plays even while confirmation beeps are disabled.
+//!
+//! Alerts are detected on the tuned station only, which therefore already is
+//! the alerting source. Weather band SAME alerts would need a WB receiver,
+//! which this firmware does not bring up.
+
+use core::cell::Cell;
+
+use embassy_futures::select::select;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Timer};
+use log::{info, warn};
+
+use crate::buzzer;
+use crate::config;
+use crate::events::{self, Origin, SystemNotify};
+use crate::frequency::Frequency;
+use crate::monitor;
+use crate::service;
+use crate::settings;
+
+/// RDS programme type of an alarm announcement.
+pub const ALARM_PTY: u8 = 31;
+/// Time between alert tones while the alert is latched.
+pub const REMIND_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A latched emergency alert.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Alert {
+    /// PI code of the alerting station, if known.
+    pub pi: Option<u16>,
+    /// Frequency of the alerting station.
+    pub frequency: Frequency,
+    pub since: Instant,
+}
+
+static LATCHED: Mutex<ThreadModeRawMutex, Cell<Option<Alert>>> = Mutex::new(Cell::new(None));
+/// Wakes the task after [`LATCHED`] changed.
+static CHANGED: Signal<ThreadModeRawMutex, ()> = Signal::new();
+
+/// The latched alert, if any.
+pub fn latched() -> Option<Alert> {
+    LATCHED.lock(|latched| latched.get())
+}
+
+/// Latch an alert from the station at `frequency`, unless the override is
+/// disabled or not built in, or an alert is already latched. Returns whether
+/// it latched.
+pub fn raise(pi: Option<u16>, frequency: Frequency) -> bool {
+    if !config::ALERT || !settings::get().alert.enabled || latched().is_some() {
+        return false;
+    }
+    let alert = Alert {
+        pi,
+        frequency,
+        since: Instant::now(),
+    };
+    LATCHED.lock(|latched| latched.set(Some(alert)));
+    CHANGED.signal(());
+    true
+}
+
+/// Acknowledge the latched alert. Returns `false` if none is latched.
+pub fn acknowledge() -> bool {
+    if LATCHED.lock(|latched| latched.take()).is_none() {
+        return false;
+    }
+    CHANGED.signal(());
+    true
+}
+
+/// Bring up the audio and sound the alert tone while an alert is latched.
+#[embassy_executor::task]
+pub async fn alert_task() {
+    let heartbeat = monitor::register("alert");
+    loop {
+        CHANGED.wait().await;
+        heartbeat.beat();
+        let Some(alert) = latched() else {
+            continue;
+        };
+        warn!("Emergency alert on {} MHz", alert.frequency);
+        events::notify_immediate(Origin::System, SystemNotify::AlertRaised(alert.pi));
+        {
+            let mut session = service::session().await;
+            let _ = session.unmute().await;
+            let _ = session.set_volume(settings::get().alert.volume).await;
+        }
+        while latched().is_some() {
+            buzzer::beep(buzzer::Beep::Alert);
+            select(Timer::after(REMIND_INTERVAL), CHANGED.wait()).await;
+            heartbeat.beat();
+        }
+        info!("Emergency alert acknowledged");
+        events::notify_immediate(Origin::System, SystemNotify::AlertCleared);
+    }
+}
@@ -0,0 +1,228 @@
+//! Command aliases and shortcuts.
+//!
+//! A few single-character [`SHORTCUTS`] are built in, and users can define
+//! their own aliases with `alias <name> <expansion>`; they are kept in
+//! [`UiSettings`](crate::settings::UiSettings). The CLI task follows the line
+//! being typed with a [`Line`] and, when Enter is pressed on a line whose
+//! first word is an alias or shortcut, replaces that word with its expansion
+//! before the parser sees the line: `t 98.5` runs `tune frequency 98.5` after
+//! `alias t "tune frequency"`. User aliases take precedence over shortcuts.
+//!
+//! Expansions are not expanded again, so aliases cannot refer to each other.
+//! Lines recalled from history or completed with Tab are passed through
+//! unchanged.
+//!
+//! In keypad mode, toggled per terminal with `keypad on|off`, a line holding
+//! only a frequency is expanded by [`keypad`] to tune to it.
+
+use core::fmt::Write;
+
+use heapless::String;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::schema;
+
+/// Number of aliases that can be stored.
+pub const MAX_ALIASES: usize = 8;
+/// Longest alias name in bytes.
+pub const MAX_NAME: usize = 8;
+/// Longest expansion in bytes.
+pub const MAX_EXPANSION: usize = 32;
+/// Longest line, the size of the CLI command buffer.
+pub const MAX_LINE: usize = 40;
+
+/// Built-in shortcuts and their expansions.
+pub const SHORTCUTS: [(&str, &str); 4] = [
+    ("u", "tune up"),
+    ("d", "tune down"),
+    ("+", "volume up"),
+    ("-", "volume down"),
+];
+
+const BACKSPACE: u8 = 0x08;
+
+/// A name and the text it stands for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Alias {
+    name: [u8; MAX_NAME],
+    name_len: u8,
+    expansion: [u8; MAX_EXPANSION],
+    expansion_len: u8,
+}
+
+impl Alias {
+    /// Names must be a single word that is not a command, expansions
+    /// printable text.
+    pub fn new(name: &str, expansion: &str) -> Result<Self, Error> {
+        let word = |c: u8| c.is_ascii_graphic() && c != b'"';
+        let expansion = expansion.trim();
+        if name.is_empty()
+            || name.len() > MAX_NAME
+            || !name.bytes().all(word)
+            || schema::COMMANDS.iter().any(|command| command.name == name)
+            || expansion.is_empty()
+            || expansion.len() > MAX_EXPANSION
+            || !expansion.bytes().all(|c| c == b' ' || word(c))
+        {
+            return Err(Error::InvalidArgument);
+        }
+        let mut alias = Self {
+            name: [0; MAX_NAME],
+            name_len: name.len() as u8,
+            expansion: [0; MAX_EXPANSION],
+            expansion_len: expansion.len() as u8,
+        };
+        alias.name[..name.len()].copy_from_slice(name.as_bytes());
+        alias.expansion[..expansion.len()].copy_from_slice(expansion.as_bytes());
+        Ok(alias)
+    }
+
+    pub fn name(&self) -> &str {
+        core::str::from_utf8(&self.name[..self.name_len as usize]).unwrap_or_default()
+    }
+
+    pub fn expansion(&self) -> &str {
+        core::str::from_utf8(&self.expansion[..self.expansion_len as usize]).unwrap_or_default()
+    }
+}
+
+/// User-defined aliases.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Aliases {
+    aliases: [Option<Alias>; MAX_ALIASES],
+}
+
+impl Aliases {
+    pub const fn new() -> Self {
+        Self {
+            aliases: [None; MAX_ALIASES],
+        }
+    }
+
+    /// Add an alias, replacing an existing alias with the same name.
+    pub fn add(&mut self, alias: Alias) -> Result<(), Error> {
+        let index = self
+            .aliases
+            .iter()
+            .position(|slot| slot.is_some_and(|a| a.name() == alias.name()))
+            .or_else(|| self.aliases.iter().position(|slot| slot.is_none()))
+            .ok_or(Error::InvalidArgument)?;
+        self.aliases[index] = Some(alias);
+        Ok(())
+    }
+
+    /// Remove the alias called `name`, returning whether one existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let slot = self
+            .aliases
+            .iter_mut()
+            .find(|slot| slot.is_some_and(|a| a.name() == name));
+        slot.map(|slot| slot.take()).is_some()
+    }
+
+    pub fn aliases(&self) -> impl Iterator<Item = &Alias> {
+        self.aliases.iter().flatten()
+    }
+
+    /// Expansion of `name`, from the user aliases or the shortcuts.
+    pub fn find(&self, name: &str) -> Option<&str> {
+        self.aliases()
+            .find(|alias| alias.name() == name)
+            .map(Alias::expansion)
+            .or_else(|| {
+                SHORTCUTS
+                    .iter()
+                    .find(|(shortcut, _)| *shortcut == name)
+                    .map(|(_, expansion)| *expansion)
+            })
+    }
+
+    /// `line` with its first word expanded, or `None` if that word is not an
+    /// alias. Fails if the expanded line is longer than [`MAX_LINE`].
+    pub fn expand(&self, line: &str) -> Option<Result<String<MAX_LINE>, Error>> {
+        let line = line.trim_start();
+        let (word, rest) = line.split_at(line.find(' ').unwrap_or(line.len()));
+        let expansion = self.find(word)?;
+        let mut expanded = String::new();
+        Some(
+            expanded
+                .push_str(expansion)
+                .and_then(|()| expanded.push_str(rest))
+                .map(|()| expanded)
+                .map_err(|_| Error::InvalidArgument),
+        )
+    }
+}
+
+impl Default for Aliases {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `tune frequency` command for a line holding only a frequency in MHz, such
+/// as `101.7`. Without a decimal point the last digit is tenths of a MHz, so
+/// `1017` also tunes to 101.7 MHz.
+pub fn keypad(line: &str) -> Option<String<MAX_LINE>> {
+    let line = line.trim();
+    let digits = line.bytes().filter(u8::is_ascii_digit).count();
+    let points = line.bytes().filter(|&c| c == b'.').count();
+    if digits == 0 || points > 1 || digits + points != line.len() {
+        return None;
+    }
+    let mut command = String::new();
+    if points == 0 && digits > 1 {
+        let (mhz, tenths) = line.split_at(line.len() - 1);
+        write!(command, "tune frequency {}.{}", mhz, tenths).ok()?;
+    } else {
+        write!(command, "tune frequency {}", line).ok()?;
+    }
+    Some(command)
+}
+
+/// The line being typed, as far as it can be followed from the bytes sent to
+/// the parser.
+pub struct Line {
+    text: String<MAX_LINE>,
+    /// Cleared by editing that is not followed, such as history recall,
+    /// completion and cursor movement, until the line is finished.
+    tracked: bool,
+}
+
+impl Line {
+    pub const fn new() -> Self {
+        Self {
+            text: String::new(),
+            tracked: true,
+        }
+    }
+
+    /// Follow one normalized input byte. Returns the finished line when
+    /// `byte` ends a line that was followed throughout.
+    pub fn feed(&mut self, byte: u8) -> Option<String<MAX_LINE>> {
+        match byte {
+            b'\r' | b'\n' => {
+                let line = core::mem::take(&mut self.text);
+                let tracked = core::mem::replace(&mut self.tracked, true);
+                return tracked.then_some(line);
+            }
+            BACKSPACE => {
+                self.text.pop();
+            }
+            b' '..=b'~' => {
+                if self.text.push(byte as char).is_err() {
+                    self.tracked = false;
+                }
+            }
+            _ => self.tracked = false,
+        }
+        None
+    }
+}
+
+impl Default for Line {
+    fn default() -> Self {
+        Self::new()
+    }
+}
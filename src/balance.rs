@@ -0,0 +1,74 @@
+//! Left/right balance.
+//!
+//! The Si47xx has a single volume for both channels, so the balance is
+//! applied through the per-channel bits of `RX_HARD_MUTE`: the channel away
+//! from the balance is muted for a share of every [`PERIOD`] proportional to
+//! the balance, which lowers its average level. At the ends of the range the
+//! channel stays muted and at the center nothing is muted, so no switching
+//! takes place. Like a volume [`Ramp`](crate::ramp::Ramp) the modulation is
+//! owned by the radio loop, which wakes up at [`Balance::next_toggle`] and
+//! writes the bits returned by [`Balance::toggle`] while audio is not muted.
+
+use embassy_time::{Duration, Instant};
+
+/// Balance moving the sound fully to one channel.
+pub const MAX: i8 = 10;
+
+/// Period of the mute modulation.
+const PERIOD: Duration = Duration::from_millis(50);
+/// `RX_HARD_MUTE` bits of the left and right channel.
+const MUTE_LEFT: u16 = 0b10;
+const MUTE_RIGHT: u16 = 0b01;
+
+/// Mute modulation for a balance from `-MAX` (left) to `MAX` (right).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Balance {
+    level: i8,
+    /// Whether the attenuated channel is muted in the current phase.
+    muted: bool,
+    next: Instant,
+}
+
+impl Balance {
+    pub fn new(level: i8) -> Self {
+        let level = level.clamp(-MAX, MAX);
+        Self {
+            level,
+            muted: level != 0,
+            next: Instant::now(),
+        }
+    }
+
+    /// `RX_HARD_MUTE` bits of the channel away from the balance.
+    fn channel(&self) -> u16 {
+        if self.level > 0 {
+            MUTE_LEFT
+        } else {
+            MUTE_RIGHT
+        }
+    }
+
+    /// `RX_HARD_MUTE` value of the current phase.
+    pub fn bits(&self) -> u16 {
+        if self.muted { self.channel() } else { 0 }
+    }
+
+    /// When [`toggle`](Self::toggle) should be called next, if the balance
+    /// needs switching at all.
+    pub fn next_toggle(&self) -> Option<Instant> {
+        (self.level != 0 && self.level.abs() != MAX).then_some(self.next)
+    }
+
+    /// Move to the next phase, returning its `RX_HARD_MUTE` value.
+    pub fn toggle(&mut self, now: Instant) -> u16 {
+        self.muted = !self.muted;
+        let muted_share = PERIOD * self.level.unsigned_abs() as u32 / MAX as u32;
+        self.next = now
+            + if self.muted {
+                muted_share
+            } else {
+                PERIOD - muted_share
+            };
+        self.bits()
+    }
+}
@@ -0,0 +1,122 @@
+//! Band plan.
+//!
+//! Named [`Segment`]s of the bands the tuner covers: the FM and medium wave
+//! broadcast bands of the configured region, the shortwave broadcast bands
+//! by their metre names and the amateur bands. `band goto` jumps to the
+//! start of a segment.
+//!
+//! The 60 m name belongs to the tropical broadcast band and the 15 m name to
+//! the amateur band; the amateur 60 m channels and the minor 18.9 MHz
+//! broadcast band are left out.
+
+use crate::config::{self, Region};
+use crate::frequency::Frequency;
+use crate::scan::Band;
+
+/// What a segment is used for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Kind {
+    Broadcast,
+    Amateur,
+}
+
+/// A named frequency range.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub name: &'static str,
+    pub band: Band,
+    pub kind: Kind,
+    pub low: Frequency,
+    pub high: Frequency,
+}
+
+impl Segment {
+    const fn broadcast(name: &'static str, low_khz: u32, high_khz: u32) -> Self {
+        Self {
+            name,
+            band: Band::Sw,
+            kind: Kind::Broadcast,
+            low: Frequency::from_khz(low_khz),
+            high: Frequency::from_khz(high_khz),
+        }
+    }
+
+    const fn amateur(name: &'static str, low_khz: u32, high_khz: u32) -> Self {
+        Self {
+            name,
+            band: Band::Sw,
+            kind: Kind::Amateur,
+            low: Frequency::from_khz(low_khz),
+            high: Frequency::from_khz(high_khz),
+        }
+    }
+
+    /// Tuning step, in kHz.
+    pub const fn step_khz(&self) -> u32 {
+        match self.band {
+            Band::Fm => config::REGION.fm_step_khz(),
+            Band::Am => MW_STEP_KHZ,
+            Band::Sw => SW_STEP_KHZ,
+        }
+    }
+}
+
+/// Medium wave band limits of the configured region, in kHz.
+const MW_KHZ: (u32, u32) = match config::REGION {
+    Region::Eu | Region::Japan => (531, 1602),
+    Region::Us => (530, 1700),
+};
+/// Medium wave channel raster of the configured region, in kHz.
+const MW_STEP_KHZ: u32 = match config::REGION {
+    Region::Eu | Region::Japan => 9,
+    Region::Us => 10,
+};
+/// Shortwave broadcast raster, in kHz.
+const SW_STEP_KHZ: u32 = 5;
+
+/// All segments, in frequency order within each band.
+pub static SEGMENTS: [Segment; 24] = [
+    Segment {
+        name: "fm",
+        band: Band::Fm,
+        kind: Kind::Broadcast,
+        low: config::REGION.fm_band().0,
+        high: config::REGION.fm_band().1,
+    },
+    Segment {
+        name: "mw",
+        band: Band::Am,
+        kind: Kind::Broadcast,
+        low: Frequency::from_khz(MW_KHZ.0),
+        high: Frequency::from_khz(MW_KHZ.1),
+    },
+    Segment::amateur("160m", 1810, 2000),
+    Segment::broadcast("120m", 2300, 2495),
+    Segment::broadcast("90m", 3200, 3400),
+    Segment::amateur("80m", 3500, 3800),
+    Segment::broadcast("75m", 3900, 4000),
+    Segment::broadcast("60m", 4750, 5060),
+    Segment::broadcast("49m", 5900, 6200),
+    Segment::amateur("40m", 7000, 7200),
+    Segment::broadcast("41m", 7200, 7450),
+    Segment::broadcast("31m", 9400, 9900),
+    Segment::amateur("30m", 10100, 10150),
+    Segment::broadcast("25m", 11600, 12100),
+    Segment::broadcast("22m", 13570, 13870),
+    Segment::amateur("20m", 14000, 14350),
+    Segment::broadcast("19m", 15100, 15800),
+    Segment::broadcast("16m", 17480, 17900),
+    Segment::amateur("17m", 18068, 18168),
+    Segment::amateur("15m", 21000, 21450),
+    Segment::broadcast("13m", 21450, 21850),
+    Segment::amateur("12m", 24890, 24990),
+    Segment::broadcast("11m", 25670, 26100),
+    Segment::amateur("10m", 28000, 29700),
+];
+
+/// Look up a segment by its name, ignoring case.
+pub fn find(name: &str) -> Option<&'static Segment> {
+    SEGMENTS
+        .iter()
+        .find(|segment| segment.name.eq_ignore_ascii_case(name))
+}
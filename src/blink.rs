@@ -0,0 +1,76 @@
+//! Blink codes for early boot failures.
+//!
+//! Until the CLI task runs, a failure during startup is only visible in the
+//! log, which needs a probe or a terminal attached. `main` therefore also
+//! shows it on the status LED as a [`BootFailure`] code: a group of short
+//! blinks, the count telling the failure apart, and a long pause. A stuck
+//! bus halts the boot with the code repeating forever; a tuner that does not
+//! respond blinks its code while `main` waits to retry it, and corrupt
+//! settings are shown for a few rounds before the boot carries on with the
+//! defaults.
+
+use embassy_time::Timer;
+use log::error;
+
+use crate::led::StatusLed;
+
+const ON_MS: u64 = 200;
+const OFF_MS: u64 = 300;
+/// Pause between two groups of blinks.
+const PAUSE_MS: u64 = 1500;
+
+/// Startup failures with a blink code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BootFailure {
+    /// SDA or SCL is held low, so the TWIM cannot reach the tuner.
+    Bus,
+    /// The tuner did not respond to power-up or mode commands; retried.
+    Radio,
+    /// The stored settings could not be read; defaults are used.
+    Settings,
+}
+
+impl BootFailure {
+    /// Blinks in each group.
+    pub const fn blinks(self) -> u8 {
+        match self {
+            BootFailure::Bus => 2,
+            BootFailure::Radio => 3,
+            BootFailure::Settings => 4,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            BootFailure::Bus => "I2C bus stuck",
+            BootFailure::Radio => "radio not responding",
+            BootFailure::Settings => "settings corrupt",
+        }
+    }
+}
+
+/// Blink the code of `failure` `rounds` times.
+pub async fn show(led: &mut StatusLed, failure: BootFailure, rounds: u8) {
+    for _ in 0..rounds {
+        for _ in 0..failure.blinks() {
+            led.set(true);
+            Timer::after_millis(ON_MS).await;
+            led.set(false);
+            Timer::after_millis(OFF_MS).await;
+        }
+        Timer::after_millis(PAUSE_MS).await;
+    }
+}
+
+/// Blink the code of `failure` until the future is dropped.
+pub async fn repeat(led: &mut StatusLed, failure: BootFailure) -> ! {
+    loop {
+        show(led, failure, 1).await;
+    }
+}
+
+/// Stop booting and blink the code of `failure` until reset.
+pub async fn halt(led: &mut StatusLed, failure: BootFailure) -> ! {
+    error!("Boot halted: {}", failure.name());
+    repeat(led, failure).await
+}
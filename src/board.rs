@@ -0,0 +1,109 @@
+//! Board revision straps.
+//!
+//! Boards identify themselves with two strap pins, P1.11 and P1.12, read
+//! with pull-ups at boot: a resistor to ground reads as 0, an unfitted strap
+//! as 1. [`detect`] maps the strap value to a [`Board`], which tells `main`
+//! where the status LED sits and which input and indicator hardware to set
+//! up, so one firmware image runs on every revision. Boards without straps
+//! read `0b11` and are the original hand-wired prototype.
+
+use core::cell::Cell;
+use core::fmt;
+
+use embassy_nrf::Peri;
+use embassy_nrf::gpio::{AnyPin, Input, Pull};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_time::Timer;
+use log::{info, warn};
+
+/// Features and pin map of one board revision.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Board {
+    pub name: &'static str,
+    /// Strap value read at boot.
+    pub id: u8,
+    /// Status LED on P0.29 instead of P0.28.
+    pub led_alt: bool,
+    /// Mechanical volume and seek buttons.
+    pub buttons: bool,
+    /// Rotary encoder on P1.06 and P1.07.
+    pub encoder: bool,
+    /// Capacitive touch pads instead of buttons; needs the `touch` feature.
+    pub touch: bool,
+    /// Connector for the WS2812 signal strength strip.
+    pub strip: bool,
+}
+
+/// Hand-wired prototype, also used for boards without straps.
+const PROTO: Board = Board {
+    name: "prototype",
+    id: 0b11,
+    led_alt: false,
+    buttons: true,
+    encoder: true,
+    touch: false,
+    strip: true,
+};
+
+/// Small PCB with buttons only.
+const COMPACT: Board = Board {
+    name: "compact",
+    id: 0b10,
+    led_alt: true,
+    buttons: true,
+    encoder: false,
+    touch: false,
+    strip: false,
+};
+
+/// Front panel PCB with touch pads and an encoder.
+const PANEL: Board = Board {
+    name: "panel",
+    id: 0b01,
+    led_alt: true,
+    buttons: false,
+    encoder: true,
+    touch: true,
+    strip: true,
+};
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (straps {:02b})", self.name, self.id)
+    }
+}
+
+static BOARD: Mutex<ThreadModeRawMutex, Cell<Board>> = Mutex::new(Cell::new(PROTO));
+
+/// The board detected at boot.
+pub fn get() -> Board {
+    BOARD.lock(|board| board.get())
+}
+
+/// Read the straps and remember the board. Call once, before the pins of
+/// the board-dependent peripherals are configured.
+pub async fn detect(strap0: Peri<'static, AnyPin>, strap1: Peri<'static, AnyPin>) -> Board {
+    let strap0 = Input::new(strap0, Pull::Up);
+    let strap1 = Input::new(strap1, Pull::Up);
+    // Let the pull-ups charge the strap traces.
+    Timer::after_micros(100).await;
+    let id = (strap1.is_high() as u8) << 1 | strap0.is_high() as u8;
+    // Dropping the inputs disconnects the pull-ups from grounded straps.
+    drop((strap0, strap1));
+    let board = match id {
+        0b11 => PROTO,
+        0b10 => COMPACT,
+        0b01 => PANEL,
+        _ => {
+            warn!("Unknown board straps {:02b}, assuming {}", id, PROTO.name);
+            Board { id, ..PROTO }
+        }
+    };
+    info!("Board: {}", board);
+    if board.touch && !cfg!(feature = "touch") {
+        warn!("Board has touch pads but touch input is not enabled in this build");
+    }
+    BOARD.lock(|cell| cell.set(board));
+    board
+}
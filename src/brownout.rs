@@ -0,0 +1,99 @@
+//! Power failure warning.
+//!
+//! The power failure comparator raises `POFWARN` when the supply drops below
+//! [`THRESHOLD`], typically some milliseconds before the brown-out reset on a
+//! discharging battery or after the plug is pulled. [`brownout_task`] then
+//! asks the radio loop to mute, so the audio does not crackle while the
+//! supply collapses, and writes pending settings and unsaved statistics at
+//! once instead of letting a later commit race the reset and leave a torn
+//! record behind. Only records that fit into an erased slot are written: a
+//! page erase takes longer than the supply is likely to last, and the
+//! previous copy is still intact if it is skipped.
+//!
+//! If the firmware is still running after [`HOLDOFF`], the supply has
+//! recovered: audio is unmuted unless it was muted before the warning, and
+//! the warning is re-armed.
+
+use embassy_nrf::interrupt::typelevel::{Binding, CLOCK_POWER, Handler, Interrupt};
+use embassy_nrf::pac;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Timer};
+use log::{info, warn};
+
+use crate::events::{self, Origin, SystemEvent};
+use crate::monitor;
+use crate::settings;
+use crate::state;
+use crate::stats;
+
+/// Supply voltage raising the warning.
+const THRESHOLD: pac::regulators::vals::Threshold = pac::regulators::vals::Threshold::V28;
+/// Time after a warning until the supply is considered recovered.
+const HOLDOFF: Duration = Duration::from_secs(2);
+
+static WARNING: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Handles the POFWARN event of the shared CLOCK/POWER interrupt.
+pub struct InterruptHandler;
+
+impl Handler<CLOCK_POWER> for InterruptHandler {
+    unsafe fn on_interrupt() {
+        let power = pac::POWER;
+        if power.events_pofwarn().read() != 0 {
+            power.events_pofwarn().write_value(0);
+            // Disarmed until the task has flushed.
+            power.intenclr().write(|w| w.set_pofwarn(true));
+            WARNING.signal(());
+        }
+    }
+}
+
+fn arm() {
+    let power = pac::POWER;
+    power.events_pofwarn().write_value(0);
+    power.intenset().write(|w| w.set_pofwarn(true));
+}
+
+/// Enable the power failure comparator and its interrupt.
+pub fn init(_irq: impl Binding<CLOCK_POWER, InterruptHandler>) {
+    pac::REGULATORS.pofcon().write(|w| {
+        w.set_pof(true);
+        w.set_threshold(THRESHOLD);
+    });
+    arm();
+    CLOCK_POWER::unpend();
+    // SAFETY: the handler is bound and only touches POFWARN.
+    unsafe { CLOCK_POWER::enable() };
+}
+
+/// Mute and flush pending flash writes on a power failure warning.
+#[embassy_executor::task]
+pub async fn brownout_task() {
+    let heartbeat = monitor::register("brownout");
+    loop {
+        WARNING.wait().await;
+        heartbeat.beat();
+        // A mute of the user's, timed or not, is left alone.
+        let muted = !state::get().muted;
+        if muted {
+            events::event_try_send(Origin::System, SystemEvent::RadioMute);
+        }
+        match settings::flush_without_erase() {
+            Ok(true) => info!("Supply low, settings written"),
+            Ok(false) => {}
+            Err(err) => warn!("Supply low, failed to save settings: {}", err),
+        }
+        match stats::flush_without_erase() {
+            Ok(true) => info!("Supply low, statistics written"),
+            Ok(false) => {}
+            Err(err) => warn!("Supply low, failed to save statistics: {}", err),
+        }
+        Timer::after(HOLDOFF).await;
+        info!("Supply recovered");
+        if muted {
+            events::event_send(Origin::System, SystemEvent::RadioUnmute).await;
+        }
+        arm();
+    }
+}
@@ -0,0 +1,104 @@
+//! Fixed-size buffer overflow accounting.
+//!
+//! Text and records are collected in `heapless` buffers of fixed size. A
+//! `write!` into a full `heapless::String` drops the whole piece that did not
+//! fit and the error used to be ignored, so overflows went unnoticed.
+//! [`Truncate`] instead keeps what fits, cut at a character boundary, and
+//! every overflow is counted per [`Buffer`]. `status buffers` shows the
+//! counters.
+//!
+//! The counters are atomics because log lines are also formatted in
+//! interrupt context.
+
+use core::fmt;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// A fixed-size buffer whose overflows are counted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Buffer {
+    /// CLI prompt.
+    Prompt,
+    /// Log line formatted in interrupt context.
+    LogLine,
+    /// NMEA sentence from the GPS module.
+    GpsSentence,
+    /// Updates decoded from one RDS group.
+    RdsUpdates,
+    /// Stations found by a scan.
+    ScanResults,
+    /// Task monitor registry.
+    Tasks,
+}
+
+impl Buffer {
+    pub const ALL: [Buffer; 6] = [
+        Buffer::Prompt,
+        Buffer::LogLine,
+        Buffer::GpsSentence,
+        Buffer::RdsUpdates,
+        Buffer::ScanResults,
+        Buffer::Tasks,
+    ];
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Buffer::Prompt => "prompt",
+            Buffer::LogLine => "log line",
+            Buffer::GpsSentence => "GPS sentence",
+            Buffer::RdsUpdates => "RDS updates",
+            Buffer::ScanResults => "scan results",
+            Buffer::Tasks => "tasks",
+        }
+    }
+}
+
+static OVERFLOWS: [AtomicU32; Buffer::ALL.len()] = [const { AtomicU32::new(0) }; Buffer::ALL.len()];
+
+/// Count an overflow of `buffer`.
+pub fn overflowed(buffer: Buffer) {
+    OVERFLOWS[buffer as usize].fetch_add(1, Ordering::Relaxed);
+}
+
+/// Overflows of `buffer` since boot.
+pub fn overflows(buffer: Buffer) -> u32 {
+    OVERFLOWS[buffer as usize].load(Ordering::Relaxed)
+}
+
+/// Writer into a `heapless::String` that keeps what fits when it is full and
+/// counts the overflow once.
+pub struct Truncate<'a, const N: usize> {
+    string: &'a mut heapless::String<N>,
+    buffer: Buffer,
+    truncated: bool,
+}
+
+impl<'a, const N: usize> Truncate<'a, N> {
+    pub fn new(string: &'a mut heapless::String<N>, buffer: Buffer) -> Self {
+        Self {
+            string,
+            buffer,
+            truncated: false,
+        }
+    }
+}
+
+impl<const N: usize> fmt::Write for Truncate<'_, N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.truncated {
+            return Ok(());
+        }
+        let room = N - self.string.len();
+        if s.len() <= room {
+            let _ = self.string.push_str(s);
+            return Ok(());
+        }
+        let mut end = room;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        let _ = self.string.push_str(&s[..end]);
+        self.truncated = true;
+        overflowed(self.buffer);
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+//! Shared I2C bus.
+//!
+//! The TWIM peripheral is owned by an async mutex so the tuner and any other
+//! I2C peripherals (display, audio codec, sensors) can each hold an
+//! [`I2cDevice`] and take turns on the bus. A transaction holds the mutex for
+//! its whole duration, so devices never interleave bytes.
+
+use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice;
+use embassy_nrf::Peri;
+use embassy_nrf::gpio::{Input, Pin, Pull};
+use embassy_nrf::twim::Twim;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::mutex::Mutex;
+use embassy_time::Timer;
+use static_cell::StaticCell;
+
+/// The bus all I2C devices are created from.
+pub type I2cBus = Mutex<ThreadModeRawMutex, Twim<'static>>;
+/// Handle used by one device on the shared bus.
+pub type SharedI2c = I2cDevice<'static, ThreadModeRawMutex, Twim<'static>>;
+
+static BUS: StaticCell<I2cBus> = StaticCell::new();
+
+/// Whether SDA and SCL idle high with the pull-ups on. A line held low by a
+/// short or a hung device fails every transfer, so this is checked before
+/// the TWIM takes the pins.
+pub async fn idle(sda: Peri<'_, impl Pin>, scl: Peri<'_, impl Pin>) -> bool {
+    let sda = Input::new(sda, Pull::Up);
+    let scl = Input::new(scl, Pull::Up);
+    // Let the pull-ups charge the bus lines.
+    Timer::after_micros(100).await;
+    sda.is_high() && scl.is_high()
+}
+
+/// Take over the TWIM peripheral. Call once during startup.
+pub fn init(twim: Twim<'static>) -> &'static I2cBus {
+    BUS.init(Mutex::new(twim))
+}
+
+/// Create a device handle on `bus`.
+pub fn device(bus: &'static I2cBus) -> SharedI2c {
+    I2cDevice::new(bus)
+}
@@ -0,0 +1,91 @@
+//! Piezo buzzer feedback.
+//!
+//! Short tone patterns confirm key events: a rising double beep when a seek
+//! completes, a low beep when a radio operation fails, and a single short
+//! beep for confirmations such as storing a preset, and a two-tone alert
+//! while an emergency alert is latched. Callers queue a [`Beep`]
+//! with [`beep`]; [`buzzer_task`] plays it on a PWM channel so the caller
+//! never waits for the tone. Beeps are dropped while disabled in
+//! [`BeepSettings`](crate::settings::BeepSettings) or when the queue is full.
+
+use embassy_nrf::pwm::{DutyCycle, SimplePwm};
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_time::Timer;
+
+use crate::monitor;
+use crate::settings;
+
+/// Highest volume step.
+pub const MAX_VOLUME: u8 = 10;
+
+/// Feedback patterns.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Beep {
+    /// A setting or preset was stored.
+    Confirm,
+    /// A seek found a station.
+    Seek,
+    /// A radio operation failed.
+    Error,
+    /// An emergency alert is latched; plays even while beeps are disabled.
+    Alert,
+}
+
+/// Tone frequency in Hz (0 for a pause) and duration in ms.
+type Tone = (u32, u64);
+
+const CONFIRM: &[Tone] = &[(2000, 60)];
+const SEEK: &[Tone] = &[(1500, 40), (0, 40), (2000, 40)];
+const ERROR: &[Tone] = &[(400, 250)];
+const ALERT: &[Tone] = &[
+    (2500, 150),
+    (0, 100),
+    (1800, 150),
+    (0, 100),
+    (2500, 150),
+    (0, 100),
+    (1800, 150),
+];
+
+impl Beep {
+    const fn tones(&self) -> &'static [Tone] {
+        match self {
+            Beep::Confirm => CONFIRM,
+            Beep::Seek => SEEK,
+            Beep::Error => ERROR,
+            Beep::Alert => ALERT,
+        }
+    }
+}
+
+static BEEPS: Channel<ThreadModeRawMutex, Beep, 4> = Channel::new();
+
+/// Queue `beep` if beeps are enabled.
+pub fn beep(beep: Beep) {
+    if beep == Beep::Alert || settings::get().beep.enabled {
+        let _ = BEEPS.try_send(beep);
+    }
+}
+
+/// Play queued beeps on channel 0 of `pwm`.
+#[embassy_executor::task]
+pub async fn buzzer_task(mut pwm: SimplePwm<'static>) {
+    let heartbeat = monitor::register("buzzer");
+    pwm.set_duty(0, DutyCycle::normal(0));
+    loop {
+        let beep = BEEPS.receive().await;
+        heartbeat.beat();
+        let volume = settings::get().beep.volume.min(MAX_VOLUME) as u32;
+        for &(frequency, duration) in beep.tones() {
+            if frequency > 0 {
+                pwm.set_period(frequency);
+                // A piezo is loudest at 50 % duty.
+                let duty = pwm.max_duty() as u32 * volume / (2 * MAX_VOLUME as u32);
+                pwm.set_duty(0, DutyCycle::normal(duty as u16));
+            }
+            Timer::after_millis(duration).await;
+            pwm.set_duty(0, DutyCycle::normal(0));
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! Frequency calibration.
+//!
+//! Boards with an off-spec reference clock tune slightly beside the frequency
+//! the chip reports: it shows 100.00 MHz while the station heard sits at
+//! 100.03 MHz. A signed offset per [`Band`], in kHz, is kept in
+//! [`CalibrationSettings`](crate::settings::CalibrationSettings) and applied
+//! where the radio loop talks to the tuner: [`to_chip`] corrects commanded
+//! frequencies and [`from_chip`] the tune status the chip reports, so the
+//! display, the RDS database and everything else above the driver see true
+//! frequencies.
+//!
+//! The offset is the true frequency minus the one the chip reports. FM
+//! frequencies are tuned in 10 kHz steps, so FM offsets are rounded to that
+//! when tuning. A changed offset applies from the next tune on.
+//!
+//! This is also where frequencies change between the driver's `f32` MHz and
+//! the integer [`Frequency`] used everywhere else.
+
+use si473x::Si47xxTuneStatus;
+
+use crate::frequency::{Frequency, TuneStatus};
+use crate::scan::Band;
+use crate::settings;
+
+/// Largest offset accepted, in kHz either way.
+pub const MAX_OFFSET_KHZ: i16 = 100;
+
+fn offset_khz(band: Band) -> i32 {
+    settings::get().calibration.offset_khz(band) as i32
+}
+
+/// Frequency in the driver's unit: MHz for FM, kHz for AM and SW.
+fn driver_unit(band: Band, frequency: Frequency) -> f32 {
+    match band {
+        Band::Fm => frequency.mhz_f32(),
+        Band::Am | Band::Sw => frequency.khz() as f32,
+    }
+}
+
+/// Frequency to command the chip with to receive `frequency` on `band`.
+pub fn to_chip(band: Band, frequency: Frequency) -> f32 {
+    driver_unit(band, frequency.offset(-offset_khz(band)))
+}
+
+/// Tune status reported by the chip on `band`, with the true frequency.
+pub fn from_chip(band: Band, status: Si47xxTuneStatus) -> TuneStatus {
+    let reported = match band {
+        Band::Fm => Frequency::from_mhz_f32(status.frequency),
+        Band::Am | Band::Sw => Frequency::from_khz((status.frequency + 0.5) as u32),
+    };
+    TuneStatus {
+        frequency: reported.offset(offset_khz(band)),
+        rssi: status.rssi,
+        snr: status.snr,
+        multipath: status.multipath,
+        valid: status.valid,
+    }
+}
@@ -0,0 +1,188 @@
+//! Console session capture to flash.
+//!
+//! While capture is enabled, every byte written to the console is also
+//! pushed into a RAM pipe by [`tee`]. [`capture_task`] drains the pipe and
+//! appends it to the [`storage::CAPTURE`] partition in word-aligned chunks, so
+//! the console itself never waits for flash. Unused bytes of a chunk are left
+//! erased (`0xFF`) and skipped by [`dump`]. Capture stops by itself when the
+//! partition is full.
+
+use core::cell::Cell;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use embassy_futures::select::{Either, select};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::{CriticalSectionRawMutex, ThreadModeRawMutex};
+use embassy_sync::pipe::Pipe;
+use embassy_time::{Duration, Timer};
+use log::warn;
+
+use crate::error::Error;
+use crate::monitor;
+use crate::storage;
+use crate::transfer::Source;
+
+const CHUNK_SIZE: usize = 256;
+/// Longest time console output waits in RAM before it is written.
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+const ERASED: u8 = 0xFF;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static DROPPED: AtomicU32 = AtomicU32::new(0);
+static PIPE: Pipe<CriticalSectionRawMutex, 1024> = Pipe::new();
+static OFFSET: Mutex<ThreadModeRawMutex, Cell<u32>> = Mutex::new(Cell::new(0));
+
+/// Whether console output is currently being captured.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Bytes lost because the pipe was full since capture was started.
+pub fn dropped() -> u32 {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+/// Bytes stored in flash by the current session.
+pub fn stored() -> u32 {
+    OFFSET.lock(|offset| offset.get())
+}
+
+/// Copy console output into the capture pipe. Called by the console writer.
+pub fn tee(buf: &[u8]) {
+    if !is_enabled() {
+        return;
+    }
+    let written = PIPE.try_write(buf).unwrap_or(0);
+    if written < buf.len() {
+        DROPPED.fetch_add((buf.len() - written) as u32, Ordering::Relaxed);
+    }
+}
+
+/// Erase the previous capture and start a new session.
+pub fn start() -> Result<(), Error> {
+    ENABLED.store(false, Ordering::Relaxed);
+    PIPE.clear();
+    storage::CAPTURE.erase_all()?;
+    OFFSET.lock(|offset| offset.set(0));
+    DROPPED.store(0, Ordering::Relaxed);
+    ENABLED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Stop capturing. Buffered output is still flushed to flash.
+pub fn stop() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+/// Call `f` with the captured output, in chunks.
+///
+/// Reads until the first fully erased chunk, so a capture taken before a
+/// reboot can still be replayed.
+pub fn dump(mut f: impl FnMut(&[u8])) -> Result<(), Error> {
+    let mut chunk = [0u8; CHUNK_SIZE];
+    for offset in (0..storage::CAPTURE.size).step_by(CHUNK_SIZE) {
+        storage::CAPTURE.read(offset, &mut chunk)?;
+        if chunk.iter().all(|&b| b == ERASED) {
+            break;
+        }
+        chunk
+            .split(|&b| b == ERASED)
+            .filter(|text| !text.is_empty())
+            .for_each(&mut f);
+    }
+    Ok(())
+}
+
+/// Pull-style reader over the captured output, for file transfers.
+///
+/// Yields the same bytes as [`dump`].
+pub struct Reader {
+    offset: u32,
+    chunk: [u8; CHUNK_SIZE],
+    position: usize,
+    done: bool,
+}
+
+impl Reader {
+    pub const fn new() -> Self {
+        Self {
+            offset: 0,
+            chunk: [ERASED; CHUNK_SIZE],
+            position: CHUNK_SIZE,
+            done: false,
+        }
+    }
+}
+
+impl Default for Reader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Source for Reader {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut len = 0;
+        while len < buf.len() {
+            if self.position == CHUNK_SIZE {
+                if self.done || self.offset >= storage::CAPTURE.size {
+                    break;
+                }
+                storage::CAPTURE.read(self.offset, &mut self.chunk)?;
+                self.offset += CHUNK_SIZE as u32;
+                if self.chunk.iter().all(|&b| b == ERASED) {
+                    self.done = true;
+                    break;
+                }
+                self.position = 0;
+            }
+            let byte = self.chunk[self.position];
+            self.position += 1;
+            if byte != ERASED {
+                buf[len] = byte;
+                len += 1;
+            }
+        }
+        Ok(len)
+    }
+}
+
+fn flush(chunk: &mut [u8; CHUNK_SIZE], len: usize) -> Result<(), Error> {
+    let padded = len.next_multiple_of(4);
+    let offset = stored();
+    if offset + padded as u32 > storage::CAPTURE.size {
+        stop();
+        warn!("Capture partition full, capture stopped");
+        return Ok(());
+    }
+    storage::CAPTURE.write(offset, &chunk[..padded])?;
+    OFFSET.lock(|o| o.set(offset + padded as u32));
+    chunk.fill(ERASED);
+    Ok(())
+}
+
+/// Move captured console output from RAM to flash.
+#[embassy_executor::task]
+pub async fn capture_task() {
+    let heartbeat = monitor::register("capture");
+    let mut chunk = [ERASED; CHUNK_SIZE];
+    let mut len = 0;
+    loop {
+        let timed_out =
+            match select(PIPE.read(&mut chunk[len..]), Timer::after(FLUSH_INTERVAL)).await {
+                Either::First(read) => {
+                    len += read;
+                    false
+                }
+                Either::Second(_) => true,
+            };
+        heartbeat.beat();
+        if len == CHUNK_SIZE || (timed_out && len > 0) {
+            if let Err(err) = flush(&mut chunk, len) {
+                stop();
+                warn!("Capture write failed: {}", err);
+            }
+            len = 0;
+        }
+    }
+}
@@ -1,83 +1,160 @@
-use crate::console;
+use crate::alarm;
+use crate::alert;
+use crate::alias;
+use crate::balance;
+use crate::bandplan;
+use crate::board;
+use crate::buffers::{self, Buffer};
+use crate::buzzer;
+use crate::calibration;
+use crate::capture;
+use crate::clock::{self, DstRule, TimeZone};
+use crate::commands::{self, *};
+use crate::config;
+use crate::console::console_colors::{BOLD_RED, RESET};
+use crate::console::{self, Terminal};
+use crate::error::Error;
 use crate::events;
 use crate::events::SystemEvent;
-use crate::events::SystemNotify;
+use crate::events::{Delivery, Received};
+use crate::events::{Operation, Origin, SystemNotify};
+use crate::frequency::Frequency;
+use crate::gps;
+use crate::health::{self, Counter};
+use crate::i2c_trace;
+use crate::i18n::{self, Language, Text};
+use crate::interactive::{self, Key};
+use crate::kiosk;
+use crate::led;
+use crate::log_filter;
+use crate::monitor;
+use crate::mute;
+use crate::patch;
+use crate::poller;
+use crate::probe;
+use crate::properties::Profile;
+use crate::rds_db;
+use crate::reset;
+use crate::scan;
+use crate::schema;
+use crate::seek;
+use crate::serial_logger::{self, TimestampFormat};
+use crate::service;
+use crate::settings;
+use crate::sleep;
+use crate::stack;
+use crate::startup;
+use crate::state::{self, RadioState};
+use crate::stats;
+use crate::stereo;
+use crate::storage;
+use crate::survey;
+use crate::telemetry;
+use crate::touch;
+use crate::trace;
+use crate::transfer;
+use crate::tune_lock;
+use crate::version;
+use crate::volume::VolumeCurve;
 use core::cell::Cell;
 use core::fmt::{Debug, Write};
+use core::future::pending;
 use core::marker::PhantomData;
-use embassy_futures::select::{Either, select};
+use embassy_futures::select::{Either, Either4, select, select4};
 use embassy_nrf::uarte;
+use embassy_time::{Duration, Instant, Timer, with_timeout};
 use embedded_cli::cli::CliBuilder;
-use embedded_cli::{Command, codes};
-
-pub const DEL: u8 = 127; // Delete character
-
-#[derive(Debug, Command)]
-enum BaseCommand {
-    Mode {
-        #[command(subcommand)]
-        command: RadioMode,
-    },
-    Volume {
-        #[command(subcommand)]
-        command: VolumeCommand,
-    },
-    Tune {
-        #[command(subcommand)]
-        command: TuneCommand,
-    },
-    /// Show some status
-    Status,
+use embedded_cli::codes;
+
+/// Seconds announced before switching the baud rate.
+const BAUD_COUNTDOWN_S: u64 = 3;
+/// Time to confirm a new baud rate before the old one is restored.
+const BAUD_CONFIRM_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Switch the console to `rate` and keep it only if Enter is pressed at the
+/// new rate within [`BAUD_CONFIRM_TIMEOUT`], so a terminal that cannot follow
+/// does not lose the console.
+async fn change_baud(rx: &mut uarte::UarteRx<'static>, rate: u32) -> Result<bool, Error> {
+    let previous = console::baud();
+    for remaining in (1..=BAUD_COUNTDOWN_S).rev() {
+        let _ = write!(console::stdout_get(), "{}.. ", remaining);
+        Timer::after_secs(1).await;
+    }
+    console::set_baud(rate)?;
+    let _ = write!(
+        console::stdout_get(),
+        "\r\nPress Enter within {} s to keep {} baud\r\n",
+        BAUD_CONFIRM_TIMEOUT.as_secs(),
+        rate
+    );
+    let confirm = async {
+        let mut byte = [0u8; 1];
+        // Bytes sent before the terminal switched arrive garbled; skip them.
+        loop {
+            if rx.read(&mut byte).await.is_ok() && matches!(byte[0], b'\r' | b'\n') {
+                break;
+            }
+        }
+    };
+    if with_timeout(BAUD_CONFIRM_TIMEOUT, confirm).await.is_ok() {
+        return Ok(true);
+    }
+    console::set_baud(previous)?;
+    Ok(false)
 }
 
-#[derive(Debug, Command)]
-enum RadioMode {
-    /// FM Mode
-    FM,
-    /// AM Mode
-    AM,
-    /// Power down the radio
-    Off,
+/// Add a log filter rule, replacing one with the same pattern.
+fn add_log_rule(action: log_filter::Action, pattern: &str) -> Result<(), Error> {
+    let rule = log_filter::Rule::new(action, pattern)?;
+    let mut result = Ok(());
+    settings::update(|s| result = s.log.filter.add(rule));
+    result
 }
 
-#[derive(Debug, Command)]
-enum TuneCommand {
-    /// Seek up
-    Up,
-    /// Seek down
-    Down,
-    /// Set frequency
-    Frequency {
-        /// Frequency in MHz
-        frequency: f32,
-    },
+fn add_alias(name: &str, expansion: &str) -> Result<(), Error> {
+    let alias = alias::Alias::new(name, expansion)?;
+    let mut result = Ok(());
+    settings::update(|s| result = s.ui.aliases.add(alias));
+    result
 }
 
-#[derive(Debug, Command)]
-enum VolumeCommand {
-    /// Increase volume
-    Up,
-    /// Decrease volume
-    Down,
-    /// Set volume to specific level
-    Set {
-        /// Volume level (0-100)
-        level: u8,
-    },
+/// File transfer started by a command.
+enum TransferJob {
+    PatchUpload(patch::Upload),
+    SettingsImport(settings::Import),
+    SettingsExport(settings::Export),
+    CaptureDownload,
+    ScanExport,
 }
 
+/// Room for the longest prompt: AM, four digit frequency, mute countdown and
+/// operation in progress.
+const PROMPT_LEN: usize = 112;
+/// Frames of the spinner shown in the prompt while an operation runs.
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+/// Time between spinner frames.
+const SPINNER_INTERVAL: Duration = Duration::from_millis(250);
+
 struct PromptStatus<'d> {
-    frequency: f32,
+    frequency: Frequency,
     mode: RadioMode,
-    prompt: Cell<heapless::String<64>>,
+    /// Seconds left of a timed mute.
+    mute_left: Option<u16>,
+    /// Operation in progress and the spinner frame shown for it.
+    busy: Option<Operation>,
+    frame: usize,
+    prompt: Cell<heapless::String<PROMPT_LEN>>,
     _p: PhantomData<&'d ()>,
 }
 
 impl<'d> PromptStatus<'d> {
     pub const fn new() -> Self {
         Self {
-            frequency: 0.0,
+            frequency: Frequency::from_khz(0),
             mode: RadioMode::FM,
+            mute_left: None,
+            busy: None,
+            frame: 0,
             prompt: Cell::new(heapless::String::new()),
             _p: PhantomData {},
         }
@@ -94,12 +171,24 @@ impl<'d> PromptStatus<'d> {
     pub fn get_prompt(&mut self) -> &'d str {
         use crate::console::console_colors::*;
         self.prompt.get_mut().clear();
+        let mut prompt = buffers::Truncate::new(self.prompt.get_mut(), Buffer::Prompt);
         let _ = write!(
-            self.prompt.get_mut(),
-            "{BOLD_GREEN}radio-cli {BOLD_BLUE}{:?} {BOLD_YELLOW}{:.1} MHz{BOLD_GREEN})>{RESET} ",
-            self.mode,
-            self.frequency,
+            prompt,
+            "{BOLD_GREEN}radio-cli {BOLD_BLUE}{:?} {BOLD_YELLOW}{:.1} MHz",
+            self.mode, self.frequency,
         );
+        if let Some(left) = self.mute_left {
+            let _ = write!(prompt, " {BOLD_RED}mute {}s", left);
+        }
+        if let Some(operation) = self.busy {
+            let _ = write!(
+                prompt,
+                " {BOLD_CYAN}{} {}",
+                operation.name(),
+                SPINNER[self.frame]
+            );
+        }
+        let _ = write!(prompt, "{BOLD_GREEN})>{RESET} ");
         self.get_prompt_str()
     }
 
@@ -107,10 +196,128 @@ impl<'d> PromptStatus<'d> {
         self.mode = mode;
         self
     }
-    pub fn set_frequency(&mut self, frequency: f32) -> &mut Self {
+    pub fn set_frequency(&mut self, frequency: Frequency) -> &mut Self {
         self.frequency = frequency;
         self
     }
+    pub fn set_mute_left(&mut self, left: Option<u16>) -> &mut Self {
+        self.mute_left = left;
+        self
+    }
+    pub fn set_busy(&mut self, operation: Option<Operation>) -> &mut Self {
+        self.busy = operation;
+        self.frame = 0;
+        self
+    }
+    pub fn is_busy(&self) -> bool {
+        self.busy.is_some()
+    }
+    /// Advance the spinner.
+    pub fn spin(&mut self) -> &mut Self {
+        self.frame = (self.frame + 1) % SPINNER.len();
+        self
+    }
+    /// Catch up with `state` after missed notifications.
+    pub fn sync(&mut self, state: &RadioState) -> &mut Self {
+        self.mode = match state.mode {
+            state::Mode::Off => RadioMode::Off,
+            state::Mode::Fm => RadioMode::FM,
+            state::Mode::Am => RadioMode::AM,
+        };
+        self.frequency = state.frequency;
+        if !state.muted {
+            self.mute_left = None;
+        }
+        self
+    }
+}
+
+/// Bytes `flash read` dumps at most.
+const FLASH_READ_MAX: u32 = 1024;
+
+/// Dump `len` bytes of `partition` from `offset`, 16 per line as hex and
+/// ASCII, each line starting with its offset in the partition.
+fn write_hex_dump(
+    writer: &mut impl Write,
+    partition: &storage::Partition,
+    offset: u32,
+    len: u32,
+) -> Result<(), Error> {
+    let end = offset.checked_add(len).ok_or(Error::InvalidArgument)?;
+    let mut line = [0u8; 16];
+    for start in (offset..end).step_by(line.len()) {
+        let line = &mut line[..(end - start).min(16) as usize];
+        partition.read(start, line)?;
+        let _ = write!(writer, "{:08X} ", start);
+        for i in 0..16 {
+            let _ = match line.get(i) {
+                Some(byte) => write!(writer, " {:02X}", byte),
+                None => write!(writer, "   "),
+            };
+        }
+        let _ = write!(writer, "  |");
+        for &byte in line.iter() {
+            let c = if (0x20..=0x7E).contains(&byte) {
+                byte as char
+            } else {
+                '.'
+            };
+            let _ = writer.write_char(c);
+        }
+        let _ = writeln!(writer, "|");
+    }
+    Ok(())
+}
+
+/// Input pause after which the next byte is taken for a reconnected terminal.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Greeting with the firmware, board and radio state, printed at startup
+/// and when a terminal reconnects.
+fn write_banner(writer: &mut impl Write) {
+    let _ = write!(
+        writer,
+        "{} {} on {}\r\nRadio: {}\r\nType help for commands",
+        version::NAME,
+        version::VERSION,
+        board::get(),
+        state::get()
+    );
+}
+
+/// Whether `delivery` results from a command of the terminal at `origin` and
+/// is not to be printed there. Errors are always printed.
+fn is_echo(delivery: &Delivery, origin: Origin) -> bool {
+    !settings::get().notify.echo
+        && delivery.origin == origin
+        && !matches!(delivery.notification, SystemNotify::Error(_))
+}
+
+/// Whether `command` may run while kiosk mode locks the controls: commands
+/// that change nothing, and unlocking.
+fn allowed_when_locked(command: &BaseCommand<'_>) -> bool {
+    matches!(
+        command,
+        BaseCommand::Status { .. }
+            | BaseCommand::Version { .. }
+            | BaseCommand::Tasks
+            | BaseCommand::Health
+            | BaseCommand::Stacks
+            | BaseCommand::Schema
+            | BaseCommand::Kiosk {
+                command: KioskCommand::Show | KioskCommand::Unlock { .. },
+            }
+    )
+}
+
+/// Whether `event` should be printed, see [`settings::NotifySettings`].
+fn notification_shown(event: &SystemNotify) -> bool {
+    let notify = settings::get().notify;
+    match event {
+        SystemNotify::SignalQuality(_) => notify.rsq,
+        SystemNotify::RdsPi(_) | SystemNotify::RdsPs(_) | SystemNotify::RdsStation(_) => notify.rds,
+        _ => true,
+    }
 }
 
 fn cli_handle_notification(
@@ -118,45 +325,262 @@ fn cli_handle_notification(
     event: SystemNotify,
     prompt_status: &mut PromptStatus,
 ) {
+    let language = i18n::language();
+    let t = |text: Text| text.get(language);
     match event {
         SystemNotify::RadioAmOn => {
             prompt_status.set_mode(RadioMode::AM);
-            write!(writer, "Switched to AM mode").ok();
+            write!(writer, "{}", t(Text::SwitchedToAm)).ok();
         }
         SystemNotify::RadioFmOn => {
             prompt_status.set_mode(RadioMode::FM);
-            write!(writer, "Switched to FM mode").ok();
+            write!(writer, "{}", t(Text::SwitchedToFm)).ok();
         }
         SystemNotify::RadioOff => {
             prompt_status.set_mode(RadioMode::Off);
-            write!(writer, "Radio powered off").ok();
+            write!(writer, "{}", t(Text::RadioOff)).ok();
+        }
+        SystemNotify::RadioMute | SystemNotify::RadioUnmute => {
+            // A timed mute announces itself with its first countdown.
+            prompt_status.set_mute_left(None);
+            write!(writer, "{}: {:?}", t(Text::Notification), event).ok();
         }
         SystemNotify::TuneStatus(tune_status) => {
             prompt_status.set_frequency(tune_status.frequency);
             write!(
                 writer,
-                "Tuned to frequency {} MHz, {:?}",
-                tune_status.frequency, tune_status
+                "{} {} MHz, {:?}",
+                t(Text::TunedTo),
+                tune_status.frequency,
+                tune_status
+            )
+            .ok();
+        }
+        SystemNotify::Property(value) => {
+            write!(writer, "{}=0x{:04X}", value.property.name, value.value).ok();
+            if value.is_modified() {
+                write!(
+                    writer,
+                    " ({} 0x{:04X})",
+                    t(Text::Default),
+                    value.property.default
+                )
+                .ok();
+            }
+        }
+        SystemNotify::PropertyDumpDone { total, modified } => {
+            write!(
+                writer,
+                "{} {}, {} {}",
+                total,
+                t(Text::PropertiesRead),
+                modified,
+                t(Text::DifferFromDefaults)
+            )
+            .ok();
+        }
+        SystemNotify::ScanStation(result) => {
+            write!(writer, "{} {} ", t(Text::Found), result.band.name()).ok();
+            match result.band {
+                scan::Band::Fm => write!(writer, "{:.2}", result.frequency),
+                _ => write!(writer, "{}", result.frequency.khz()),
+            }
+            .ok();
+            write!(writer, " RSSI {} SNR {}", result.rssi, result.snr).ok();
+        }
+        SystemNotify::ScanBandSkipped(band) => {
+            write!(writer, "{} {}", band.name(), t(Text::BandSkipped)).ok();
+        }
+        SystemNotify::ScanDone => {
+            write!(writer, "{}", t(Text::ScanDone)).ok();
+            for band in scan::Band::ALL {
+                write!(writer, " {} {}", band.name(), scan::count(band)).ok();
+            }
+        }
+        SystemNotify::ProbeDone => {
+            let probes = probe::probes();
+            let best = probe::best(&probes);
+            write!(writer, "{}", t(Text::ProbeHeader)).ok();
+            for (index, probe) in probes.iter().enumerate() {
+                writeln!(writer).ok();
+                let marker = if best == Some(index) { '*' } else { ' ' };
+                write!(writer, "{} {:>7.2}", marker, probe.frequency).ok();
+                match probe.reading {
+                    Some(reading) => {
+                        write!(
+                            writer,
+                            "  {:>4}  {:>3}  {:>9}  {:>4} %",
+                            reading.rssi, reading.snr, reading.multipath, reading.stereo_blend
+                        )
+                        .ok();
+                        if !reading.valid {
+                            write!(writer, "  {}", t(Text::NoStation)).ok();
+                        }
+                    }
+                    None => {
+                        write!(writer, "  {}", t(Text::NotProbed)).ok();
+                    }
+                }
+            }
+        }
+        SystemNotify::ProfileApplied(profile) => {
+            write!(
+                writer,
+                "{} {} {}",
+                t(Text::ReceptionProfile),
+                profile.name(),
+                t(Text::Applied)
+            )
+            .ok();
+        }
+        SystemNotify::RdsPi(pi) => {
+            write!(writer, "RDS PI {:04X}", pi).ok();
+        }
+        SystemNotify::RdsPs(ps) => {
+            write!(writer, "RDS PS \"{}\"", ps).ok();
+        }
+        SystemNotify::RdsStation(station) => {
+            write!(
+                writer,
+                "{} \"{}\" (PI {:04X}, {})",
+                t(Text::Station),
+                station.ps,
+                station.pi,
+                t(Text::Remembered)
+            )
+            .ok();
+        }
+        SystemNotify::FmDiagnostics(diag) => {
+            writeln!(
+                writer,
+                "{}: {}",
+                t(Text::Pilot),
+                t(if diag.pilot {
+                    Text::Present
+                } else {
+                    Text::Absent
+                })
+            )
+            .ok();
+            writeln!(writer, "{}: {} %", t(Text::StereoBlend), diag.stereo_blend).ok();
+            writeln!(
+                writer,
+                "{}: {} kHz",
+                t(Text::FrequencyOffset),
+                diag.frequency_offset
+            )
+            .ok();
+            writeln!(
+                writer,
+                "RSSI {} dBuV, SNR {} dB, {} {}",
+                diag.rssi,
+                diag.snr,
+                t(Text::Multipath),
+                diag.multipath
+            )
+            .ok();
+            write!(writer, "{}", t(Text::DeviationNotReported)).ok();
+        }
+        SystemNotify::SignalQuality(quality) => {
+            write!(
+                writer,
+                "RSSI {} dBuV, SNR {} dB, {} {}, {} {} %",
+                quality.rssi,
+                quality.snr,
+                t(Text::Multipath),
+                quality.multipath,
+                t(Text::Stereo),
+                quality.stereo_blend
+            )
+            .ok();
+        }
+        SystemNotify::TransferProgress(bytes) => {
+            write!(
+                writer,
+                "{} {} {}",
+                t(Text::Transferred),
+                bytes,
+                t(Text::Bytes)
             )
             .ok();
         }
+        SystemNotify::AlertRaised(pi) => {
+            write!(writer, "{BOLD_RED}{}", t(Text::EmergencyAlert)).ok();
+            if let Some(pi) = pi {
+                write!(writer, " (PI {:04X})", pi).ok();
+            }
+            write!(writer, "{RESET}, {}", t(Text::AcknowledgeAlert)).ok();
+        }
+        SystemNotify::AlertCleared => {
+            write!(writer, "{}", t(Text::AlertAcknowledged)).ok();
+        }
+        SystemNotify::RadioInitFailed { attempt, retry_s } => {
+            write!(
+                writer,
+                "{BOLD_RED}{} ({} {}), {} {} s{RESET}",
+                t(Text::RadioNotResponding),
+                t(Text::Attempt),
+                attempt,
+                t(Text::RetryingIn),
+                retry_s
+            )
+            .ok();
+        }
+        SystemNotify::HealthWarning(counter) => {
+            write!(
+                writer,
+                "{BOLD_RED}{}: {} {} {}, {}{RESET}",
+                t(Text::Health),
+                counter.name(),
+                t(Text::Reached),
+                counter.threshold(),
+                t(Text::SeeHealth)
+            )
+            .ok();
+        }
+        SystemNotify::Error(err) => {
+            write!(writer, "{}: {}", t(Text::Error), err).ok();
+        }
         _ => {
-            write!(writer, "Notification: {:?}", event).ok();
+            write!(writer, "{}: {:?}", t(Text::Notification), event).ok();
         }
     }
 }
 
 #[embassy_executor::task]
-pub async fn my_task(mut rx: uarte::UarteRx<'static>) {
+pub async fn my_task(rx: uarte::UarteRx<'static>) {
     let (command_buffer, history_buffer) = unsafe {
-        static mut COMMAND_BUFFER: [u8; 40] = [0; 40];
+        static mut COMMAND_BUFFER: [u8; alias::MAX_LINE] = [0; alias::MAX_LINE];
         static mut HISTORY_BUFFER: [u8; 41] = [0; 41];
         #[allow(static_mut_refs)]
         (COMMAND_BUFFER.as_mut(), HISTORY_BUFFER.as_mut())
     };
+    run(rx, Terminal::Console, command_buffer, history_buffer).await
+}
+
+/// CLI on the UART of a control head, see [`console::remote_init`].
+#[embassy_executor::task]
+pub async fn remote_task(rx: uarte::UarteRx<'static>) {
+    let (command_buffer, history_buffer) = unsafe {
+        static mut COMMAND_BUFFER: [u8; alias::MAX_LINE] = [0; alias::MAX_LINE];
+        static mut HISTORY_BUFFER: [u8; 41] = [0; 41];
+        #[allow(static_mut_refs)]
+        (COMMAND_BUFFER.as_mut(), HISTORY_BUFFER.as_mut())
+    };
+    run(rx, Terminal::Remote, command_buffer, history_buffer).await
+}
+
+/// Run a CLI reading from `rx` and writing to `terminal`. Every instance has
+/// its own prompt and pending commands; events and notifications are shared.
+async fn run(
+    mut rx: uarte::UarteRx<'static>,
+    terminal: Terminal,
+    command_buffer: &'static mut [u8],
+    history_buffer: &'static mut [u8],
+) {
     let mut prompt_status: PromptStatus = PromptStatus::new();
     let mut cli = CliBuilder::default()
-        .writer(console::stdout_get())
+        .writer(terminal)
         .command_buffer(command_buffer)
         .history_buffer(history_buffer)
         .prompt(prompt_status.get_prompt())
@@ -165,15 +589,217 @@ pub async fn my_task(mut rx: uarte::UarteRx<'static>) {
         .unwrap();
 
     let mut notification_subscriber = events::notify_subscriber().unwrap();
+    startup::subscribed();
+    // Commands typed before the radio loop runs would be dropped. Failed
+    // attempts to bring the tuner up are reported meanwhile.
+    while let Either::Second(received) =
+        select(startup::radio_ready(), notification_subscriber.next()).await
+    {
+        if let Received::Notification(Delivery {
+            notification: event @ SystemNotify::RadioInitFailed { .. },
+            ..
+        }) = received
+        {
+            cli.write(|writer| {
+                cli_handle_notification(writer, event, &mut prompt_status);
+                Ok(())
+            })
+            .ok();
+        }
+    }
+    let heartbeat = monitor::register(match terminal {
+        Terminal::Console => "cli",
+        Terminal::Remote => "remote cli",
+    });
+    // Attributed to events sent from this terminal.
+    let origin = match terminal {
+        Terminal::Console => Origin::Console,
+        Terminal::Remote => Origin::Remote,
+    };
+    prompt_status.sync(&state::get());
+    cli.set_prompt(prompt_status.get_prompt()).ok();
+    cli.write(|writer| {
+        write_banner(writer);
+        Ok(())
+    })
+    .ok();
+    // Time of the last byte read, to spot a terminal reconnecting.
+    let mut last_input = Instant::now();
+    // Binary transfer requested by a command, run before reading the next byte.
+    let mut pending_transfer: Option<TransferJob> = None;
+    // Baud rate requested by `term baud`, switched to before reading the next byte.
+    let mut pending_baud: Option<u32> = None;
+    // Reset requested by `reboot` or `bootloader`, done before reading the next byte.
+    let mut pending_reset: Option<reset::Target> = None;
+    // Line being typed, followed to expand aliases when it is finished.
+    let mut line = alias::Line::new();
+    // Lines holding only a frequency tune to it, set by `keypad`.
+    let mut keypad = false;
+    // Key decoder while in interactive mode, where bytes bypass the parser.
+    let mut keys: Option<interactive::Keys> = None;
+    // Bytes fed to the parser before reading more, to swap an alias for its
+    // expansion: backspaces over the typed line, the expansion and Enter.
+    let mut replay: heapless::Deque<u8, { 2 * alias::MAX_LINE + 1 }> = heapless::Deque::new();
 
     loop {
         let buffer = &mut [0u8; 1];
 
-        loop {
-            let char = rx.read(buffer);
-            match select(char, notification_subscriber.next_message_pure()).await {
-                Either::First(_) => break,
-                Either::Second(event) => {
+        // File transfers and the baud rate belong to the console UART.
+        if terminal == Terminal::Remote && (pending_transfer.is_some() || pending_baud.is_some()) {
+            pending_transfer = None;
+            pending_baud = None;
+            cli.write(|writer| {
+                write!(writer, "Only available on the console").ok();
+                Ok(())
+            })
+            .ok();
+            continue;
+        }
+
+        if let Some(job) = pending_transfer.take() {
+            let result = match job {
+                TransferJob::PatchUpload(mut upload) => {
+                    transfer::receive(&mut rx, &mut upload).await
+                }
+                TransferJob::SettingsImport(mut import) => {
+                    transfer::receive(&mut rx, &mut import).await
+                }
+                TransferJob::SettingsExport(mut export) => {
+                    transfer::send(&mut rx, &mut export).await
+                }
+                TransferJob::CaptureDownload => {
+                    transfer::send(&mut rx, &mut capture::Reader::new()).await
+                }
+                TransferJob::ScanExport => {
+                    transfer::send(&mut rx, &mut scan::CsvExport::new()).await
+                }
+            };
+            heartbeat.beat();
+            cli.write(|writer| {
+                match result {
+                    Ok(bytes) => write!(writer, "Transfer complete, {} bytes", bytes),
+                    Err(err) => write!(writer, "Transfer failed: {}", err),
+                }
+                .ok();
+                Ok(())
+            })
+            .ok();
+            continue;
+        }
+
+        if let Some(target) = pending_reset.take() {
+            reset::restart(target).await;
+        }
+
+        if let Some(rate) = pending_baud.take() {
+            let result = change_baud(&mut rx, rate).await;
+            heartbeat.beat();
+            cli.write(|writer| {
+                match result {
+                    Ok(true) => write!(writer, "Console at {} baud", rate),
+                    Ok(false) => write!(writer, "Not confirmed, back to {} baud", console::baud()),
+                    Err(err) => write!(writer, "Baud rate change failed: {}", err),
+                }
+                .ok();
+                Ok(())
+            })
+            .ok();
+            continue;
+        }
+
+        let replayed = replay.pop_front();
+        while replayed.is_none() {
+            // A lone Esc in interactive mode is told by nothing following it.
+            let escape = keys.as_ref().is_some_and(interactive::Keys::pending);
+            let char = async {
+                if escape {
+                    with_timeout(interactive::ESCAPE_TIMEOUT, rx.read(buffer)).await
+                } else {
+                    Ok(rx.read(buffer).await)
+                }
+            };
+            // Interrupt log lines are printed once, by the console.
+            let deferred = async {
+                match terminal {
+                    Terminal::Console => serial_logger::next_deferred().await,
+                    Terminal::Remote => pending().await,
+                }
+            };
+            // Turning the spinner while an operation runs.
+            let busy = prompt_status.is_busy();
+            let spin = async {
+                if busy {
+                    Timer::after(SPINNER_INTERVAL).await
+                } else {
+                    pending().await
+                }
+            };
+            let result = select4(char, notification_subscriber.next(), deferred, spin).await;
+            heartbeat.beat();
+            match result {
+                Either4::First(Err(_)) => {
+                    keys = None;
+                    cli.write(|writer| {
+                        write!(writer, "Interactive mode off").ok();
+                        Ok(())
+                    })
+                    .ok();
+                }
+                Either4::First(Ok(Err(_))) => health::add(Counter::UartErrors, 1),
+                Either4::First(Ok(Ok(()))) => {
+                    // A byte after a long pause likely comes from a terminal
+                    // that was just connected and shows a blank screen.
+                    if last_input.elapsed() >= IDLE_TIMEOUT {
+                        prompt_status.sync(&state::get());
+                        cli.set_prompt(prompt_status.get_prompt()).ok();
+                        cli.write(|writer| {
+                            write_banner(writer);
+                            Ok(())
+                        })
+                        .ok();
+                    }
+                    last_input = Instant::now();
+                    break;
+                }
+                Either4::Second(Received::Lagged { missed, state }) => {
+                    // The end of an operation may be among the missed ones.
+                    prompt_status.sync(&state).set_busy(None);
+                    cli.write(|writer| {
+                        write!(writer, "{} notifications missed, now: {}", missed, state).ok();
+                        Ok(())
+                    })
+                    .ok();
+                    cli.set_prompt(prompt_status.get_prompt()).ok();
+                }
+                // Counting down in the prompt instead of printing every second.
+                Either4::Second(Received::Notification(Delivery {
+                    notification: SystemNotify::MuteCountdown(left),
+                    ..
+                })) => {
+                    prompt_status.set_mute_left(Some(left));
+                    cli.set_prompt(prompt_status.get_prompt()).ok();
+                }
+                // Shown as a spinner in the prompt instead of printed.
+                Either4::Second(Received::Notification(Delivery {
+                    notification: SystemNotify::Progress(operation),
+                    ..
+                })) => {
+                    prompt_status.set_busy(operation);
+                    cli.set_prompt(prompt_status.get_prompt()).ok();
+                }
+                Either4::Second(Received::Notification(Delivery {
+                    notification: event,
+                    ..
+                })) if !notification_shown(&event) => {}
+                // Not printed, but the prompt still follows the change.
+                Either4::Second(Received::Notification(delivery)) if is_echo(&delivery, origin) => {
+                    prompt_status.sync(&state::get());
+                    cli.set_prompt(prompt_status.get_prompt()).ok();
+                }
+                Either4::Second(Received::Notification(Delivery {
+                    notification: event,
+                    ..
+                })) => {
                     cli.write(|writer| {
                         cli_handle_notification(writer, event, &mut prompt_status);
                         Ok(())
@@ -181,65 +807,1721 @@ pub async fn my_task(mut rx: uarte::UarteRx<'static>) {
                     .ok();
                     cli.set_prompt(prompt_status.get_prompt()).ok();
                 }
+                Either4::Third(line) => {
+                    let dropped = serial_logger::deferred_dropped();
+                    cli.write(|writer| {
+                        write!(writer, "{}{}", line, RESET).ok();
+                        if dropped > 0 {
+                            write!(writer, "\r\n{} interrupt log records dropped", dropped).ok();
+                        }
+                        Ok(())
+                    })
+                    .ok();
+                }
+                Either4::Fourth(()) => {
+                    prompt_status.spin();
+                    cli.set_prompt(prompt_status.get_prompt()).ok();
+                }
             }
         }
 
-        if buffer[0] == DEL {
-            // Currently CLI does not handle DEL
-            buffer[0] = codes::BACKSPACE; // To overcome map DEL to BACKSPACE
+        let byte = replayed.unwrap_or_else(|| commands::normalize(buffer[0]));
+        if let Some(decoder) = &mut keys {
+            match decoder.feed(byte) {
+                Some(Key::Exit) => {
+                    keys = None;
+                    cli.write(|writer| {
+                        write!(writer, "Interactive mode off").ok();
+                        Ok(())
+                    })
+                    .ok();
+                }
+                Some(key) => {
+                    if let Some(event) = key.event() {
+                        events::event_try_send(origin, event);
+                    }
+                }
+                None => {}
+            }
+            continue;
+        }
+
+        // A finished line starting with an alias is typed again, expanded.
+        let expanded = match replayed {
+            Some(_) => None,
+            None => line.feed(byte).and_then(|typed| {
+                let expanded = keypad
+                    .then(|| alias::keypad(&typed).map(Ok))
+                    .flatten()
+                    .or_else(|| settings::get().ui.aliases.expand(&typed))?;
+                Some((typed.len(), expanded))
+            }),
+        };
+        if let Some((typed, expanded)) = expanded {
+            for _ in 0..typed {
+                let _ = replay.push_back(codes::BACKSPACE);
+            }
+            match expanded {
+                Ok(expanded) => {
+                    for byte in expanded.bytes().chain([byte]) {
+                        let _ = replay.push_back(byte);
+                    }
+                }
+                Err(_) => {
+                    cli.write(|writer| {
+                        write!(
+                            writer,
+                            "Expanded command longer than {} characters",
+                            alias::MAX_LINE
+                        )
+                        .ok();
+                        Ok(())
+                    })
+                    .ok();
+                }
+            }
+            continue;
         }
 
         // Process incoming byte
         // Command type is specified for autocompletion and help
         // Processor accepts closure where we can process parsed command
         // we can use different command and processor with each call
-        let _ = cli.process_byte::<BaseCommand, _>(
-            buffer[0],
-            &mut BaseCommand::processor(|cli, command| match command {
-                BaseCommand::Status => {
-                    let _ = cli
-                        .writer()
-                        .write_str("System status: All systems operational");
+        let _ = cli.process_byte::<BaseCommand<'_>, _>(
+            byte,
+            &mut BaseCommand::handler(|cli, command| match command {
+                command if kiosk::locked() && !allowed_when_locked(&command) => {
+                    let _ = write!(cli.writer(), "Controls locked, see kiosk unlock");
+                    Ok(())
+                }
+                BaseCommand::Status { detail: None } => {
+                    let _ = write!(
+                        cli.writer(),
+                        "System status: All systems operational\r\nBoard: {}\r\nRadio: {}",
+                        board::get(),
+                        state::get()
+                    );
+                    Ok(())
+                }
+                BaseCommand::Status {
+                    detail: Some("buffers"),
+                } => {
+                    let writer = cli.writer();
+                    let _ = write!(writer, "Buffer overflows since boot:");
+                    for buffer in Buffer::ALL {
+                        let _ = write!(
+                            writer,
+                            "\r\n  {:<14}{}",
+                            buffer.name(),
+                            buffers::overflows(buffer)
+                        );
+                    }
+                    Ok(())
+                }
+                BaseCommand::Status { detail: Some(_) } => {
+                    let _ = write!(cli.writer(), "Detail must be buffers");
+                    Ok(())
+                }
+                BaseCommand::Reboot => {
+                    let _ = write!(cli.writer(), "Rebooting");
+                    pending_reset = Some(reset::Target::Application);
+                    Ok(())
+                }
+                BaseCommand::Bootloader { mode } => {
+                    let (target, name) = match mode {
+                        BootloaderMode::Uf2 => (reset::Target::BootloaderUf2, "UF2"),
+                        BootloaderMode::Serial => (reset::Target::BootloaderSerial, "serial"),
+                    };
+                    let _ = write!(cli.writer(), "Entering {} bootloader", name);
+                    pending_reset = Some(target);
+                    Ok(())
+                }
+                BaseCommand::Input { command } => {
+                    match command {
+                        InputCommand::Show => {}
+                        InputCommand::Repeat { delay, interval } => settings::update(|s| {
+                            s.input.repeat_delay_ms = delay;
+                            s.input.repeat_interval_ms = interval.max(1);
+                        }),
+                        InputCommand::Accel {
+                            threshold,
+                            multiplier,
+                        } => settings::update(|s| {
+                            s.input.accel_threshold_ms = threshold;
+                            s.input.accel_multiplier = multiplier.max(1);
+                        }),
+                    }
+                    let input = settings::get().input;
+                    let _ = write!(
+                        cli.writer(),
+                        "Repeat after {} ms every {} ms, accelerate x{} below {} ms",
+                        input.repeat_delay_ms,
+                        input.repeat_interval_ms,
+                        input.accel_multiplier,
+                        input.accel_threshold_ms
+                    );
+                    Ok(())
+                }
+                BaseCommand::Touch { command } => {
+                    let writer = cli.writer();
+                    if !cfg!(feature = "touch") {
+                        let _ = write!(writer, "Touch input is not enabled in this build");
+                        return Ok(());
+                    }
+                    match command {
+                        TouchCommand::Show => {}
+                        TouchCommand::Calibrate => {
+                            touch::calibrate();
+                            let _ = writeln!(writer, "Calibrating, keep hands off the pads");
+                        }
+                        TouchCommand::Threshold { percent } => {
+                            settings::update(|s| s.input.touch_threshold_pct = percent.clamp(1, 50))
+                        }
+                    }
+                    let _ = writeln!(
+                        writer,
+                        "Touch threshold {} %",
+                        settings::get().input.touch_threshold_pct
+                    );
+                    for (index, pad) in touch::state().iter().enumerate() {
+                        let _ = writeln!(
+                            writer,
+                            "Pad {}: count {}, baseline {}{}",
+                            index,
+                            pad.count,
+                            pad.baseline,
+                            if pad.touched { ", touched" } else { "" }
+                        );
+                    }
+                    Ok(())
+                }
+                BaseCommand::Beep { command } => {
+                    match command {
+                        BeepCommand::Show => {}
+                        BeepCommand::On => settings::update(|s| s.beep.enabled = true),
+                        BeepCommand::Off => settings::update(|s| s.beep.enabled = false),
+                        BeepCommand::Volume { level } => {
+                            settings::update(|s| s.beep.volume = level.min(buzzer::MAX_VOLUME))
+                        }
+                        BeepCommand::Test => buzzer::beep(buzzer::Beep::Confirm),
+                    }
+                    let beep = settings::get().beep;
+                    let _ = write!(
+                        cli.writer(),
+                        "Beeps {}, volume {}/{}",
+                        if beep.enabled { "on" } else { "off" },
+                        beep.volume,
+                        buzzer::MAX_VOLUME
+                    );
+                    Ok(())
+                }
+                BaseCommand::Led { command } => {
+                    match command {
+                        LedCommand::Show => {}
+                        LedCommand::Polarity { level } => settings::update(|s| {
+                            s.led.active_low = matches!(level, LedPolarity::Low)
+                        }),
+                        LedCommand::Strip { count } => {
+                            settings::update(|s| s.led.strip_len = count.min(led::MAX_STRIP_LEDS))
+                        }
+                        LedCommand::Brightness { percent } => {
+                            settings::update(|s| s.led.brightness = percent.min(100))
+                        }
+                    }
+                    led::refresh();
+                    let config = settings::get().led;
+                    let _ = write!(
+                        cli.writer(),
+                        "Status LED active {}, strip {} LEDs at {} %",
+                        if config.active_low { "low" } else { "high" },
+                        config.strip_len,
+                        config.brightness
+                    );
+                    Ok(())
+                }
+                BaseCommand::Lang { command } => {
+                    let language = match command {
+                        LangCommand::Show => None,
+                        LangCommand::En => Some(Language::En),
+                        LangCommand::De => Some(Language::De),
+                    };
+                    if let Some(language) = language {
+                        settings::update(|s| s.ui.language = language);
+                    }
+                    let current = i18n::language();
+                    let _ = write!(
+                        cli.writer(),
+                        "{} ({}), notifications only",
+                        current.name(),
+                        current.code()
+                    );
+                    Ok(())
+                }
+                BaseCommand::Prop { command } => {
+                    match command {
+                        PropCommand::Dump => {
+                            events::event_try_send(origin, SystemEvent::RadioPropertyDump)
+                        }
+                        PropCommand::Diff => {
+                            events::event_try_send(origin, SystemEvent::RadioPropertyDiff)
+                        }
+                    }
+                    Ok(())
+                }
+                BaseCommand::Profile { command } => {
+                    let profile = match command {
+                        ProfileCommand::Show => None,
+                        ProfileCommand::Dx => Some(Profile::Dx),
+                        ProfileCommand::Normal => Some(Profile::Normal),
+                        ProfileCommand::Local => Some(Profile::Local),
+                    };
+                    match profile {
+                        Some(profile) => {
+                            events::event_try_send(origin, SystemEvent::RadioProfile(profile))
+                        }
+                        None => {
+                            let _ = write!(
+                                cli.writer(),
+                                "Reception profile {}",
+                                settings::get().reception.profile.name()
+                            );
+                        }
+                    }
+                    Ok(())
+                }
+                BaseCommand::Seek {
+                    command: SeekCommand::Best,
+                } => {
+                    let _ = cli.writer().write_str("Seeking both ways");
+                    events::event_try_send(origin, SystemEvent::RadioSeekBest);
                     Ok(())
                 }
-                BaseCommand::Mode { command } => {
+                BaseCommand::Seek { command } => {
+                    let writer = cli.writer();
+                    if let SeekCommand::Sensitivity { band, level } = command {
+                        match scan::Band::from_name(band) {
+                            Some(scan::Band::Fm) => {}
+                            Some(_) => {
+                                let _ = write!(writer, "AM and SW seek are not implemented");
+                                return Ok(());
+                            }
+                            None => {
+                                let _ = write!(writer, "Band must be fm");
+                                return Ok(());
+                            }
+                        }
+                        let Some(sensitivity) = seek::Sensitivity::from_name(level) else {
+                            let _ = write!(writer, "Sensitivity must be low, normal or high");
+                            return Ok(());
+                        };
+                        settings::update(|s| s.seek.sensitivity = sensitivity);
+                        events::event_try_send(origin, SystemEvent::RadioSeekSensitivity);
+                    }
+                    let sensitivity = settings::get().seek.sensitivity;
+                    let (rssi, snr) = seek::thresholds(sensitivity);
+                    let _ = write!(
+                        writer,
+                        "fm: {}, RSSI {} dBuV, SNR {} dB",
+                        sensitivity.name(),
+                        rssi,
+                        snr
+                    );
+                    Ok(())
+                }
+                BaseCommand::Calibrate { command } => {
+                    let writer = cli.writer();
+                    if let CalibrateCommand::Set { band, offset } = command {
+                        let Some(band) = scan::Band::from_name(band) else {
+                            let _ = write!(writer, "Band must be fm, am or sw");
+                            return Ok(());
+                        };
+                        let max = calibration::MAX_OFFSET_KHZ;
+                        let Some(offset) = offset
+                            .parse::<i16>()
+                            .ok()
+                            .filter(|offset| (-max..=max).contains(offset))
+                        else {
+                            let _ = write!(writer, "Offset must be -{} to {} kHz", max, max);
+                            return Ok(());
+                        };
+                        settings::update(|s| s.calibration.set_offset_khz(band, offset));
+                    }
+                    let calibration = settings::get().calibration;
+                    for band in scan::Band::ALL {
+                        let _ = writeln!(
+                            writer,
+                            "{}: {:+} kHz",
+                            band.name(),
+                            calibration.offset_khz(band)
+                        );
+                    }
+                    Ok(())
+                }
+                BaseCommand::Stereo { command } => {
+                    let writer = cli.writer();
+                    match command {
+                        StereoCommand::Show => {}
+                        StereoCommand::On => stereo::set_enabled(true),
+                        StereoCommand::Off => stereo::set_enabled(false),
+                        StereoCommand::Thresholds {
+                            mono,
+                            stereo: above,
+                        } => {
+                            let mut thresholds = settings::get().stereo;
+                            thresholds.mono_below_snr = mono;
+                            thresholds.stereo_above_snr = above;
+                            if !stereo::valid(&thresholds) {
+                                let _ = write!(writer, "Need mono < stereo <= 127 dB");
+                                return Ok(());
+                            }
+                            settings::update(|s| s.stereo = thresholds);
+                        }
+                    }
+                    let settings = settings::get().stereo;
+                    let _ = writeln!(
+                        writer,
+                        "Mono override {}, mono below SNR {} dB, stereo from {} dB",
+                        if settings.enabled { "on" } else { "off" },
+                        settings.mono_below_snr,
+                        settings.stereo_above_snr
+                    );
+                    if stereo::is_forced() {
+                        let _ = writeln!(writer, "Mono forced now");
+                    }
+                    Ok(())
+                }
+                BaseCommand::Telemetry { command } => {
+                    let writer = cli.writer();
+                    match command {
+                        TelemetryCommand::On { rate }
+                            if (1..=telemetry::MAX_RATE_HZ).contains(&rate) =>
+                        {
+                            telemetry::start(rate);
+                            let _ = write!(writer, "Telemetry at {} Hz", rate);
+                        }
+                        TelemetryCommand::On { .. } => {
+                            let _ = write!(writer, "Rate must be 1-{} Hz", telemetry::MAX_RATE_HZ);
+                        }
+                        TelemetryCommand::Off => {
+                            telemetry::stop();
+                            let _ = write!(writer, "Telemetry off");
+                        }
+                    }
+                    Ok(())
+                }
+                BaseCommand::Abort => {
+                    service::abort();
+                    let _ = cli.writer().write_str("Aborting");
+                    Ok(())
+                }
+                BaseCommand::Probe {
+                    first,
+                    second,
+                    third,
+                    fourth,
+                    fifth,
+                    sixth,
+                    seventh,
+                    eighth,
+                } => {
+                    let band = config::REGION.fm_band();
+                    let texts = [
+                        Some(first),
+                        second,
+                        third,
+                        fourth,
+                        fifth,
+                        sixth,
+                        seventh,
+                        eighth,
+                    ];
+                    let mut frequencies =
+                        heapless::Vec::<Frequency, { probe::MAX_FREQUENCIES }>::new();
+                    let mut invalid = None;
+                    for text in texts.into_iter().flatten() {
+                        match Frequency::parse_mhz(text)
+                            .filter(|frequency| (band.0..=band.1).contains(frequency))
+                        {
+                            Some(frequency) => {
+                                let _ = frequencies.push(frequency);
+                            }
+                            None => invalid = invalid.or(Some(text)),
+                        }
+                    }
+                    let writer = cli.writer();
+                    match invalid {
+                        Some(text) => {
+                            let _ = write!(writer, "{} is not an FM frequency in MHz", text);
+                        }
+                        None => {
+                            probe::set(&frequencies);
+                            let _ = write!(writer, "Probing {} frequencies", frequencies.len());
+                            events::event_try_send(origin, SystemEvent::RadioProbe);
+                        }
+                    }
+                    Ok(())
+                }
+                BaseCommand::Scan { command } => {
+                    match command {
+                        ScanCommand::Band => {
+                            let _ = cli.writer().write_str("Scanning");
+                            events::event_try_send(
+                                origin,
+                                SystemEvent::RadioScan { all_bands: false },
+                            );
+                        }
+                        ScanCommand::All => {
+                            let _ = cli.writer().write_str("Scanning all bands");
+                            events::event_try_send(
+                                origin,
+                                SystemEvent::RadioScan { all_bands: true },
+                            );
+                        }
+                        ScanCommand::List => {
+                            let writer = cli.writer();
+                            let _ = writeln!(writer, "Band  Frequency  RSSI  SNR");
+                            scan::for_each(|result| {
+                                let _ = write!(writer, "{:<4}  ", result.band.name());
+                                let _ = match result.band {
+                                    scan::Band::Fm => write!(writer, "{:>9.2}", result.frequency),
+                                    _ => write!(writer, "{:>9}", result.frequency.khz()),
+                                };
+                                let _ = writeln!(writer, "  {:>4}  {:>3}", result.rssi, result.snr);
+                            });
+                            for band in scan::Band::ALL {
+                                let _ = write!(writer, "{}: {} ", band.name(), scan::count(band));
+                            }
+                        }
+                        ScanCommand::Export {
+                            command: ScanExportCommand::Csv,
+                        } => {
+                            let _ = scan::write_csv(cli.writer());
+                        }
+                        ScanCommand::Export {
+                            command: ScanExportCommand::Download,
+                        } => {
+                            pending_transfer = Some(TransferJob::ScanExport);
+                            let _ = cli.writer().write_str("Start XMODEM download");
+                        }
+                    }
+                    Ok(())
+                }
+                BaseCommand::Patch { command } => {
+                    let writer = cli.writer();
+                    match command {
+                        PatchCommand::Upload { length, crc } => {
+                            match patch::Upload::begin(length, crc) {
+                                Ok(transfer) => {
+                                    pending_transfer = Some(TransferJob::PatchUpload(transfer));
+                                    let _ = write!(writer, "Send {} bytes", length);
+                                }
+                                Err(err) => {
+                                    let _ = write!(writer, "Patch upload failed: {}", err);
+                                }
+                            }
+                        }
+                        PatchCommand::Status => match patch::stored() {
+                            Ok(Some(info)) => {
+                                let _ = write!(
+                                    writer,
+                                    "Patch: {} bytes, CRC {:08X}, {}",
+                                    info.length,
+                                    info.crc,
+                                    if patch::is_applied() {
+                                        "applied"
+                                    } else {
+                                        "not applied"
+                                    }
+                                );
+                            }
+                            Ok(None) => {
+                                let _ = write!(
+                                    writer,
+                                    "No patch stored ({} bytes free)",
+                                    patch::CAPACITY
+                                );
+                            }
+                            Err(err) => {
+                                let _ = write!(writer, "Patch read failed: {}", err);
+                            }
+                        },
+                        PatchCommand::Erase => match patch::erase() {
+                            Ok(()) => {
+                                let _ = writer.write_str("Patch erased");
+                            }
+                            Err(err) => {
+                                let _ = write!(writer, "Patch erase failed: {}", err);
+                            }
+                        },
+                    }
+                    Ok(())
+                }
+                BaseCommand::Settings { command } => {
+                    let writer = cli.writer();
+                    match command {
+                        SettingsCommand::Export => match settings::Export::new() {
+                            Ok(export) => {
+                                pending_transfer = Some(TransferJob::SettingsExport(export));
+                                let _ = writer.write_str("Start XMODEM download");
+                            }
+                            Err(err) => {
+                                let _ = write!(writer, "Settings export failed: {}", err);
+                            }
+                        },
+                        SettingsCommand::Import if !settings::get().tune_lock.is_modifiable() => {
+                            let _ = writer.write_str("Locked, unlock with the PIN first");
+                        }
+                        SettingsCommand::Import => {
+                            pending_transfer =
+                                Some(TransferJob::SettingsImport(settings::Import::new()));
+                            let _ = writer.write_str("Send the settings file");
+                        }
+                    }
+                    Ok(())
+                }
+                BaseCommand::Radio { command } => {
+                    match command {
+                        RadioCommand::Diag {
+                            command: DiagCommand::Fm,
+                        } => events::event_try_send(origin, SystemEvent::RadioDiagFm),
+                    }
+                    Ok(())
+                }
+                BaseCommand::Poll { command } => {
+                    let selected = match command {
+                        PollCommand::Show => None,
+                        PollCommand::Rsq { command } => Some((poller::Source::Rsq, command)),
+                        PollCommand::Rds { command } => Some((poller::Source::Rds, command)),
+                        PollCommand::Survey { command } => Some((poller::Source::Survey, command)),
+                    };
+                    if let Some((source, command)) = selected {
+                        match command {
+                            PollSourceCommand::On => poller::set_enabled(source, true),
+                            PollSourceCommand::Off => poller::set_enabled(source, false),
+                            PollSourceCommand::Interval { interval } => poller::set_interval(
+                                source,
+                                Duration::from_millis(interval.max(10) as u64),
+                            ),
+                        }
+                    }
+                    let writer = cli.writer();
+                    for source in poller::Source::ALL {
+                        let state = poller::state(source);
+                        let _ = writeln!(
+                            writer,
+                            "{:<9} {:<3} every {} ms, {} reads, {} failures",
+                            source.name(),
+                            if state.enabled { "on" } else { "off" },
+                            state.effective_interval().as_millis(),
+                            state.reads,
+                            state.failures
+                        );
+                    }
+                    Ok(())
+                }
+                BaseCommand::Rds { command } => {
+                    let writer = cli.writer();
+                    match command {
+                        RdsCommand::Db {
+                            command: RdsDbCommand::List,
+                        } => {
+                            let _ = writeln!(writer, "PI    Frequency  PS");
+                            rds_db::for_each(|station| {
+                                let _ = writeln!(
+                                    writer,
+                                    "{:04X}  {:>9.2}  {}",
+                                    station.pi, station.frequency, station.ps
+                                );
+                            });
+                        }
+                        RdsCommand::Db {
+                            command: RdsDbCommand::Clear,
+                        } => match rds_db::clear() {
+                            Ok(()) => {
+                                let _ = writer.write_str("RDS stations erased");
+                            }
+                            Err(err) => {
+                                let _ = write!(writer, "RDS database clear failed: {}", err);
+                            }
+                        },
+                    }
+                    Ok(())
+                }
+                BaseCommand::Audio { command } => {
+                    match command {
+                        AudioCommand::Show => {}
+                        AudioCommand::Coalesce { window } => {
+                            settings::update(|s| s.audio.volume_coalesce_ms = window)
+                        }
+                        AudioCommand::Tunemute { state } => {
+                            settings::update(|s| s.audio.tune_mute = state.enabled())
+                        }
+                        AudioCommand::Stepmute { debounce } => {
+                            settings::update(|s| s.audio.step_mute_ms = debounce)
+                        }
+                        AudioCommand::Curve { curve } => {
+                            settings::update(|s| {
+                                s.audio.volume_curve = match curve {
+                                    AudioCurve::Linear => VolumeCurve::Linear,
+                                    AudioCurve::Soft => VolumeCurve::Soft,
+                                }
+                            });
+                            events::event_try_send(origin, SystemEvent::RadioVolumeLimit);
+                        }
+                        AudioCommand::Max { percent } => {
+                            settings::update(|s| s.audio.max_volume_pct = percent.min(100));
+                            events::event_try_send(origin, SystemEvent::RadioVolumeLimit);
+                        }
+                        AudioCommand::Balance { side } => {
+                            let amount = |amount: u8| amount.min(balance::MAX as u8) as i8;
+                            let level = match side {
+                                BalanceSide::Center => 0,
+                                BalanceSide::Left { amount: left } => -amount(left),
+                                BalanceSide::Right { amount: right } => amount(right),
+                            };
+                            settings::update(|s| s.audio.balance = level);
+                            events::event_try_send(origin, SystemEvent::RadioBalance);
+                        }
+                        AudioCommand::Leveling { command } => match command {
+                            LevelingCommand::List => {
+                                let writer = cli.writer();
+                                let _ = writeln!(writer, "Frequency  Offset");
+                                for station in settings::get().audio.leveling.offsets() {
+                                    let _ = writeln!(
+                                        writer,
+                                        "{:>9.2}  {:>+6}",
+                                        station.frequency, station.offset
+                                    );
+                                }
+                            }
+                            LevelingCommand::On => {
+                                settings::update(|s| s.audio.leveling.enabled = true)
+                            }
+                            LevelingCommand::Off => {
+                                settings::update(|s| s.audio.leveling.enabled = false)
+                            }
+                            LevelingCommand::Clear => {
+                                settings::update(|s| s.audio.leveling.clear())
+                            }
+                        },
+                    }
+                    let audio = settings::get().audio;
+                    let _ = write!(
+                        cli.writer(),
+                        "Coalescing window {} ms, mute while tuning {}, ",
+                        audio.volume_coalesce_ms,
+                        if audio.tune_mute { "on" } else { "off" },
+                    );
+                    let _ = match audio.step_mute_ms {
+                        0 => write!(cli.writer(), "step mute off, "),
+                        ms => write!(cli.writer(), "step mute {} ms, ", ms),
+                    };
+                    let _ = write!(
+                        cli.writer(),
+                        "{} curve, max {} %, ",
+                        audio.volume_curve.name(),
+                        audio.max_volume_pct
+                    );
+                    let _ = match audio.balance {
+                        0 => write!(cli.writer(), "balance center"),
+                        level if level < 0 => write!(cli.writer(), "balance left {}", -level),
+                        level => write!(cli.writer(), "balance right {}", level),
+                    };
+                    let _ = write!(
+                        cli.writer(),
+                        ", leveling {} ({} stations)",
+                        if audio.leveling.enabled { "on" } else { "off" },
+                        audio.leveling.offsets().count()
+                    );
+                    Ok(())
+                }
+                BaseCommand::Term {
+                    command: TermCommand::Baud { rate },
+                } => {
+                    let writer = cli.writer();
+                    match rate {
+                        None => {
+                            let _ = write!(writer, "Console at {} baud", console::baud());
+                        }
+                        Some(rate) if console::baudrate(rate).is_some() => {
+                            let _ = write!(
+                                writer,
+                                "Switching to {} baud in {} s, reconnect and press Enter",
+                                rate, BAUD_COUNTDOWN_S
+                            );
+                            pending_baud = Some(rate);
+                        }
+                        Some(_) => {
+                            let _ = write!(writer, "Supported rates:");
+                            for rate in console::BAUD_RATES {
+                                let _ = write!(writer, " {}", rate);
+                            }
+                        }
+                    }
+                    Ok(())
+                }
+                BaseCommand::Log {
+                    command: LogCommand::Format { command },
+                } => {
                     match command {
-                        RadioMode::FM => events::event_try_send(SystemEvent::RadioFmOn),
-                        RadioMode::AM => events::event_try_send(SystemEvent::RadioAmOn),
-                        RadioMode::Off => events::event_try_send(SystemEvent::RadioOff),
+                        LogFormatCommand::Show => {}
+                        LogFormatCommand::Ms => serial_logger::set_format(TimestampFormat::Uptime),
+                        LogFormatCommand::Clock => {
+                            serial_logger::set_format(TimestampFormat::Clock)
+                        }
+                        LogFormatCommand::Rds => serial_logger::set_format(TimestampFormat::Rds),
+                        LogFormatCommand::Task { state } => {
+                            serial_logger::set_show_task(state.enabled())
+                        }
+                    }
+                    let _ = write!(
+                        cli.writer(),
+                        "Timestamps: {}, task name: {}",
+                        serial_logger::format().name(),
+                        if serial_logger::show_task() {
+                            "on"
+                        } else {
+                            "off"
+                        }
+                    );
+                    Ok(())
+                }
+                BaseCommand::Log {
+                    command: LogCommand::Filter { command },
+                } => {
+                    let writer = cli.writer();
+                    let mut result = Ok(());
+                    match command {
+                        LogFilterCommand::Show => {}
+                        LogFilterCommand::Allow { pattern } => {
+                            result = add_log_rule(log_filter::Action::Allow, pattern)
+                        }
+                        LogFilterCommand::Deny { pattern } => {
+                            result = add_log_rule(log_filter::Action::Deny, pattern)
+                        }
+                        LogFilterCommand::Remove { pattern } => {
+                            let mut found = false;
+                            settings::update(|s| found = s.log.filter.remove(pattern));
+                            if !found {
+                                let _ = writeln!(writer, "No rule for {}", pattern);
+                            }
+                        }
+                        LogFilterCommand::Clear => settings::update(|s| s.log.filter.clear()),
+                    }
+                    if let Err(err) = result {
+                        let _ = writeln!(writer, "Error: {}", err);
+                    }
+                    let filter = settings::get().log.filter;
+                    if filter.rules().next().is_none() {
+                        let _ = writeln!(writer, "No log filter rules, all targets logged");
+                    }
+                    for rule in filter.rules() {
+                        let _ = writeln!(writer, "{:<5} {}", rule.action.name(), rule.pattern());
+                    }
+                    Ok(())
+                }
+                BaseCommand::Trace { command } => {
+                    let writer = cli.writer();
+                    let result = match command {
+                        TraceCommand::Show => Ok(()),
+                        TraceCommand::On => trace::set_enabled(true),
+                        TraceCommand::Off => trace::set_enabled(false),
+                        TraceCommand::Clear => trace::clear(),
+                        TraceCommand::I2c { state } => {
+                            i2c_trace::set_enabled(state.enabled());
+                            Ok(())
+                        }
+                        TraceCommand::List => trace::for_each_last(|entry| {
+                            let _ = writeln!(
+                                writer,
+                                "{:>8} ms  {:?}",
+                                entry.time.as_millis(),
+                                entry.event
+                            );
+                        })
+                        .map(|found| {
+                            if !found {
+                                let _ = writeln!(writer, "No earlier session recorded");
+                            }
+                        }),
+                    };
+                    if let Err(err) = result {
+                        let _ = writeln!(writer, "Trace failed: {}", err);
+                    }
+                    match trace::usage() {
+                        Ok(usage) => {
+                            let _ = write!(
+                                writer,
+                                "Trace {}, {} of {} entries used, {} sessions, I2C trace {}",
+                                if trace::is_recording() { "on" } else { "off" },
+                                usage.used,
+                                trace::CAPACITY,
+                                usage.sessions,
+                                if i2c_trace::is_enabled() { "on" } else { "off" }
+                            );
+                        }
+                        Err(err) => {
+                            let _ = write!(writer, "Trace unreadable: {}", err);
+                        }
+                    }
+                    Ok(())
+                }
+                BaseCommand::Replay {
+                    command: ReplayCommand::Last,
+                } => {
+                    match trace::request_replay() {
+                        Ok(()) => {
+                            let _ = write!(cli.writer(), "Restarting to replay the last session");
+                            pending_reset = Some(reset::Target::Application);
+                        }
+                        Err(Error::InvalidArgument) => {
+                            let _ = write!(cli.writer(), "No earlier session recorded");
+                        }
+                        Err(err) => {
+                            let _ = write!(cli.writer(), "Replay failed: {}", err);
+                        }
+                    }
+                    Ok(())
+                }
+                BaseCommand::Notify { command } => {
+                    if let NotifyCommand::Show { command } = command {
+                        settings::update(|s| match command {
+                            NotifyShowCommand::Rsq { state } => s.notify.rsq = state.enabled(),
+                            NotifyShowCommand::Rds { state } => s.notify.rds = state.enabled(),
+                            NotifyShowCommand::Echo { state } => s.notify.echo = state.enabled(),
+                        });
+                    }
+                    let notify = settings::get().notify;
+                    let writer = cli.writer();
+                    for (name, shown) in [
+                        ("rsq", notify.rsq),
+                        ("rds", notify.rds),
+                        ("echo", notify.echo),
+                    ] {
+                        let _ =
+                            writeln!(writer, "{:<8} {}", name, if shown { "on" } else { "off" });
                     }
                     Ok(())
                 }
+                BaseCommand::Clock { command } => {
+                    let writer = cli.writer();
+                    if let ClockCommand::Tz { command } = command {
+                        let zone = match command {
+                            TzCommand::Broadcast => Some(TimeZone::Broadcast),
+                            TzCommand::Set { offset, dst } => {
+                                match (
+                                    TimeZone::parse_offset(offset),
+                                    DstRule::from_name(dst.unwrap_or("none")),
+                                ) {
+                                    (Some(offset), Some(dst)) => {
+                                        Some(TimeZone::Fixed { offset, dst })
+                                    }
+                                    (None, _) => {
+                                        let _ = writeln!(writer, "Offset must look like utc+1");
+                                        None
+                                    }
+                                    (_, None) => {
+                                        let _ = writeln!(writer, "DST rule must be none, eu or us");
+                                        None
+                                    }
+                                }
+                            }
+                        };
+                        if let Some(zone) = zone {
+                            settings::update(|s| s.clock.zone = zone);
+                        }
+                    }
+                    match clock::local() {
+                        Some(local) => {
+                            let _ = writeln!(writer, "{}", local);
+                        }
+                        None => {
+                            let _ = writeln!(writer, "Clock not set, waiting for RDS clock time");
+                        }
+                    }
+                    let _ = write!(writer, "Time zone: {}", settings::get().clock.zone);
+                    Ok(())
+                }
+                BaseCommand::Alarm { command } => {
+                    let writer = cli.writer();
+                    match command {
+                        AlarmCommand::Show => {}
+                        AlarmCommand::On => settings::update(|s| s.alarm.enabled = true),
+                        AlarmCommand::Off => settings::update(|s| s.alarm.enabled = false),
+                        AlarmCommand::Time { time } => match alarm::parse_time(time) {
+                            Some((hour, minute)) => settings::update(|s| {
+                                s.alarm.hour = hour;
+                                s.alarm.minute = minute;
+                            }),
+                            None => {
+                                let _ = writeln!(writer, "Time must be hh:mm");
+                            }
+                        },
+                        AlarmCommand::Station { frequency } => {
+                            match frequency.map(Frequency::parse_mhz) {
+                                Some(None) => {
+                                    let _ = writeln!(writer, "Frequency must be in MHz");
+                                }
+                                frequency => {
+                                    settings::update(|s| s.alarm.frequency = frequency.flatten())
+                                }
+                            }
+                        }
+                        AlarmCommand::Volume { percent } => {
+                            settings::update(|s| s.alarm.volume = percent.min(100))
+                        }
+                        AlarmCommand::Ramp { seconds } => {
+                            settings::update(|s| s.alarm.ramp_s = seconds)
+                        }
+                        AlarmCommand::SnoozeTime { minutes } => {
+                            settings::update(|s| s.alarm.snooze_min = minutes.max(1))
+                        }
+                        AlarmCommand::Snooze => {
+                            if !alarm::snooze() {
+                                let _ = writeln!(writer, "Alarm is not ringing");
+                            }
+                        }
+                        AlarmCommand::Stop => {
+                            if !alarm::stop() {
+                                let _ = writeln!(writer, "Alarm is not ringing");
+                            }
+                        }
+                    }
+                    let config = settings::get().alarm;
+                    let _ = write!(
+                        writer,
+                        "Alarm {} at {:02}:{:02}, ",
+                        if config.enabled { "on" } else { "off" },
+                        config.hour,
+                        config.minute
+                    );
+                    let _ = match config.frequency {
+                        Some(frequency) => write!(writer, "{:.2} MHz", frequency),
+                        None => write!(writer, "current station"),
+                    };
+                    let _ = writeln!(
+                        writer,
+                        ", volume {} % after {} s, snooze {} min",
+                        config.volume, config.ramp_s, config.snooze_min
+                    );
+                    let _ = match alarm::state() {
+                        alarm::State::Idle if clock::local().is_none() => {
+                            write!(writer, "Clock not set, the alarm cannot ring yet")
+                        }
+                        alarm::State::Idle => write!(writer, "Idle"),
+                        alarm::State::Ringing => write!(writer, "Ringing"),
+                        alarm::State::Snoozed { until } => write!(
+                            writer,
+                            "Snoozed, ringing again in {} s",
+                            until.saturating_duration_since(Instant::now()).as_secs()
+                        ),
+                    };
+                    Ok(())
+                }
+                BaseCommand::Alert { command } => {
+                    let writer = cli.writer();
+                    match command {
+                        AlertCommand::Show => {}
+                        AlertCommand::On => settings::update(|s| s.alert.enabled = true),
+                        AlertCommand::Off => settings::update(|s| s.alert.enabled = false),
+                        AlertCommand::Volume { percent } => {
+                            settings::update(|s| s.alert.volume = percent.min(100))
+                        }
+                        AlertCommand::Ack => {
+                            if !alert::acknowledge() {
+                                let _ = writeln!(writer, "No alert latched");
+                            }
+                        }
+                        AlertCommand::Test => {
+                            if !alert::raise(None, prompt_status.frequency) {
+                                let _ = writeln!(writer, "Alerts are off or one is latched");
+                            }
+                        }
+                    }
+                    let config = settings::get().alert;
+                    let _ = writeln!(
+                        writer,
+                        "Emergency alerts {}, volume {} %",
+                        if config.enabled { "on" } else { "off" },
+                        config.volume
+                    );
+                    let _ = match alert::latched() {
+                        Some(alert) => write!(
+                            writer,
+                            "Alert from {:.2} MHz latched for {} s",
+                            alert.frequency,
+                            alert.since.elapsed().as_secs()
+                        ),
+                        None => write!(writer, "No alert latched"),
+                    };
+                    Ok(())
+                }
+                BaseCommand::Kiosk { command } => {
+                    let writer = cli.writer();
+                    match command {
+                        KioskCommand::Show => {}
+                        KioskCommand::On { code } => {
+                            settings::update(|s| {
+                                s.kiosk.enabled = true;
+                                s.kiosk.code = code;
+                            });
+                            kiosk::lock();
+                        }
+                        KioskCommand::Off => settings::update(|s| s.kiosk.enabled = false),
+                        KioskCommand::Station { frequency } => {
+                            match frequency.map(Frequency::parse_mhz) {
+                                Some(None) => {
+                                    let _ = writeln!(writer, "Frequency must be in MHz");
+                                }
+                                station => {
+                                    settings::update(|s| s.kiosk.station = station.flatten())
+                                }
+                            }
+                        }
+                        KioskCommand::Unlock { code } => {
+                            if !kiosk::unlock(code) {
+                                let _ = writeln!(writer, "Wrong code");
+                            }
+                        }
+                        KioskCommand::Lock => kiosk::lock(),
+                    }
+                    let config = settings::get().kiosk;
+                    let _ = write!(
+                        writer,
+                        "Kiosk mode {}, ",
+                        if config.enabled { "on" } else { "off" }
+                    );
+                    let _ = match config.station {
+                        Some(station) => write!(writer, "boots to {:.2} MHz", station),
+                        None => write!(writer, "boots to the startup frequency"),
+                    };
+                    if config.enabled {
+                        let _ = write!(
+                            writer,
+                            ", controls {}",
+                            if kiosk::locked() {
+                                "locked"
+                            } else {
+                                "unlocked"
+                            }
+                        );
+                    }
+                    Ok(())
+                }
+                BaseCommand::TuneLock { command } => {
+                    let writer = cli.writer();
+                    let modifies = !matches!(
+                        command,
+                        TuneLockCommand::Show
+                            | TuneLockCommand::Unlock { .. }
+                            | TuneLockCommand::Lock
+                    );
+                    let band = config::REGION.fm_band();
+                    let in_band = |text: &str| {
+                        Frequency::parse_mhz(text)
+                            .filter(|frequency| (band.0..=band.1).contains(frequency))
+                    };
+                    match command {
+                        _ if modifies && !settings::get().tune_lock.is_modifiable() => {
+                            let _ = writeln!(writer, "Locked, unlock with the PIN first");
+                        }
+                        TuneLockCommand::Show => {}
+                        TuneLockCommand::Unlock { pin } => {
+                            if !settings::get().tune_lock.unlock(pin) {
+                                let _ = writeln!(writer, "Wrong PIN");
+                            }
+                        }
+                        TuneLockCommand::Lock => tune_lock::relock(),
+                        TuneLockCommand::Pin { pin } => {
+                            settings::update(|s| s.tune_lock.set_pin(pin));
+                            tune_lock::relock();
+                        }
+                        TuneLockCommand::Range { low: None, .. } => {
+                            settings::update(|s| s.tune_lock.range = None)
+                        }
+                        TuneLockCommand::Range { low, high } => {
+                            match (low.and_then(in_band), high.and_then(in_band)) {
+                                (Some(low), Some(high)) if low <= high => {
+                                    settings::update(|s| s.tune_lock.range = Some((low, high)))
+                                }
+                                _ => {
+                                    let _ = writeln!(
+                                        writer,
+                                        "Give the lowest and highest frequency in MHz, in band"
+                                    );
+                                }
+                            }
+                        }
+                        TuneLockCommand::Allow { frequency } => match in_band(frequency) {
+                            Some(frequency) => {
+                                let mut lock = settings::get().tune_lock;
+                                match lock.allow(frequency) {
+                                    Ok(()) => settings::update(|s| s.tune_lock = lock),
+                                    Err(_) => {
+                                        let _ = writeln!(
+                                            writer,
+                                            "At most {} stations",
+                                            tune_lock::MAX_ALLOWED
+                                        );
+                                    }
+                                }
+                            }
+                            None => {
+                                let _ = writeln!(writer, "Frequency must be in MHz, in band");
+                            }
+                        },
+                        TuneLockCommand::Remove { frequency } => {
+                            match Frequency::parse_mhz(frequency) {
+                                Some(frequency) => {
+                                    let mut lock = settings::get().tune_lock;
+                                    if lock.remove(frequency) {
+                                        settings::update(|s| s.tune_lock = lock);
+                                    } else {
+                                        let _ = writeln!(writer, "Station not permitted");
+                                    }
+                                }
+                                None => {
+                                    let _ = writeln!(writer, "Frequency must be in MHz");
+                                }
+                            }
+                        }
+                        TuneLockCommand::Clear => settings::update(|s| s.tune_lock.clear()),
+                    }
+                    let lock = settings::get().tune_lock;
+                    if lock.is_active() {
+                        let _ = write!(writer, "Tuning limited to");
+                        if let Some((low, high)) = lock.range {
+                            let _ = write!(writer, " {:.2}-{:.2} MHz", low, high);
+                        }
+                        let mut stations = lock.allowed().peekable();
+                        if stations.peek().is_some() {
+                            let _ = write!(
+                                writer,
+                                "{}",
+                                if lock.range.is_some() { " and" } else { "" }
+                            );
+                            for station in stations {
+                                let _ = write!(writer, " {:.2}", station);
+                            }
+                            let _ = write!(writer, " MHz");
+                        }
+                    } else {
+                        let _ = write!(writer, "Tuning not limited");
+                    }
+                    let _ = write!(
+                        writer,
+                        ", {}",
+                        match (lock.has_pin(), lock.is_modifiable()) {
+                            (false, _) => "no PIN",
+                            (true, false) => "changes need the PIN",
+                            (true, true) => "unlocked",
+                        }
+                    );
+                    Ok(())
+                }
+                BaseCommand::Sleep { command } => {
+                    match command {
+                        SleepCommand::Show => {}
+                        SleepCommand::Off => sleep::set(None),
+                        SleepCommand::Set { minutes } => {
+                            sleep::set(Some(Duration::from_secs(minutes as u64 * 60)))
+                        }
+                    }
+                    let _ = match sleep::end() {
+                        Some(end) => {
+                            let left = end.saturating_duration_since(Instant::now()).as_secs();
+                            write!(
+                                cli.writer(),
+                                "Radio switches off in {} min {} s",
+                                left / 60,
+                                left % 60
+                            )
+                        }
+                        None => write!(cli.writer(), "Sleep timer off"),
+                    };
+                    Ok(())
+                }
+                BaseCommand::Stats { command } => {
+                    let writer = cli.writer();
+                    if let StatsCommand::Clear = command
+                        && let Err(err) = stats::clear()
+                    {
+                        let _ = writeln!(writer, "Statistics clear failed: {}", err);
+                    }
+                    let stats = stats::get();
+                    // Whole hours and tenths, without float formatting.
+                    let hours = |seconds: u32| {
+                        let tenths = (seconds + 180) / 360;
+                        (tenths / 10, tenths % 10)
+                    };
+                    let _ = writeln!(writer, "Power cycles: {}", stats.power_cycles);
+                    for band in scan::Band::ALL {
+                        let (whole, tenths) = hours(stats.band_seconds(band));
+                        let _ = writeln!(writer, "{:<3} {:>6}.{} h", band.name(), whole, tenths);
+                    }
+                    for station in stats.stations() {
+                        let _ = match station.band {
+                            scan::Band::Fm => write!(writer, "{:>7.2} MHz", station.frequency()),
+                            scan::Band::Am | scan::Band::Sw => {
+                                write!(writer, "{:>7} kHz", station.frequency().khz())
+                            }
+                        };
+                        let name = match station.band {
+                            scan::Band::Fm => rds_db::find_frequency(station.frequency()),
+                            scan::Band::Am | scan::Band::Sw => None,
+                        };
+                        let (whole, tenths) = hours(station.seconds);
+                        let _ = write!(writer, " {:>6}.{} h", whole, tenths);
+                        let _ = match name {
+                            Some(known) => writeln!(writer, " {}", known.ps),
+                            None => writeln!(writer),
+                        };
+                    }
+                    Ok(())
+                }
+                BaseCommand::Flash {
+                    command: FlashCommand::Stats,
+                } => {
+                    let writer = cli.writer();
+                    for partition in storage::PARTITIONS {
+                        let _ = write!(
+                            writer,
+                            "{:<9} {:>2} pages, {:>6} erases, wear {:>3} %",
+                            partition.name,
+                            partition.pages(),
+                            partition.erases(),
+                            partition.wear_pct()
+                        );
+                        let _ = match partition.free() {
+                            Ok(free) => write!(writer, ", {:>6} bytes free", free),
+                            Err(err) => {
+                                write!(writer, ", free space unknown: {}", Error::from(err))
+                            }
+                        };
+                        let _ = if partition.wear_pct() >= storage::WEAR_WARNING_PCT {
+                            writeln!(writer, " WORN")
+                        } else {
+                            writeln!(writer)
+                        };
+                    }
+                    Ok(())
+                }
+                BaseCommand::Flash {
+                    command:
+                        FlashCommand::Read {
+                            partition,
+                            offset,
+                            len,
+                        },
+                } => {
+                    let writer = cli.writer();
+                    let Some(partition) = storage::find(partition) else {
+                        let _ = write!(writer, "Unknown partition, see flash stats");
+                        return Ok(());
+                    };
+                    if len > FLASH_READ_MAX {
+                        let _ = write!(writer, "At most {} bytes at a time", FLASH_READ_MAX);
+                        return Ok(());
+                    }
+                    if let Err(err) = write_hex_dump(writer, &partition, offset, len) {
+                        let _ = write!(writer, "Flash read failed: {}", err);
+                    }
+                    Ok(())
+                }
+                BaseCommand::Alias { name, expansion } => {
+                    let writer = cli.writer();
+                    match (name, expansion) {
+                        (Some(name), Some(expansion)) => {
+                            if let Err(err) = add_alias(name, expansion) {
+                                let _ = writeln!(writer, "Error: {}", err);
+                            }
+                        }
+                        (Some(_), None) => {
+                            let _ = writeln!(writer, "Usage: alias <name> \"<expansion>\"");
+                        }
+                        (None, _) => {}
+                    }
+                    for entry in settings::get().ui.aliases.aliases() {
+                        let _ = writeln!(writer, "{:<8} {}", entry.name(), entry.expansion());
+                    }
+                    for (name, expansion) in alias::SHORTCUTS {
+                        let _ = writeln!(writer, "{:<8} {} (built in)", name, expansion);
+                    }
+                    Ok(())
+                }
+                BaseCommand::Keypad { state } => {
+                    keypad = state.enabled();
+                    let _ = if keypad {
+                        write!(
+                            cli.writer(),
+                            "Keypad on, type a frequency such as 1017 and Enter"
+                        )
+                    } else {
+                        write!(cli.writer(), "Keypad off")
+                    };
+                    Ok(())
+                }
+                BaseCommand::Interactive => {
+                    keys = Some(interactive::Keys::new());
+                    let _ = write!(
+                        cli.writer(),
+                        "Interactive mode: Left/Right tune, Up/Down volume, Esc or q exits"
+                    );
+                    Ok(())
+                }
+                BaseCommand::Unalias { name } => {
+                    let mut found = false;
+                    settings::update(|s| found = s.ui.aliases.remove(name));
+                    if !found {
+                        let _ = write!(cli.writer(), "No alias {}", name);
+                    }
+                    Ok(())
+                }
+                BaseCommand::Schema => {
+                    let _ = schema::write_json(cli.writer());
+                    Ok(())
+                }
+                BaseCommand::Health => {
+                    let writer = cli.writer();
+                    let _ = write!(writer, "Since boot:");
+                    for counter in Counter::ALL {
+                        let count = health::count(counter);
+                        let _ = write!(
+                            writer,
+                            "\r\n  {:<17}{:>6}, warning at {}{}",
+                            counter.name(),
+                            count,
+                            counter.threshold(),
+                            if count >= counter.threshold() {
+                                " !"
+                            } else {
+                                ""
+                            }
+                        );
+                    }
+                    Ok(())
+                }
+                BaseCommand::Stacks => {
+                    let usage = stack::usage();
+                    let _ = write!(
+                        cli.writer(),
+                        "Main stack: at most {} of {} bytes used ({} %)\r\n\
+                         Tasks and interrupts share it; task state is static",
+                        usage.peak,
+                        usage.size,
+                        usage.peak as u64 * 100 / usage.size as u64
+                    );
+                    Ok(())
+                }
+                BaseCommand::Version { size } => {
+                    let writer = cli.writer();
+                    let _ = write!(
+                        writer,
+                        "{} {}, {} build (opt-level {})",
+                        version::NAME,
+                        version::VERSION,
+                        version::PROFILE,
+                        version::OPT_LEVEL
+                    );
+                    if size {
+                        let used = version::size();
+                        let percent = |used: u32, total: u32| used as u64 * 100 / total as u64;
+                        let _ = write!(
+                            writer,
+                            "\r\nFlash: {} of {} bytes used ({} %)\r\n\
+                             RAM: {} of {} bytes static ({} %), the rest is the main stack",
+                            used.flash,
+                            version::FLASH.length,
+                            percent(used.flash, version::FLASH.length),
+                            used.ram,
+                            version::RAM.length,
+                            percent(used.ram, version::RAM.length)
+                        );
+                    }
+                    Ok(())
+                }
+                BaseCommand::Tasks => {
+                    let writer = cli.writer();
+                    monitor::for_each(|task| {
+                        let _ = writeln!(
+                            writer,
+                            "{:<8} last alive {} ms ago, {} beats",
+                            task.name,
+                            task.last_alive.elapsed().as_millis(),
+                            task.beats
+                        );
+                    });
+                    let (events, events_capacity) = events::event_queue_depth();
+                    let (notifications, notifications_capacity) = events::notify_queue_depth();
+                    let _ = write!(
+                        writer,
+                        "Event queue: {}/{}, notification queue: {}/{}, deferred log queue: {}/{}",
+                        events,
+                        events_capacity,
+                        notifications,
+                        notifications_capacity,
+                        serial_logger::deferred_len(),
+                        serial_logger::DEFERRED_DEPTH
+                    );
+                    Ok(())
+                }
+                BaseCommand::Mode { .. } => {
+                    let _ = write!(
+                        cli.writer(),
+                        "Mode switching is not supported, the receiver stays in FM"
+                    );
+                    Ok(())
+                }
                 BaseCommand::Volume { command } => {
                     match command {
                         VolumeCommand::Up => {
                             let _ = cli.writer().write_str("Volume increased");
-                            events::event_try_send(SystemEvent::RadioVolumeUp);
+                            events::event_try_send(origin, SystemEvent::RadioVolumeUp);
                         }
                         VolumeCommand::Down => {
                             let _ = cli.writer().write_str("Volume decreased");
-                            events::event_try_send(SystemEvent::RadioVolumeDown);
+                            events::event_try_send(origin, SystemEvent::RadioVolumeDown);
                         }
                         VolumeCommand::Set { level } => {
                             let _ = cli
                                 .writer()
                                 .write_fmt(format_args!("Volume set to {}", level));
-                            events::event_try_send(SystemEvent::RadioVolumeSet(level));
+                            events::event_try_send(origin, SystemEvent::RadioVolumeSet(level));
                         }
                     }
                     Ok(())
                 }
+                BaseCommand::Mute { duration } => {
+                    let Some(duration) = duration else {
+                        let _ = write!(cli.writer(), "Muted");
+                        events::event_try_send(origin, SystemEvent::RadioMute);
+                        return Ok(());
+                    };
+                    match mute::parse_duration(duration) {
+                        Some(seconds) => {
+                            let _ = write!(cli.writer(), "Muted for {} s", seconds);
+                            events::event_try_send(origin, SystemEvent::RadioMuteFor(seconds));
+                        }
+                        None => {
+                            let _ = write!(
+                                cli.writer(),
+                                "Duration must be seconds or minutes up to {} s, e.g. 30s or 2m",
+                                mute::MAX_SECONDS
+                            );
+                        }
+                    }
+                    Ok(())
+                }
+                BaseCommand::Unmute => {
+                    let _ = write!(cli.writer(), "Unmuted");
+                    events::event_try_send(origin, SystemEvent::RadioUnmute);
+                    Ok(())
+                }
                 BaseCommand::Tune { command } => {
                     match command {
                         TuneCommand::Up => {
                             let _ = cli.writer().write_str("Tuning up");
-                            events::event_try_send(SystemEvent::RadioSeekUp);
+                            events::event_try_send(origin, SystemEvent::RadioSeekUp);
                         }
                         TuneCommand::Down => {
-                            let _ = cli.writer().write_str("Tuning down not supported");
+                            let _ = cli.writer().write_str("Tuning down");
+                            events::event_try_send(origin, SystemEvent::RadioSeekDown);
                         }
                         TuneCommand::Frequency { frequency } => {
-                            events::event_try_send(SystemEvent::RadioSetFrequency(frequency));
+                            match Frequency::parse_mhz(frequency) {
+                                Some(frequency) => events::event_try_send(
+                                    origin,
+                                    SystemEvent::RadioSetFrequency(frequency),
+                                ),
+                                None => {
+                                    let _ = cli.writer().write_str("Frequency must be in MHz");
+                                }
+                            }
+                        }
+                    }
+                    Ok(())
+                }
+                BaseCommand::Band { command } => {
+                    let writer = cli.writer();
+                    match command {
+                        BandCommand::List => {
+                            for segment in &bandplan::SEGMENTS {
+                                let _ = write!(
+                                    writer,
+                                    "{:<5} {} {:?} ",
+                                    segment.name,
+                                    segment.band.name(),
+                                    segment.kind
+                                );
+                                let _ = match segment.band {
+                                    scan::Band::Fm => {
+                                        write!(writer, "{:.1}-{:.1} MHz", segment.low, segment.high)
+                                    }
+                                    scan::Band::Am | scan::Band::Sw => write!(
+                                        writer,
+                                        "{}-{} kHz",
+                                        segment.low.khz(),
+                                        segment.high.khz()
+                                    ),
+                                };
+                                let _ = writeln!(writer, ", step {} kHz", segment.step_khz());
+                            }
+                        }
+                        BandCommand::Goto { name } => {
+                            let Some(segment) = bandplan::find(name) else {
+                                let _ = write!(writer, "Unknown band, see band list");
+                                return Ok(());
+                            };
+                            if segment.band == scan::Band::Fm {
+                                events::event_try_send(
+                                    origin,
+                                    SystemEvent::RadioSetFrequency(segment.low),
+                                );
+                                let _ = write!(writer, "Tuning {:.1} MHz", segment.low);
+                            } else {
+                                let _ = writeln!(
+                                    writer,
+                                    "{} starts at {} kHz",
+                                    segment.name,
+                                    segment.low.khz()
+                                );
+                                let _ = write!(
+                                    writer,
+                                    "{} reception is not implemented yet",
+                                    segment.band.name()
+                                );
+                            }
+                        }
+                    }
+                    Ok(())
+                }
+                BaseCommand::Survey { command } => {
+                    match command {
+                        SurveyCommand::Start { interval } => {
+                            let interval = interval.unwrap_or(survey::DEFAULT_INTERVAL_S);
+                            survey::start(interval);
+                            let _ = cli
+                                .writer()
+                                .write_fmt(format_args!("Survey started, every {} s", interval));
+                        }
+                        SurveyCommand::Stop => {
+                            survey::stop();
+                            let _ = cli.writer().write_str("Survey stopped");
+                        }
+                        SurveyCommand::Dump => {
+                            let writer = cli.writer();
+                            let _ = writeln!(
+                                writer,
+                                "seq,time_ms,frequency,rssi,snr,multipath,latitude,longitude"
+                            );
+                            let result = survey::dump(|record| {
+                                let _ = write!(
+                                    writer,
+                                    "{},{},{:.2},{},{},{},",
+                                    record.sequence,
+                                    record.timestamp_ms,
+                                    record.frequency,
+                                    record.rssi,
+                                    record.snr,
+                                    record.multipath
+                                );
+                                let _ = match record.position {
+                                    Some((latitude, longitude)) => {
+                                        writeln!(writer, "{},{}", latitude, longitude)
+                                    }
+                                    None => writeln!(writer, ","),
+                                };
+                            });
+                            if let Err(err) = result {
+                                let _ = write!(writer, "Survey dump failed: {}", err);
+                            }
+                        }
+                        SurveyCommand::Clear => match survey::clear() {
+                            Ok(()) => {
+                                let _ = cli.writer().write_str("Survey log erased");
+                            }
+                            Err(err) => {
+                                let _ = write!(cli.writer(), "Survey clear failed: {}", err);
+                            }
+                        },
+                    }
+                    Ok(())
+                }
+                BaseCommand::Capture { command } => {
+                    let writer = cli.writer();
+                    match command {
+                        CaptureCommand::On => match capture::start() {
+                            Ok(()) => {
+                                let _ = writer.write_str("Capture started");
+                            }
+                            Err(err) => {
+                                let _ = write!(writer, "Capture start failed: {}", err);
+                            }
+                        },
+                        CaptureCommand::Off => {
+                            capture::stop();
+                            let _ = writer.write_str("Capture stopped");
+                        }
+                        CaptureCommand::Dump | CaptureCommand::Download
+                            if capture::is_enabled() =>
+                        {
+                            let _ = writer.write_str("Stop capture before dumping");
+                        }
+                        CaptureCommand::Download => {
+                            pending_transfer = Some(TransferJob::CaptureDownload);
+                            let _ = writer.write_str("Start XMODEM download");
+                        }
+                        CaptureCommand::Dump => {
+                            let result = capture::dump(|text| {
+                                for chunk in text.utf8_chunks() {
+                                    let _ = writer.write_str(chunk.valid());
+                                }
+                            });
+                            if let Err(err) = result {
+                                let _ = write!(writer, "Capture dump failed: {}", err);
+                            }
+                        }
+                        CaptureCommand::Status => {
+                            let _ = write!(
+                                writer,
+                                "Capture {}, {} bytes stored, {} dropped",
+                                if capture::is_enabled() { "on" } else { "off" },
+                                capture::stored(),
+                                capture::dropped()
+                            );
+                        }
+                    }
+                    Ok(())
+                }
+                BaseCommand::Gps { command } => {
+                    match command {
+                        GpsCommand::Status => {
+                            let status = gps::status();
+                            let writer = cli.writer();
+                            match (gps::fix(), status.last_fix) {
+                                (Some(fix), _) => {
+                                    let _ = writeln!(
+                                        writer,
+                                        "Fix: {}, {} ({} satellites, UTC {:06})",
+                                        fix.latitude, fix.longitude, fix.satellites, fix.utc
+                                    );
+                                }
+                                (None, Some((_, at))) => {
+                                    let _ = writeln!(
+                                        writer,
+                                        "Fix lost {} s ago",
+                                        at.elapsed().as_secs()
+                                    );
+                                }
+                                (None, None) => {
+                                    let _ = writeln!(writer, "No fix");
+                                }
+                            }
+                            let _ = write!(
+                                writer,
+                                "Sentences: {}, errors: {}",
+                                status.sentences, status.errors
+                            );
                         }
                     }
                     Ok(())
@@ -0,0 +1,343 @@
+//! Wall-clock time learned from RDS.
+//!
+//! The board has no battery-backed clock. Stations that broadcast RDS group
+//! 4A send UTC time and date plus their local offset once a minute; the radio
+//! loop passes each one to [`sync`] and [`now`] extrapolates from the last
+//! one with the embassy timer. The time is lost on reboot and is only as good
+//! as the station's clock.
+//!
+//! Local time follows [`ClockSettings`](crate::settings::ClockSettings):
+//! either the offset the station broadcasts, or a fixed zone offset with an
+//! optional daylight saving rule for receivers listening across a border or
+//! to stations that send a wrong offset.
+
+use core::cell::Cell;
+use core::fmt;
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use embassy_time::Instant;
+use serde::{Deserialize, Serialize};
+
+use crate::rds::ClockTime;
+use crate::settings;
+
+const MS_PER_MINUTE: u64 = 60 * 1000;
+const MS_PER_HOUR: u64 = 60 * MS_PER_MINUTE;
+const MS_PER_DAY: u64 = 24 * MS_PER_HOUR;
+/// Largest accepted zone offset, in minutes.
+const MAX_OFFSET_MINUTES: i16 = 14 * 60;
+
+/// Time formatted as `hh:mm:ss.mmm`, given in milliseconds.
+///
+/// Hours are not wrapped, so the same type displays uptime.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TimeOfDay(pub u64);
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = self.0;
+        write!(
+            f,
+            "{:02}:{:02}:{:02}.{:03}",
+            ms / 3_600_000,
+            ms / 60_000 % 60,
+            ms / 1000 % 60,
+            ms % 1000
+        )
+    }
+}
+
+/// Daylight saving time rule.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DstRule {
+    /// Standard time all year.
+    None,
+    /// European Union: last Sunday of March to last Sunday of October,
+    /// switching at 01:00 UTC.
+    Eu,
+    /// United States and Canada: second Sunday of March to first Sunday of
+    /// November, switching at 02:00 local time.
+    Us,
+}
+
+impl DstRule {
+    pub const ALL: [DstRule; 3] = [DstRule::None, DstRule::Eu, DstRule::Us];
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            DstRule::None => "none",
+            DstRule::Eu => "eu",
+            DstRule::Us => "us",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+
+    /// Whether daylight saving time is in effect at `utc_ms` (ms since MJD
+    /// 0) in a zone `offset` minutes east of UTC.
+    fn active(&self, utc_ms: u64, offset: i16) -> bool {
+        let (year, _, _) = date(utc_ms / MS_PER_DAY);
+        let (start, end) = match self {
+            DstRule::None => return false,
+            DstRule::Eu => {
+                let start = last_sunday(year, 3) * MS_PER_DAY + MS_PER_HOUR;
+                let end = last_sunday(year, 10) * MS_PER_DAY + MS_PER_HOUR;
+                (start, end)
+            }
+            DstRule::Us => {
+                // 02:00 standard time in spring, 02:00 daylight time in autumn.
+                let local = |mjd: u64, hours: i64| {
+                    let ms = (mjd * MS_PER_DAY) as i64 + hours * MS_PER_HOUR as i64;
+                    (ms - offset as i64 * MS_PER_MINUTE as i64) as u64
+                };
+                let start = local(first_sunday(year, 3) + 7, 2);
+                let end = local(first_sunday(year, 11), 1);
+                (start, end)
+            }
+        };
+        (start..end).contains(&utc_ms)
+    }
+}
+
+/// How local time is derived from RDS clock time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeZone {
+    /// Use the local offset sent by the station.
+    Broadcast,
+    /// Fixed offset east of UTC in minutes, plus daylight saving time.
+    Fixed { offset: i16, dst: DstRule },
+}
+
+impl TimeZone {
+    /// Parse an offset written as `utc+h`, `utc-hh:mm` and so on, in
+    /// minutes east of UTC. The prefix keeps the CLI from taking a negative
+    /// offset for an option.
+    pub fn parse_offset(text: &str) -> Option<i16> {
+        let text = text
+            .strip_prefix("utc")
+            .or_else(|| text.strip_prefix("UTC"))?;
+        let (negative, text) = match text.as_bytes().first()? {
+            b'+' => (false, &text[1..]),
+            b'-' => (true, &text[1..]),
+            _ => return None,
+        };
+        let (hours, minutes) = text.split_once(':').unwrap_or((text, "0"));
+        let hours: u8 = hours.parse().ok()?;
+        let minutes: u8 = minutes.parse().ok()?;
+        let offset = hours as i16 * 60 + minutes as i16;
+        if minutes >= 60 || offset > MAX_OFFSET_MINUTES {
+            return None;
+        }
+        Some(if negative { -offset } else { offset })
+    }
+}
+
+impl fmt::Display for TimeZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeZone::Broadcast => f.write_str("broadcast offset"),
+            TimeZone::Fixed { offset, dst } => {
+                write!(f, "{}, DST rule {}", Offset(*offset), dst.name())
+            }
+        }
+    }
+}
+
+/// Offset from UTC in minutes, formatted as `UTC+hh:mm`.
+struct Offset(i16);
+
+impl fmt::Display for Offset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { '-' } else { '+' };
+        let minutes = self.0.unsigned_abs();
+        write!(f, "UTC{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+    }
+}
+
+/// Modified Julian Day of a Gregorian date.
+fn mjd(year: i32, month: u32, day: u32) -> u64 {
+    // Days from civil, shifted so the year starts in March.
+    let y = (if month <= 2 { year - 1 } else { year }) as i64;
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    // 678_881 days from 0000-03-01 to MJD 0 (1858-11-17).
+    (era * 146_097 + doe - 678_881) as u64
+}
+
+/// Gregorian date of a Modified Julian Day.
+fn date(mjd: u64) -> (i32, u32, u32) {
+    let z = mjd as i64 + 678_881;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = (yoe + era * 400 + (month <= 2) as i64) as i32;
+    (year, month, day)
+}
+
+/// Day of the week, 0 for Monday.
+fn weekday(mjd: u64) -> u64 {
+    // MJD 0 was a Wednesday.
+    (mjd + 2) % 7
+}
+
+/// MJD of the first Sunday of `month`.
+fn first_sunday(year: i32, month: u32) -> u64 {
+    let first = mjd(year, month, 1);
+    first + (6 + 7 - weekday(first)) % 7
+}
+
+/// MJD of the last Sunday of `month`.
+fn last_sunday(year: i32, month: u32) -> u64 {
+    let next = if month == 12 {
+        mjd(year + 1, 1, 1)
+    } else {
+        mjd(year, month + 1, 1)
+    };
+    let last = next - 1;
+    last - (weekday(last) + 1) % 7
+}
+
+/// Local date and time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LocalTime {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    pub time: TimeOfDay,
+    /// Offset from UTC in minutes, daylight saving included.
+    pub offset: i16,
+    pub dst: bool,
+}
+
+impl fmt::Display for LocalTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {} {}{}",
+            self.year,
+            self.month,
+            self.day,
+            self.time,
+            Offset(self.offset),
+            if self.dst { " DST" } else { "" }
+        )
+    }
+}
+
+/// Last clock time: when it was received, UTC in ms since MJD 0 and the
+/// broadcast offset in minutes. Logging reads it from any context, hence the
+/// critical section mutex.
+static SYNC: CriticalSectionMutex<Cell<Option<(Instant, u64, i16)>>> =
+    CriticalSectionMutex::new(Cell::new(None));
+
+/// Set the clock from a received clock time.
+pub fn sync(time: ClockTime) {
+    let utc = time.mjd as u64 * MS_PER_DAY
+        + time.hour as u64 * MS_PER_HOUR
+        + time.minute as u64 * MS_PER_MINUTE;
+    let offset = time.offset as i16 * 30;
+    SYNC.lock(|sync| sync.set(Some((Instant::now(), utc, offset))));
+}
+
+/// Current local date and time, once a clock time has been received.
+pub fn local() -> Option<LocalTime> {
+    let (at, utc, broadcast) = SYNC.lock(|sync| sync.get())?;
+    let utc = utc + at.elapsed().as_millis();
+    let (offset, dst) = match settings::get().clock.zone {
+        TimeZone::Broadcast => (broadcast, false),
+        TimeZone::Fixed { offset, dst } if dst.active(utc, offset) => (offset + 60, true),
+        TimeZone::Fixed { offset, .. } => (offset, false),
+    };
+    let local = (utc as i64 + offset as i64 * MS_PER_MINUTE as i64) as u64;
+    let (year, month, day) = date(local / MS_PER_DAY);
+    Some(LocalTime {
+        year,
+        month,
+        day,
+        time: TimeOfDay(local % MS_PER_DAY),
+        offset,
+        dst,
+    })
+}
+
+/// Current local time of day, once a clock time has been received.
+pub fn now() -> Option<TimeOfDay> {
+    local().map(|local| local.time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mjd_of_known_dates() {
+        assert_eq!(mjd(1858, 11, 17), 0);
+        assert_eq!(mjd(2000, 1, 1), 51_544);
+        assert_eq!(mjd(2023, 2, 25), 60_000);
+        assert_eq!(mjd(2024, 2, 29), 60_369);
+        assert_eq!(mjd(2024, 3, 1), 60_370);
+    }
+
+    #[test]
+    fn date_inverts_mjd() {
+        assert_eq!(date(0), (1858, 11, 17));
+        assert_eq!(date(60_369), (2024, 2, 29));
+        for day in 51_000..62_000 {
+            let (year, month, day_of_month) = date(day);
+            assert_eq!(mjd(year, month, day_of_month), day);
+        }
+    }
+
+    #[test]
+    fn first_sunday_of_month() {
+        assert_eq!(date(first_sunday(2024, 3)), (2024, 3, 3));
+        assert_eq!(date(first_sunday(2024, 11)), (2024, 11, 3));
+        // The month starts on a Sunday.
+        assert_eq!(date(first_sunday(2024, 9)), (2024, 9, 1));
+        // The month starts on a Monday.
+        assert_eq!(date(first_sunday(2024, 1)), (2024, 1, 7));
+    }
+
+    #[test]
+    fn last_sunday_of_month() {
+        // The month ends on a Sunday.
+        assert_eq!(date(last_sunday(2024, 3)), (2024, 3, 31));
+        assert_eq!(date(last_sunday(2024, 10)), (2024, 10, 27));
+        assert_eq!(date(last_sunday(2025, 3)), (2025, 3, 30));
+        // December rolls over into the next year.
+        assert_eq!(date(last_sunday(2024, 12)), (2024, 12, 29));
+        // February of a leap year.
+        assert_eq!(date(last_sunday(2024, 2)), (2024, 2, 25));
+    }
+
+    #[test]
+    fn eu_rule_switches_at_one_utc() {
+        let start = mjd(2024, 3, 31) * MS_PER_DAY + MS_PER_HOUR;
+        let end = mjd(2024, 10, 27) * MS_PER_DAY + MS_PER_HOUR;
+        assert!(!DstRule::Eu.active(start - 1, 60));
+        assert!(DstRule::Eu.active(start, 60));
+        assert!(DstRule::Eu.active(end - 1, 60));
+        assert!(!DstRule::Eu.active(end, 60));
+    }
+
+    #[test]
+    fn us_rule_switches_at_two_local() {
+        // Eastern time, UTC-5: 07:00 UTC on 10 March, 06:00 UTC on 3 November.
+        let start = mjd(2024, 3, 10) * MS_PER_DAY + 7 * MS_PER_HOUR;
+        let end = mjd(2024, 11, 3) * MS_PER_DAY + 6 * MS_PER_HOUR;
+        assert!(!DstRule::Us.active(start - 1, -300));
+        assert!(DstRule::Us.active(start, -300));
+        assert!(DstRule::Us.active(end - 1, -300));
+        assert!(!DstRule::Us.active(end, -300));
+        assert!(!DstRule::None.active(start, -300));
+    }
+}
@@ -0,0 +1,1054 @@
+//! Console command grammar.
+//!
+//! The commands the CLI accepts and the byte handling in front of the
+//! parser. Only `embedded_cli` is used here, so the fuzz harness in `fuzz/`
+//! builds this module on the host and feeds it random input.
+
+use embedded_cli::cli::CliHandle;
+use embedded_cli::service::CommandProcessor;
+use embedded_cli::{Command, codes};
+
+/// Delete character, sent by most terminals for the backspace key.
+pub const DEL: u8 = 127;
+
+/// Map a console byte to what the parser expects. The CLI does not handle
+/// DEL, so it is turned into a backspace.
+pub const fn normalize(byte: u8) -> u8 {
+    if byte == DEL { codes::BACKSPACE } else { byte }
+}
+
+#[derive(Debug, Command)]
+pub enum BaseCommand<'a> {
+    /// Switch radio mode
+    Mode {
+        #[command(subcommand)]
+        command: RadioMode,
+    },
+    /// Adjust audio level
+    Volume {
+        #[command(subcommand)]
+        command: VolumeCommand,
+    },
+    /// Mute audio, optionally for a time such as 30s or 2m
+    Mute {
+        /// Time until audio is unmuted again
+        duration: Option<&'a str>,
+    },
+    /// Unmute audio
+    Unmute,
+    /// Seek or set the frequency
+    Tune {
+        #[command(subcommand)]
+        command: TuneCommand<'a>,
+    },
+    /// Band plan
+    Band {
+        #[command(subcommand)]
+        command: BandCommand<'a>,
+    },
+    /// Signal quality logging
+    Survey {
+        #[command(subcommand)]
+        command: SurveyCommand,
+    },
+    /// GPS receiver
+    Gps {
+        #[command(subcommand)]
+        command: GpsCommand,
+    },
+    /// Console session capture
+    Capture {
+        #[command(subcommand)]
+        command: CaptureCommand,
+    },
+    /// Buttons and encoder
+    Input {
+        #[command(subcommand)]
+        command: InputCommand,
+    },
+    /// Audio behaviour
+    Audio {
+        #[command(subcommand)]
+        command: AudioCommand,
+    },
+    /// Capacitive touch pads
+    Touch {
+        #[command(subcommand)]
+        command: TouchCommand,
+    },
+    /// Buzzer feedback
+    Beep {
+        #[command(subcommand)]
+        command: BeepCommand,
+    },
+    /// Status LED and signal strength strip
+    Led {
+        #[command(subcommand)]
+        command: LedCommand,
+    },
+    /// Language of notifications; help and command replies stay English
+    Lang {
+        #[command(subcommand)]
+        command: LangCommand,
+    },
+    /// Chip properties
+    Prop {
+        #[command(subcommand)]
+        command: PropCommand,
+    },
+    /// Reception profiles
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+    /// Station scan
+    Scan {
+        #[command(subcommand)]
+        command: ScanCommand,
+    },
+    /// Tune each frequency briefly and compare their signal quality
+    Probe {
+        /// Frequency in MHz
+        first: &'a str,
+        /// Further frequency in MHz
+        second: Option<&'a str>,
+        /// Further frequency in MHz
+        third: Option<&'a str>,
+        /// Further frequency in MHz
+        fourth: Option<&'a str>,
+        /// Further frequency in MHz
+        fifth: Option<&'a str>,
+        /// Further frequency in MHz
+        sixth: Option<&'a str>,
+        /// Further frequency in MHz
+        seventh: Option<&'a str>,
+        /// Further frequency in MHz
+        eighth: Option<&'a str>,
+    },
+    /// Seek behaviour
+    Seek {
+        #[command(subcommand)]
+        command: SeekCommand<'a>,
+    },
+    /// Frequency calibration
+    Calibrate {
+        #[command(subcommand)]
+        command: CalibrateCommand<'a>,
+    },
+    /// SNR controlled mono override
+    Stereo {
+        #[command(subcommand)]
+        command: StereoCommand,
+    },
+    /// Signal quality stream for plotting tools
+    Telemetry {
+        #[command(subcommand)]
+        command: TelemetryCommand,
+    },
+    /// Stop a running scan or property dump
+    Abort,
+    /// Tuner firmware patch
+    Patch {
+        #[command(subcommand)]
+        command: PatchCommand,
+    },
+    /// Settings file transfer
+    Settings {
+        #[command(subcommand)]
+        command: SettingsCommand,
+    },
+    /// Radio Data System
+    Rds {
+        #[command(subcommand)]
+        command: RdsCommand,
+    },
+    /// Tuner diagnostics
+    Radio {
+        #[command(subcommand)]
+        command: RadioCommand,
+    },
+    /// Periodic chip reads
+    Poll {
+        #[command(subcommand)]
+        command: PollCommand,
+    },
+    /// Console settings
+    Term {
+        #[command(subcommand)]
+        command: TermCommand,
+    },
+    /// Logger settings
+    Log {
+        #[command(subcommand)]
+        command: LogCommand<'a>,
+    },
+    /// Radio event trace
+    Trace {
+        #[command(subcommand)]
+        command: TraceCommand,
+    },
+    /// Replay a recorded event trace
+    Replay {
+        #[command(subcommand)]
+        command: ReplayCommand,
+    },
+    /// Notifications printed on the terminal
+    Notify {
+        #[command(subcommand)]
+        command: NotifyCommand,
+    },
+    /// Wall clock from RDS
+    Clock {
+        #[command(subcommand)]
+        command: ClockCommand<'a>,
+    },
+    /// Wake-up alarm
+    Alarm {
+        #[command(subcommand)]
+        command: AlarmCommand<'a>,
+    },
+    /// Emergency alert override
+    Alert {
+        #[command(subcommand)]
+        command: AlertCommand,
+    },
+    /// Kiosk mode with locked controls
+    Kiosk {
+        #[command(subcommand)]
+        command: KioskCommand<'a>,
+    },
+    /// Limit tuning to permitted frequencies
+    TuneLock {
+        #[command(subcommand)]
+        command: TuneLockCommand<'a>,
+    },
+    /// Sleep timer
+    Sleep {
+        #[command(subcommand)]
+        command: SleepCommand,
+    },
+    /// Usage statistics
+    Stats {
+        #[command(subcommand)]
+        command: StatsCommand,
+    },
+    /// Data partitions in internal flash
+    Flash {
+        #[command(subcommand)]
+        command: FlashCommand<'a>,
+    },
+    /// List aliases, or define one such as `alias t "tune frequency"`
+    Alias {
+        /// Alias name
+        name: Option<&'a str>,
+        /// Command the name stands for, quoted
+        expansion: Option<&'a str>,
+    },
+    /// Remove an alias
+    Unalias {
+        /// Alias name
+        name: &'a str,
+    },
+    /// Step frequency and volume with the arrow keys until Esc
+    Interactive,
+    /// Tune by typing a frequency alone, such as 1017 for 101.7 MHz
+    Keypad {
+        #[command(subcommand)]
+        state: Switch,
+    },
+    /// Print this command description as JSON
+    Schema,
+    /// Show task liveness and event queue depths
+    Tasks,
+    /// Show counters of recovered failures
+    Health,
+    /// Show the main stack high-water mark
+    Stacks,
+    /// Show the firmware version
+    Version {
+        /// Also show flash and RAM usage
+        #[arg(long)]
+        size: bool,
+    },
+    /// Show some status
+    Status {
+        /// Detail to show instead: buffers
+        detail: Option<&'a str>,
+    },
+    /// Save state and restart the firmware
+    Reboot,
+    /// Save state and restart into the bootloader
+    Bootloader {
+        #[command(subcommand)]
+        mode: BootloaderMode,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum BootloaderMode {
+    /// Present a UF2 drive over USB
+    Uf2,
+    /// Accept serial DFU only
+    Serial,
+}
+
+impl<'a> BaseCommand<'a> {
+    /// Processor calling `f` with every parsed command. The derived
+    /// `processor` is private to this module.
+    pub fn handler<W, E, F>(f: F) -> impl CommandProcessor<W, E>
+    where
+        W: embedded_io::Write<Error = E>,
+        E: embedded_io::Error,
+        F: FnMut(&mut CliHandle<'_, W, E>, BaseCommand<'a>) -> Result<(), E>,
+    {
+        Self::processor(f)
+    }
+}
+
+#[derive(Debug, Command)]
+pub enum RadioMode {
+    /// FM Mode
+    FM,
+    /// AM Mode
+    AM,
+    /// Power down the radio
+    Off,
+}
+
+#[derive(Debug, Command)]
+pub enum BandCommand<'a> {
+    /// List the band plan segments
+    List,
+    /// Jump to the start of a segment
+    Goto {
+        /// Segment name, e.g. fm, mw, 31m or 20m
+        name: &'a str,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum TuneCommand<'a> {
+    /// Seek up
+    Up,
+    /// Seek down
+    Down,
+    /// Set frequency
+    Frequency {
+        /// Frequency in MHz
+        frequency: &'a str,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum VolumeCommand {
+    /// Increase volume
+    Up,
+    /// Decrease volume
+    Down,
+    /// Set volume to specific level
+    Set {
+        /// Volume level (0-100)
+        level: u8,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum SurveyCommand {
+    /// Start logging signal quality to flash
+    Start {
+        /// Sampling interval in seconds
+        interval: Option<u16>,
+    },
+    /// Stop logging
+    Stop,
+    /// Print all logged records as CSV
+    Dump,
+    /// Erase the survey log
+    Clear,
+}
+
+#[derive(Debug, Command)]
+pub enum GpsCommand {
+    /// Show GPS receiver and fix state
+    Status,
+}
+
+#[derive(Debug, Command)]
+pub enum InputCommand {
+    /// Show button and encoder settings
+    Show,
+    /// Set button hold delay and auto-repeat interval
+    Repeat {
+        /// Hold time before repeating, in ms
+        delay: u16,
+        /// Time between repeats, in ms
+        interval: u16,
+    },
+    /// Set encoder acceleration
+    Accel {
+        /// Detents faster than this are accelerated, in ms
+        threshold: u16,
+        /// Step multiplier while accelerated
+        multiplier: u8,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum TouchCommand {
+    /// Show touch pad counts and baselines
+    Show,
+    /// Measure new baselines, keep hands off the pads
+    Calibrate,
+    /// Set how far a count must drop to count as a touch
+    Threshold {
+        /// Drop below the baseline in percent
+        percent: u8,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum BeepCommand {
+    /// Show buzzer settings
+    Show,
+    /// Enable feedback beeps
+    On,
+    /// Disable feedback beeps
+    Off,
+    /// Set the beep loudness
+    Volume {
+        /// Loudness from 0 to 10
+        level: u8,
+    },
+    /// Play the confirmation beep
+    Test,
+}
+
+#[derive(Debug, Command)]
+pub enum LedCommand {
+    /// Show the LED settings
+    Show,
+    /// Status LED wiring
+    Polarity {
+        #[command(subcommand)]
+        level: LedPolarity,
+    },
+    /// Set the length of the WS2812 strip
+    Strip {
+        /// Number of LEDs, 0 to 8
+        count: u8,
+    },
+    /// Set the strip brightness
+    Brightness {
+        /// Brightness in percent
+        percent: u8,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum LedPolarity {
+    /// Lit when the pin is high
+    High,
+    /// Lit when the pin is low
+    Low,
+}
+
+#[derive(Debug, Command)]
+pub enum LangCommand {
+    /// Show the notification language
+    Show,
+    /// English
+    En,
+    /// German
+    De,
+}
+
+#[derive(Debug, Command)]
+pub enum PropCommand {
+    /// Print curated chip properties
+    Dump,
+    /// Print properties that differ from power-up defaults
+    Diff,
+}
+
+#[derive(Debug, Command)]
+pub enum ScanCommand {
+    /// Scan the current band for stations
+    Band,
+    /// Scan FM, AM and SW in sequence; only FM is received, AM and SW are skipped
+    All,
+    /// Print the results of the last scan
+    List,
+    /// Export the results of the last scan
+    Export {
+        #[command(subcommand)]
+        command: ScanExportCommand,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum ScanExportCommand {
+    /// Print the results as CSV
+    Csv,
+    /// Send the results as a CSV file over XMODEM
+    Download,
+}
+
+#[derive(Debug, Command)]
+pub enum SeekCommand<'a> {
+    /// Show the seek sensitivity of every band
+    Show,
+    /// Seek both ways and tune the station with the better SNR
+    Best,
+    /// Set how weak a station a seek stops on
+    Sensitivity {
+        /// Band: fm (AM and SW seek are not implemented)
+        band: &'a str,
+        /// Sensitivity: low, normal or high
+        level: &'a str,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum CalibrateCommand<'a> {
+    /// Show the frequency offset of every band
+    Show,
+    /// Set the frequency offset of a band
+    Set {
+        /// Band: fm, am or sw
+        band: &'a str,
+        /// True minus reported frequency in kHz, after -- if negative
+        offset: &'a str,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum StereoCommand {
+    /// Show the mono override and its thresholds
+    Show,
+    /// Force mono on weak stations
+    On,
+    /// Leave stereo blending to the tuner
+    Off,
+    /// Set the SNR thresholds of the mono override
+    Thresholds {
+        /// Mono below this SNR, in dB
+        mono: u8,
+        /// Stereo again from this SNR, in dB
+        stereo: u8,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum TelemetryCommand {
+    /// Stream signal quality lines for plotting tools
+    On {
+        /// Lines per second, 1 to 20
+        rate: u8,
+    },
+    /// Stop the stream
+    Off,
+}
+
+#[derive(Debug, Command)]
+pub enum PatchCommand {
+    /// Receive a firmware patch over XMODEM/YMODEM
+    Upload {
+        /// Image size in bytes, a multiple of 8
+        length: u32,
+        /// CRC-32 of the image, decimal
+        crc: u32,
+    },
+    /// Show the stored firmware patch
+    Status,
+    /// Remove the stored firmware patch
+    Erase,
+}
+
+#[derive(Debug, Command)]
+pub enum SettingsCommand {
+    /// Send the settings as a file over XMODEM
+    Export,
+    /// Replace the settings with a file sent over XMODEM/YMODEM
+    Import,
+}
+
+#[derive(Debug, Command)]
+pub enum RadioCommand {
+    /// Signal diagnostics
+    Diag {
+        #[command(subcommand)]
+        command: DiagCommand,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum DiagCommand {
+    /// Show FM pilot, stereo blend and frequency offset
+    Fm,
+}
+
+#[derive(Debug, Command)]
+pub enum PollCommand {
+    /// Show periodic chip reads
+    Show,
+    /// Signal quality readings
+    Rsq {
+        #[command(subcommand)]
+        command: PollSourceCommand,
+    },
+    /// RDS FIFO reads
+    Rds {
+        #[command(subcommand)]
+        command: PollSourceCommand,
+    },
+    /// Survey samples
+    Survey {
+        #[command(subcommand)]
+        command: PollSourceCommand,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum PollSourceCommand {
+    /// Enable the source
+    On,
+    /// Disable the source
+    Off,
+    /// Set the polling interval
+    Interval {
+        /// Interval in ms
+        interval: u32,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum TermCommand {
+    /// Show or change the console baud rate
+    Baud {
+        /// New rate in baud, confirmed by pressing Enter after the switch
+        rate: Option<u32>,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum LogCommand<'a> {
+    /// Log line layout
+    Format {
+        #[command(subcommand)]
+        command: LogFormatCommand,
+    },
+    /// Allow/deny rules for log targets
+    Filter {
+        #[command(subcommand)]
+        command: LogFilterCommand<'a>,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum TraceCommand {
+    /// Show whether events are recorded and the space used
+    Show,
+    /// Record radio events to flash, across resets
+    On,
+    /// Stop recording
+    Off,
+    /// Print the events of the last session before this boot
+    List,
+    /// Erase the recorded events
+    Clear,
+    /// Log every I2C exchange with the tuner
+    I2c {
+        #[command(subcommand)]
+        state: Switch,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum ReplayCommand {
+    /// Restart and replay the last session before this boot
+    Last,
+}
+
+#[derive(Debug, Command)]
+pub enum LogFilterCommand<'a> {
+    /// List the rules
+    Show,
+    /// Only log targets matching this or another allow rule
+    Allow {
+        /// Module path, `*` matches anything
+        pattern: &'a str,
+    },
+    /// Never log targets matching the pattern
+    Deny {
+        /// Module path, `*` matches anything
+        pattern: &'a str,
+    },
+    /// Remove the rule with this pattern
+    Remove {
+        /// Pattern of the rule
+        pattern: &'a str,
+    },
+    /// Remove all rules
+    Clear,
+}
+
+#[derive(Debug, Command)]
+pub enum LogFormatCommand {
+    /// Show the log line layout
+    Show,
+    /// Timestamp in ms since boot
+    Ms,
+    /// Timestamp as hh:mm:ss.mmm since boot
+    Clock,
+    /// Timestamp as local time from RDS
+    Rds,
+    /// Include the task name
+    Task {
+        #[command(subcommand)]
+        state: Switch,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum NotifyCommand {
+    /// Show which notifications are printed
+    List,
+    /// Choose which notifications are printed
+    Show {
+        #[command(subcommand)]
+        command: NotifyShowCommand,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum ClockCommand<'a> {
+    /// Show the local time received over RDS
+    Show,
+    /// Time zone used for local time
+    Tz {
+        #[command(subcommand)]
+        command: TzCommand<'a>,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum TzCommand<'a> {
+    /// Use the offset sent by the station
+    Broadcast,
+    /// Use a fixed offset and daylight saving rule
+    Set {
+        /// Offset from UTC, e.g. utc+1, utc-5 or utc+5:30
+        offset: &'a str,
+        /// Daylight saving rule: none, eu or us
+        dst: Option<&'a str>,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum KioskCommand<'a> {
+    /// Show the kiosk settings
+    Show,
+    /// Lock the controls from now on and at every boot
+    On {
+        /// Code unlocking the controls
+        code: u32,
+    },
+    /// Leave kiosk mode
+    Off,
+    /// Choose the station tuned at boot
+    Station {
+        /// Frequency in MHz, startup frequency if omitted
+        frequency: Option<&'a str>,
+    },
+    /// Unlock the controls until reset
+    Unlock {
+        /// Code given with kiosk on
+        code: u32,
+    },
+    /// Lock the controls again
+    Lock,
+}
+
+#[derive(Debug, Command)]
+pub enum TuneLockCommand<'a> {
+    /// Show the permitted frequencies
+    Show,
+    /// Allow changes until reset
+    Unlock {
+        /// PIN set with tune-lock pin
+        pin: u32,
+    },
+    /// Require the PIN for changes again
+    Lock,
+    /// Set the PIN needed for changes
+    Pin {
+        /// New PIN, none if omitted
+        pin: Option<u32>,
+    },
+    /// Limit tuning to a sub-band
+    Range {
+        /// Lowest frequency in MHz, no sub-band if omitted
+        low: Option<&'a str>,
+        /// Highest frequency in MHz
+        high: Option<&'a str>,
+    },
+    /// Permit a station
+    Allow {
+        /// Frequency in MHz
+        frequency: &'a str,
+    },
+    /// Stop permitting a station
+    Remove {
+        /// Frequency in MHz
+        frequency: &'a str,
+    },
+    /// Permit every frequency
+    Clear,
+}
+
+#[derive(Debug, Command)]
+pub enum AlertCommand {
+    /// Show the emergency alert settings and state
+    Show,
+    /// React to RDS alarm announcements
+    On,
+    /// Ignore RDS alarm announcements
+    Off,
+    /// Set the volume used for alerts
+    Volume {
+        /// Volume in percent
+        percent: u8,
+    },
+    /// Acknowledge the latched alert
+    Ack,
+    /// Raise an alert from the current station
+    Test,
+}
+
+#[derive(Debug, Command)]
+pub enum AlarmCommand<'a> {
+    /// Show the alarm settings
+    Show,
+    /// Enable the daily alarm
+    On,
+    /// Disable the daily alarm
+    Off,
+    /// Set the alarm time
+    Time {
+        /// Local time as hh:mm
+        time: &'a str,
+    },
+    /// Choose the station tuned by the alarm
+    Station {
+        /// Frequency in MHz, current station if omitted
+        frequency: Option<&'a str>,
+    },
+    /// Set the final alarm volume
+    Volume {
+        /// Volume in percent
+        percent: u8,
+    },
+    /// Set the volume ramp time
+    Ramp {
+        /// Seconds from silence to full volume
+        seconds: u16,
+    },
+    /// Set the snooze length
+    SnoozeTime {
+        /// Snooze length in minutes
+        minutes: u8,
+    },
+    /// Silence the ringing alarm for the snooze time
+    Snooze,
+    /// Stop the ringing alarm
+    Stop,
+}
+
+#[derive(Debug, Command)]
+pub enum SleepCommand {
+    /// Show the time left
+    Show,
+    /// Cancel the sleep timer
+    Off,
+    /// Switch the radio off after a delay
+    Set {
+        /// Delay in minutes
+        minutes: u16,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum StatsCommand {
+    /// Show power cycles and listening time per band and station
+    Listening,
+    /// Reset all statistics
+    Clear,
+}
+
+#[derive(Debug, Command)]
+pub enum FlashCommand<'a> {
+    /// Show free space and erase counts of the data partitions
+    Stats,
+    /// Dump bytes of a partition as hex and ASCII
+    Read {
+        /// Partition name as listed by flash stats
+        partition: &'a str,
+        /// Offset within the partition
+        offset: u32,
+        /// Number of bytes, at most 1024
+        len: u32,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum NotifyShowCommand {
+    /// Periodic signal quality readings
+    Rsq {
+        #[command(subcommand)]
+        state: Switch,
+    },
+    /// RDS PI, PS and station names
+    Rds {
+        #[command(subcommand)]
+        state: Switch,
+    },
+    /// Results of this terminal's own commands
+    Echo {
+        #[command(subcommand)]
+        state: Switch,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum RdsCommand {
+    /// Learned station database
+    Db {
+        #[command(subcommand)]
+        command: RdsDbCommand,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum RdsDbCommand {
+    /// List learned stations
+    List,
+    /// Forget all learned stations
+    Clear,
+}
+
+#[derive(Debug, Command)]
+pub enum CaptureCommand {
+    /// Start capturing console output to flash
+    On,
+    /// Stop capturing
+    Off,
+    /// Replay the captured session
+    Dump,
+    /// Send the captured session over XMODEM
+    Download,
+    /// Show capture state
+    Status,
+}
+
+#[derive(Debug, Command)]
+pub enum AudioCommand {
+    /// Show audio settings
+    Show,
+    /// Set the window for merging volume steps
+    Coalesce {
+        /// Window in ms, 0 to apply every step
+        window: u16,
+    },
+    /// Mute audio while tuning and seeking
+    Tunemute {
+        #[command(subcommand)]
+        state: Switch,
+    },
+    /// Keep audio muted while step tuning until the knob rests
+    Stepmute {
+        /// Rest time in ms, 0 to disable
+        debounce: u16,
+    },
+    /// Choose how volume percent maps to chip steps
+    Curve {
+        #[command(subcommand)]
+        curve: AudioCurve,
+    },
+    /// Limit the volume
+    Max {
+        /// Maximum volume in percent
+        percent: u8,
+    },
+    /// Set the left/right balance
+    Balance {
+        #[command(subcommand)]
+        side: BalanceSide,
+    },
+    /// Level the volume across stations
+    Leveling {
+        #[command(subcommand)]
+        command: LevelingCommand,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum LevelingCommand {
+    /// List the learned station volume offsets
+    List,
+    /// Learn and apply station volume offsets
+    On,
+    /// Stop applying station volume offsets
+    Off,
+    /// Forget the learned offsets
+    Clear,
+}
+
+#[derive(Debug, Command)]
+pub enum BalanceSide {
+    /// Both channels at full level
+    Center,
+    /// Attenuate the right channel
+    Left {
+        /// Amount from 0 to 10, 10 mutes the right channel
+        amount: u8,
+    },
+    /// Attenuate the left channel
+    Right {
+        /// Amount from 0 to 10, 10 mutes the left channel
+        amount: u8,
+    },
+}
+
+#[derive(Debug, Command)]
+pub enum AudioCurve {
+    /// Steps proportional to the percentage
+    Linear,
+    /// Fine steps at low volume
+    Soft,
+}
+
+#[derive(Debug, Command)]
+pub enum Switch {
+    /// Enable
+    On,
+    /// Disable
+    Off,
+}
+
+impl Switch {
+    pub fn enabled(&self) -> bool {
+        matches!(self, Switch::On)
+    }
+}
+
+#[derive(Debug, Command)]
+pub enum ProfileCommand {
+    /// Show the active reception profile
+    Show,
+    /// Weak stations: early mono blend, soft-mute and hi-cut
+    Dx,
+    /// Power-up defaults
+    Normal,
+    /// Strong stations: keep stereo and full bandwidth
+    Local,
+}
@@ -0,0 +1,47 @@
+//! Compile-time product configuration.
+//!
+//! `build.rs` generates the constants below from `config.toml`, or from the
+//! file named by the `RADIO_CONFIG` environment variable, so product variants
+//! differ in a configuration file instead of code edits. They are defaults
+//! the firmware starts from: runtime settings changed from the CLI are kept
+//! in flash and take precedence where they overlap.
+
+use crate::frequency::Frequency;
+
+/// Broadcast region the product is built for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Region {
+    /// ITU region 1: Europe, Africa and the Middle East.
+    Eu,
+    /// The Americas.
+    Us,
+    Japan,
+}
+
+impl Region {
+    /// FM band limits.
+    pub const fn fm_band(&self) -> (Frequency, Frequency) {
+        match self {
+            Region::Eu | Region::Us => (Frequency::from_khz(87_500), Frequency::from_khz(108_000)),
+            Region::Japan => (Frequency::from_khz(76_000), Frequency::from_khz(95_000)),
+        }
+    }
+
+    /// FM channel spacing, in kHz.
+    pub const fn fm_step_khz(&self) -> u32 {
+        match self {
+            Region::Eu | Region::Japan => 100,
+            Region::Us => 200,
+        }
+    }
+
+    /// `FM_DEEMPHASIS` value: 1 for 50 µs, 2 for 75 µs.
+    pub const fn fm_deemphasis(&self) -> u16 {
+        match self {
+            Region::Eu | Region::Japan => 1,
+            Region::Us => 2,
+        }
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/config.rs"));
@@ -6,12 +6,33 @@
 //! touching the HAL types directly. Output is protected by a
 //! `critical_section::Mutex` to keep logging cheap and safe in interrupt
 //! contexts.
+//!
+//! Text output is multiplexed to the UART and, once [`rtt_init`] ran, to an
+//! RTT up channel, so `probe-rs attach` shows the same console as the serial
+//! terminal. The RTT channel never blocks: without a probe reading it, output
+//! beyond the buffer is dropped.
+//!
+//! A product with a control head runs a second CLI on another UARTE. Its
+//! output goes through [`Terminal::Remote`] to that UART only: it is not
+//! mirrored, captured or suspended with the console.
 
 use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use critical_section::Mutex;
 use embedded_io::Write;
 
 use embassy_nrf::uarte;
+use rtt_target::{UpChannel, rtt_init};
+
+use crate::capture;
+use crate::error::Error;
+
+/// Baud rate the console starts with.
+pub const DEFAULT_BAUD: u32 = 115_200;
+/// Baud rates accepted by [`set_baud`].
+pub const BAUD_RATES: [u32; 9] = [
+    9_600, 19_200, 38_400, 57_600, 115_200, 230_400, 460_800, 921_600, 1_000_000,
+];
 
 /// Thin wrapper that stores a shared UARTE TX handle and exposes a
 /// `Write`-compatible API.
@@ -20,6 +41,17 @@ struct SerialPort<'a>(&'a Mutex<RefCell<Option<uarte::UarteTx<'a>>>>);
 static WRITER_MUTEX: Mutex<RefCell<Option<uarte::UarteTx<'static>>>> =
     Mutex::new(RefCell::new(None));
 static WRITER_OUT: SerialPort = SerialPort(&WRITER_MUTEX);
+/// TX half of the UARTE running the remote CLI.
+static REMOTE_MUTEX: Mutex<RefCell<Option<uarte::UarteTx<'static>>>> =
+    Mutex::new(RefCell::new(None));
+/// Size of the RTT mirror buffer.
+const RTT_BUFFER_SIZE: usize = 1024;
+
+static RTT_CHANNEL: Mutex<RefCell<Option<UpChannel>>> = Mutex::new(RefCell::new(None));
+/// Set while a binary transfer owns the UART.
+static SUSPENDED: AtomicBool = AtomicBool::new(false);
+/// Current baud rate of the console UART.
+static BAUD: AtomicU32 = AtomicU32::new(DEFAULT_BAUD);
 
 pub mod console_colors {
     #![allow(dead_code)]
@@ -50,6 +82,82 @@ pub fn stdout_get() -> StdOut {
     StdOut
 }
 
+/// Drop all text output (logs, CLI, notifications) until [`resume`], so a
+/// binary transfer can use the UART through [`write_raw`].
+pub fn suspend() {
+    SUSPENDED.store(true, Ordering::Relaxed);
+}
+
+/// Re-enable text output after [`suspend`].
+pub fn resume() {
+    SUSPENDED.store(false, Ordering::Relaxed);
+}
+
+/// Write bytes to the UART even while text output is suspended.
+///
+/// The bytes are not copied into the session capture.
+pub fn write_raw(buf: &[u8]) {
+    critical_section::with(|cs| {
+        if let Some(tx) = WRITER_MUTEX.borrow_ref_mut(cs).as_mut() {
+            let _ = tx.blocking_write(buf);
+        }
+    });
+}
+
+/// UARTE setting for `rate`, if the peripheral supports it.
+pub fn baudrate(rate: u32) -> Option<uarte::Baudrate> {
+    Some(match rate {
+        9_600 => uarte::Baudrate::BAUD9600,
+        19_200 => uarte::Baudrate::BAUD19200,
+        38_400 => uarte::Baudrate::BAUD38400,
+        57_600 => uarte::Baudrate::BAUD57600,
+        115_200 => uarte::Baudrate::BAUD115200,
+        230_400 => uarte::Baudrate::BAUD230400,
+        460_800 => uarte::Baudrate::BAUD460800,
+        921_600 => uarte::Baudrate::BAUD921600,
+        1_000_000 => uarte::Baudrate::BAUD1M,
+        _ => return None,
+    })
+}
+
+/// Current baud rate of the console UART.
+pub fn baud() -> u32 {
+    BAUD.load(Ordering::Relaxed)
+}
+
+/// Switch the console UART to `rate`.
+///
+/// TX and RX share the peripheral's baud rate register, so this also changes
+/// the rate the CLI receives at. Runs in a critical section, so it never
+/// lands in the middle of a write.
+pub fn set_baud(rate: u32) -> Result<(), Error> {
+    let baudrate = baudrate(rate).ok_or(Error::InvalidArgument)?;
+    critical_section::with(|cs| {
+        if let Some(tx) = WRITER_MUTEX.borrow_ref_mut(cs).as_mut() {
+            tx.set_baudrate(baudrate);
+        }
+    });
+    BAUD.store(rate, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Create the RTT up channel console output is mirrored to.
+///
+/// Call this once during startup, before anything is logged.
+pub fn rtt_init() {
+    let channels = rtt_init! {
+        up: {
+            0: {
+                size: RTT_BUFFER_SIZE,
+                name: "Terminal"
+            }
+        }
+    };
+    critical_section::with(|cs| {
+        RTT_CHANNEL.borrow_ref_mut(cs).replace(channels.up.0);
+    });
+}
+
 /// Install the TX half of a configured UARTE instance as the global writer.
 ///
 /// Call this once during startup after the peripheral has been initialized.
@@ -57,6 +165,13 @@ pub fn stdout_init(tx: uarte::UarteTx<'static>) {
     WRITER_OUT.init(tx);
 }
 
+/// Install the TX half of the UARTE running the remote CLI.
+pub fn remote_init(tx: uarte::UarteTx<'static>) {
+    critical_section::with(|cs| {
+        REMOTE_MUTEX.borrow_ref_mut(cs).replace(tx);
+    });
+}
+
 impl<'a> SerialPort<'a> {
     /// Store the provided TX handle and emit a leading newline so that early
     /// logs start on a clean line. Safe to call only once during boot.
@@ -66,17 +181,26 @@ impl<'a> SerialPort<'a> {
             self.write(b"\n").ok();
         });
     }
-    /// Write a buffer to the UART if it has been initialized.
+    /// Write a buffer to the UART and the RTT mirror, whichever have been
+    /// initialized.
     ///
     /// The function always returns `Ok(buf.len())`; if UART TX is not yet
     /// installed the bytes are silently dropped. This keeps logging sites
-    /// lightweight and failure-tolerant during early boot.
+    /// lightweight and failure-tolerant during early boot. Output is also
+    /// dropped while the console is suspended.
     fn write(&self, buf: &[u8]) -> Result<usize, uarte::Error> {
+        if SUSPENDED.load(Ordering::Relaxed) {
+            return Ok(buf.len());
+        }
         critical_section::with(|cs| {
             // This code runs within a critical section.
             if let Some(tx) = self.0.borrow_ref_mut(cs).as_mut() {
                 let _ = tx.blocking_write(buf);
             }
+            if let Some(channel) = RTT_CHANNEL.borrow_ref_mut(cs).as_mut() {
+                channel.write(buf);
+            }
+            capture::tee(buf);
             Ok(buf.len())
         })
     }
@@ -106,3 +230,41 @@ impl core::fmt::Write for StdOut {
         Ok(())
     }
 }
+
+/// Terminal a CLI instance talks to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Terminal {
+    /// The console UART, shared with the log.
+    Console,
+    /// The UART installed with [`remote_init`].
+    Remote,
+}
+
+impl embedded_io::ErrorType for Terminal {
+    type Error = uarte::Error;
+}
+
+impl embedded_io::Write for Terminal {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        match self {
+            Terminal::Console => WRITER_OUT.write(buf),
+            Terminal::Remote => critical_section::with(|cs| {
+                if let Some(tx) = REMOTE_MUTEX.borrow_ref_mut(cs).as_mut() {
+                    let _ = tx.blocking_write(buf);
+                }
+                Ok(buf.len())
+            }),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl core::fmt::Write for Terminal {
+    fn write_str(&mut self, s: &str) -> Result<(), core::fmt::Error> {
+        let _ = self.write(s.as_bytes());
+        Ok(())
+    }
+}
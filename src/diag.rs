@@ -0,0 +1,23 @@
+//! Signal diagnostics.
+//!
+//! Values come from `FM_RSQ_STATUS`. The Si473x family reports whether the
+//! stereo pilot is present, the stereo/mono blend and the frequency offset,
+//! but not the modulation deviation or pilot level; those need an Si474x-class
+//! part and are reported as unavailable.
+
+/// Snapshot of the FM received signal quality.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FmDiagnostics {
+    /// 19 kHz stereo pilot detected.
+    pub pilot: bool,
+    /// Stereo share of the blend, in percent (0 is mono).
+    pub stereo_blend: u8,
+    /// Offset of the carrier from the tuned frequency, in kHz.
+    pub frequency_offset: i8,
+    /// Received signal strength, in dBµV.
+    pub rssi: u8,
+    /// Signal-to-noise ratio, in dB.
+    pub snr: u8,
+    /// Multipath indicator, 0 (none) to 100.
+    pub multipath: u8,
+}
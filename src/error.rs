@@ -0,0 +1,76 @@
+//! Crate-wide error type.
+//!
+//! Fallible operations in the radio loop, the persistence layer and the
+//! background tasks return [`Error`] so failures can be propagated with `?`,
+//! logged once by the caller and reported to the user as
+//! [`SystemNotify::Error`](crate::events::SystemNotify::Error) instead of
+//! panicking.
+
+use core::fmt;
+
+use crate::storage::StorageError;
+
+/// Errors surfaced by the firmware.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// Communication with the tuner over I2C failed.
+    I2c,
+    /// The tuner or the host did not complete an operation in time.
+    Timeout,
+    /// The requested frequency is outside the current band.
+    InvalidFrequency,
+    /// The fitted Si47xx part or firmware build does not support the operation.
+    NotSupportedByChip,
+    /// Accessing a flash partition failed.
+    Flash(StorageError),
+    /// An argument is out of range.
+    InvalidArgument,
+    /// Received or stored data does not match its checksum.
+    Checksum,
+    /// A file transfer was cancelled or gave up after repeated errors.
+    Transfer,
+    /// The operation was stopped with an abort request.
+    Aborted,
+    /// The frequency is outside the tuning lock.
+    Locked,
+    /// A fixed-size table has no room left.
+    Full,
+}
+
+impl<E> From<si473x::Error<E>> for Error {
+    /// Convert a tuner driver error. A timeout stays one; anything the
+    /// driver reports besides bus failures means the chip rejected the
+    /// command.
+    fn from(err: si473x::Error<E>) -> Self {
+        match err {
+            si473x::Error::I2c(_) => Error::I2c,
+            si473x::Error::Timeout => Error::Timeout,
+            _ => Error::NotSupportedByChip,
+        }
+    }
+}
+
+impl From<StorageError> for Error {
+    fn from(err: StorageError) -> Self {
+        Error::Flash(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::I2c => f.write_str("tuner communication failed"),
+            Error::Timeout => f.write_str("timed out"),
+            Error::InvalidFrequency => f.write_str("frequency out of band"),
+            Error::NotSupportedByChip => f.write_str("not supported by this tuner"),
+            Error::Flash(err) => write!(f, "flash access failed ({:?})", err),
+            Error::InvalidArgument => f.write_str("invalid argument"),
+            Error::Checksum => f.write_str("checksum mismatch"),
+            Error::Transfer => f.write_str("file transfer aborted"),
+            Error::Aborted => f.write_str("aborted"),
+            Error::Locked => f.write_str("frequency locked"),
+            Error::Full => f.write_str("no room left"),
+        }
+    }
+}
@@ -11,15 +11,36 @@
 //! - Use [`notify_publisher`] and [`notify_subscriber`] for publishing and subscribing to notifications.
 //!
 //! The channels are implemented using Embassy's async synchronization primitives.
+//! Every published notification is also folded into the [`state`] snapshot,
+//! which late subscribers can read instead of waiting for the next change.
+//! Subscribers receive notifications wrapped in a [`Delivery`] carrying a
+//! sequence number and the time they were published.
+
+use core::cell::Cell;
+use core::sync::atomic::{AtomicU32, Ordering};
 
 use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
 use embassy_sync::channel::Channel;
-use embassy_sync::pubsub::{PubSubChannel, Publisher, Subscriber};
+use embassy_sync::pubsub::{PubSubChannel, Publisher, Subscriber, WaitResult};
+use embassy_time::Instant;
+use serde::{Deserialize, Serialize};
+
+use si473x::Si47xxRevision;
 
-use si473x::{Si47xxRevision, Si47xxTuneStatus};
+use crate::diag::FmDiagnostics;
+use crate::error::Error;
+use crate::frequency::{Frequency, TuneStatus};
+use crate::health::{self, Counter};
+use crate::properties::{Profile, PropertyValue};
+use crate::rds::ProgramService;
+use crate::rds_db::Station;
+use crate::scan::{Band, ScanResult};
+use crate::state::{self, RadioState};
 
 /// Events representing user actions or commands for the radio system.
-#[derive(Debug, Copy, Clone, PartialEq)]
+///
+/// Serializable so the [`trace`](crate::trace) can store and replay them.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SystemEvent {
     /// Turn on FM radio.
     RadioFmOn,
@@ -31,27 +52,142 @@ pub enum SystemEvent {
     RadioSeekUp,
     /// Seek down to the previous station.
     RadioSeekDown,
-    /// Set radio frequency (in MHz for FM, kHz for AM).
-    RadioSetFrequency(f32),
+    /// Find the nearest stations above and below and tune the one with the
+    /// better SNR.
+    RadioSeekBest,
+    /// Set radio frequency.
+    RadioSetFrequency(Frequency),
+    /// Move the frequency by the given number of channel steps.
+    RadioTuneStep(i16),
     /// Mute audio output.
     RadioMute,
     /// Unmute audio output.
     RadioUnmute,
+    /// Mute audio output for the given number of seconds, then unmute.
+    RadioMuteFor(u16),
     /// Increase volume by one step.
     RadioVolumeUp,
     /// Decrease volume by one step.
     RadioVolumeDown,
     /// Set volume to a specific value.
     RadioVolumeSet(u8),
+    /// Move the volume gradually to `level` percent over `duration_ms`.
+    RadioVolumeRamp { level: u8, duration_ms: u32 },
+    /// Stop a running volume ramp at the current level.
+    RadioVolumeRampStop,
+    /// Lower the volume to the configured maximum if it is above.
+    RadioVolumeLimit,
+    /// Apply the left/right balance setting.
+    RadioBalance,
+    /// Read back all curated chip properties.
+    RadioPropertyDump,
+    /// Read back curated chip properties and report those differing from defaults.
+    RadioPropertyDiff,
+    /// Scan the current band, or FM, AM and SW in sequence when `all_bands` is set.
+    RadioScan { all_bands: bool },
+    /// Apply a reception profile.
+    RadioProfile(Profile),
+    /// Apply the seek sensitivity settings.
+    RadioSeekSensitivity,
+    /// Read FM signal diagnostics.
+    RadioDiagFm,
+    /// Read the signal quality on each frequency set with
+    /// [`probe::set`](crate::probe::set), then retune the current one.
+    RadioProbe,
+    /// Apply the stereo override setting.
+    RadioStereo,
+}
+
+impl SystemEvent {
+    /// Whether the event retunes the receiver and may sweep through noise.
+    pub const fn is_tuning(&self) -> bool {
+        matches!(
+            self,
+            SystemEvent::RadioSeekUp
+                | SystemEvent::RadioSeekDown
+                | SystemEvent::RadioSeekBest
+                | SystemEvent::RadioSetFrequency(_)
+                | SystemEvent::RadioTuneStep(_)
+                | SystemEvent::RadioScan { .. }
+                | SystemEvent::RadioProbe
+        )
+    }
+
+    /// Long-running operation the event starts, see [`SystemNotify::Progress`].
+    pub const fn operation(&self) -> Option<Operation> {
+        match self {
+            SystemEvent::RadioSeekUp | SystemEvent::RadioSeekDown | SystemEvent::RadioSeekBest => {
+                Some(Operation::Seek)
+            }
+            SystemEvent::RadioScan { .. } => Some(Operation::Scan),
+            SystemEvent::RadioProbe => Some(Operation::Probe),
+            _ => None,
+        }
+    }
+}
+
+/// Operation taking long enough for its progress to be shown.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operation {
+    Seek,
+    Scan,
+    Probe,
+    Transfer,
+}
+
+impl Operation {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Operation::Seek => "seek",
+            Operation::Scan => "scan",
+            Operation::Probe => "probe",
+            Operation::Transfer => "transfer",
+        }
+    }
+}
+
+/// Where an event came from, and with it the notifications it caused.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// The radio loop itself: startup, timers and periodic reads.
+    Radio,
+    /// The CLI on the console UART.
+    Console,
+    /// The CLI on the remote control head UART.
+    Remote,
+    /// Buttons, the rotary encoder and touch pads.
+    Input,
+    /// Requests through the [`service`](crate::service).
+    Service,
+    /// Events replayed from the [`trace`](crate::trace).
+    Replay,
+    /// Other firmware tasks, such as brownout handling.
+    System,
+}
+
+impl Origin {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Origin::Radio => "radio",
+            Origin::Console => "console",
+            Origin::Remote => "remote",
+            Origin::Input => "input",
+            Origin::Service => "service",
+            Origin::Replay => "replay",
+            Origin::System => "system",
+        }
+    }
 }
 
 /// Notifications representing status updates or responses from the radio hardware.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum SystemNotify {
     /// Current tuning status (frequency, signal, etc).
-    TuneStatus(Si47xxTuneStatus),
+    TuneStatus(TuneStatus),
     /// Hardware revision information.
     RevisionInfo(Si47xxRevision),
+    /// Bringing the tuner up failed; it is tried again in `retry_s` seconds.
+    RadioInitFailed { attempt: u8, retry_s: u16 },
     /// FM radio has been turned on.
     RadioFmOn,
     /// AM radio has been turned on.
@@ -62,45 +198,197 @@ pub enum SystemNotify {
     RadioMute,
     /// Audio output has been unmuted.
     RadioUnmute,
-    /// Volume has changed to the given value.
+    /// Seconds left until a timed mute ends.
+    MuteCountdown(u16),
+    /// Volume has changed to the given value, in percent.
     VolumeChanged(u8),
+    /// Value of a chip property read by a dump or diff.
+    Property(PropertyValue),
+    /// A property dump or diff has finished.
+    PropertyDumpDone { total: u8, modified: u8 },
+    /// A scan found a station.
+    ScanStation(ScanResult),
+    /// A scan skipped a band the tuner cannot receive.
+    ScanBandSkipped(Band),
+    /// A scan has finished; results are available from the scan module.
+    ScanDone,
+    /// A probe has finished; readings are available from the probe module.
+    ProbeDone,
+    /// A reception profile has been applied.
+    ProfileApplied(Profile),
+    /// RDS program identification code of the tuned station.
+    RdsPi(u16),
+    /// RDS program service name of the tuned station.
+    RdsPs(ProgramService),
+    /// Station name recalled from the RDS database before PS is decoded.
+    RdsStation(Station),
+    /// FM signal diagnostics requested with `RadioDiagFm`.
+    FmDiagnostics(FmDiagnostics),
+    /// Periodic signal quality reading.
+    SignalQuality(FmDiagnostics),
+    /// A long-running operation has started, or with `None` has finished.
+    Progress(Option<Operation>),
+    /// A file transfer has moved the given number of bytes so far.
+    TransferProgress(u32),
+    /// An emergency alert from the station with the given PI code was latched.
+    AlertRaised(Option<u16>),
+    /// The latched emergency alert was acknowledged.
+    AlertCleared,
+    /// A health counter reached its warning threshold.
+    HealthWarning(Counter),
+    /// An operation requested by an event failed.
+    Error(Error),
+}
+
+/// A notification as delivered to subscribers.
+///
+/// Every published notification takes the next sequence number, so
+/// subscribers can spot gaps and order notifications from different
+/// publishers by when they were published. Notifications the radio loop
+/// publishes while handling an event carry that event's origin.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Delivery {
+    /// Position among the notifications published since boot, wrapping.
+    pub sequence: u32,
+    /// Time of publishing.
+    pub time: Instant,
+    pub origin: Origin,
+    pub notification: SystemNotify,
+}
+
+/// Sequence number of the next notification.
+static SEQUENCE: AtomicU32 = AtomicU32::new(0);
+
+impl Delivery {
+    fn new(origin: Origin, notification: SystemNotify) -> Self {
+        Self {
+            sequence: SEQUENCE.fetch_add(1, Ordering::Relaxed),
+            time: Instant::now(),
+            origin,
+            notification,
+        }
+    }
 }
 
 /// Notification channel for broadcasting system notifications.
-static NOTIFICATION_CHANNEL: PubSubChannel<ThreadModeRawMutex, SystemNotify, 4, 4, 2> =
+static NOTIFICATION_CHANNEL: PubSubChannel<ThreadModeRawMutex, Delivery, 4, 4, 2> =
     PubSubChannel::new();
 /// Event channel for sending system events.
-static EVENT_CHANNEL: Channel<ThreadModeRawMutex, SystemEvent, 1> = Channel::new();
+static EVENT_CHANNEL: Channel<ThreadModeRawMutex, (Origin, SystemEvent), 1> = Channel::new();
 
 /// Asynchronously send a system event to the event channel.
-pub async fn event_send(state: SystemEvent) {
-    EVENT_CHANNEL.send(state).await;
+pub async fn event_send(origin: Origin, state: SystemEvent) {
+    EVENT_CHANNEL.send((origin, state)).await;
 }
 
 /// Try to send a system event to the event channel without blocking.
 ///
-/// If the channel is full, the event is dropped.
-pub fn event_try_send(state: SystemEvent) {
-    EVENT_CHANNEL.try_send(state).ok();
+/// If the channel is full, the event is dropped and counted.
+pub fn event_try_send(origin: Origin, state: SystemEvent) {
+    if EVENT_CHANNEL.try_send((origin, state)).is_err() {
+        health::add(Counter::EventDrops, 1);
+    }
 }
 
-/// Asynchronously receive the next system event from the event channel.
-pub async fn event_receive() -> SystemEvent {
+/// Asynchronously receive the next system event and its origin from the event
+/// channel.
+pub async fn event_receive() -> (Origin, SystemEvent) {
     EVENT_CHANNEL.receive().await
 }
 
+/// Number of queued events and the event channel capacity.
+pub fn event_queue_depth() -> (usize, usize) {
+    (EVENT_CHANNEL.len(), EVENT_CHANNEL.capacity())
+}
+
+/// Number of queued notifications and the notification channel capacity.
+pub fn notify_queue_depth() -> (usize, usize) {
+    (NOTIFICATION_CHANNEL.len(), NOTIFICATION_CHANNEL.capacity())
+}
+
+/// What a [`NotifySubscriber`] receives next.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Received {
+    Notification(Delivery),
+    /// The subscriber fell behind and `missed` notifications were displaced
+    /// before it read them. `state` is the current snapshot to re-sync from.
+    Lagged {
+        missed: u64,
+        state: RadioState,
+    },
+}
+
+/// Subscriber for system notifications that reports missed ones.
+pub struct NotifySubscriber<'a> {
+    subscriber: Subscriber<'a, ThreadModeRawMutex, Delivery, 4, 4, 2>,
+}
+
+impl NotifySubscriber<'_> {
+    /// Wait for the next notification, or for the news that some were missed.
+    pub async fn next(&mut self) -> Received {
+        match self.subscriber.next_message().await {
+            WaitResult::Message(delivery) => Received::Notification(delivery),
+            WaitResult::Lagged(missed) => {
+                health::add(Counter::NotificationLag, missed as u32);
+                Received::Lagged {
+                    missed,
+                    state: state::get(),
+                }
+            }
+        }
+    }
+}
+
 /// Create a new subscriber for system notifications.
 ///
-/// Returns a [`Subscriber`] that can receive notifications published to the notification channel.
-pub fn notify_subscriber<'a>()
--> Result<Subscriber<'a, ThreadModeRawMutex, SystemNotify, 4, 4, 2>, embassy_sync::pubsub::Error> {
-    NOTIFICATION_CHANNEL.subscriber()
+/// Returns a [`NotifySubscriber`] that can receive notifications published to
+/// the notification channel.
+pub fn notify_subscriber<'a>() -> Result<NotifySubscriber<'a>, embassy_sync::pubsub::Error> {
+    NOTIFICATION_CHANNEL
+        .subscriber()
+        .map(|subscriber| NotifySubscriber { subscriber })
+}
+
+/// Publish a notification without waiting, displacing the oldest one if the
+/// channel is full.
+///
+/// For tasks that must not block on their own subscribers.
+pub fn notify_immediate(origin: Origin, notification: SystemNotify) {
+    state::apply(&notification);
+    NOTIFICATION_CHANNEL
+        .immediate_publisher()
+        .publish_immediate(Delivery::new(origin, notification));
+}
+
+/// Publisher for system notifications that keeps the [`state`] snapshot
+/// current.
+pub struct Notifier<'a> {
+    publisher: Publisher<'a, ThreadModeRawMutex, Delivery, 4, 4, 2>,
+    /// Origin of the event being handled, given to published notifications.
+    origin: Cell<Origin>,
+}
+
+impl Notifier<'_> {
+    /// Publish a notification, waiting for room in the channel.
+    pub async fn publish(&self, notification: SystemNotify) {
+        state::apply(&notification);
+        self.publisher
+            .publish(Delivery::new(self.origin.get(), notification))
+            .await;
+    }
+
+    /// Attribute the following notifications to `origin`.
+    pub fn set_origin(&self, origin: Origin) {
+        self.origin.set(origin);
+    }
 }
 
 /// Create a new publisher for system notifications.
 ///
-/// Returns a [`Publisher`] that can send notifications to all subscribers.
-pub fn notify_publisher<'a>()
--> Result<Publisher<'a, ThreadModeRawMutex, SystemNotify, 4, 4, 2>, embassy_sync::pubsub::Error> {
-    NOTIFICATION_CHANNEL.publisher()
+/// Returns a [`Notifier`] that can send notifications to all subscribers.
+pub fn notify_publisher<'a>() -> Result<Notifier<'a>, embassy_sync::pubsub::Error> {
+    Ok(Notifier {
+        publisher: NOTIFICATION_CHANNEL.publisher()?,
+        origin: Cell::new(Origin::Radio),
+    })
 }
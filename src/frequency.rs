@@ -0,0 +1,179 @@
+//! Integer frequencies.
+//!
+//! Frequencies are kept as whole kHz in a [`Frequency`] everywhere above the
+//! tuner driver, which takes and reports MHz as `f32`. The conversion happens
+//! once, where the radio loop talks to the chip through
+//! [`calibration`](crate::calibration), and rounds to the kHz, so a station
+//! on 101.7 MHz never turns into 101.69999 and frequencies compare exactly.
+//!
+//! [`Frequency`] formats as MHz with integer arithmetic, so the firmware needs
+//! no float formatting code: two decimals by default (`101.70`), or as many as
+//! a precision asks for (`{:.1}` gives `101.7`). AM and SW frequencies are
+//! shown in kHz from [`Frequency::khz`]. [`Frequency::parse_mhz`] reads them
+//! back from text without float parsing.
+
+use core::fmt::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+/// A frequency in kHz.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Frequency(u32);
+
+impl Frequency {
+    pub const fn from_khz(khz: u32) -> Self {
+        Self(khz)
+    }
+
+    pub const fn khz(self) -> u32 {
+        self.0
+    }
+
+    /// Frequency `khz` kHz away, saturating at zero.
+    pub const fn offset(self, khz: i32) -> Self {
+        Self(self.0.saturating_add_signed(khz))
+    }
+
+    /// Frequency reported by the tuner driver in MHz, rounded to the kHz.
+    pub fn from_mhz_f32(mhz: f32) -> Self {
+        // Negative values saturate to zero.
+        Self((mhz * 1000.0 + 0.5) as u32)
+    }
+
+    /// Frequency in MHz for the tuner driver.
+    pub fn mhz_f32(self) -> f32 {
+        self.0 as f32 / 1000.0
+    }
+
+    /// Parse a frequency in MHz with up to three decimals, such as `101.7`.
+    pub fn parse_mhz(text: &str) -> Option<Self> {
+        let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+        if whole.is_empty() || fraction.len() > 3 {
+            return None;
+        }
+        let mut khz = whole.parse::<u32>().ok()?.checked_mul(1000)?;
+        for (scale, digit) in [100, 10, 1].into_iter().zip(fraction.bytes()) {
+            if !digit.is_ascii_digit() {
+                return None;
+            }
+            khz += (digit - b'0') as u32 * scale;
+        }
+        Some(Self(khz))
+    }
+}
+
+impl fmt::Display for Frequency {
+    /// MHz with two decimals, or with the precision given, up to three.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let decimals = f.precision().unwrap_or(2).min(3) as u32;
+        let scale = 10u32.pow(3 - decimals);
+        // Rounded to the last digit shown.
+        let value = (self.0 + scale / 2) / scale;
+        let unit = 10u32.pow(decimals);
+        let mut text = heapless::String::<16>::new();
+        if decimals == 0 {
+            write!(text, "{}", value)?;
+        } else {
+            let width = decimals as usize;
+            write!(text, "{}.{:0width$}", value / unit, value % unit)?;
+        }
+        // Numbers align right unless asked otherwise.
+        let pad = f.width().unwrap_or(0).saturating_sub(text.len());
+        let left = f.align() == Some(fmt::Alignment::Left);
+        if !left {
+            for _ in 0..pad {
+                f.write_char(' ')?;
+            }
+        }
+        f.write_str(&text)?;
+        if left {
+            for _ in 0..pad {
+                f.write_char(' ')?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Tune status reported by the chip, with the true frequency.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TuneStatus {
+    pub frequency: Frequency,
+    /// Received signal strength in dBµV.
+    pub rssi: u8,
+    /// Signal-to-noise ratio in dB.
+    pub snr: u8,
+    /// Multipath indicator.
+    pub multipath: u8,
+    /// Whether the channel holds a valid station.
+    pub valid: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(args: fmt::Arguments) -> heapless::String<32> {
+        let mut text = heapless::String::new();
+        text.write_fmt(args).unwrap();
+        text
+    }
+
+    fn parse(text: &str) -> Option<u32> {
+        Frequency::parse_mhz(text).map(Frequency::khz)
+    }
+
+    #[test]
+    fn parse_mhz_decimals() {
+        assert_eq!(parse("101.7"), Some(101_700));
+        assert_eq!(parse("101.75"), Some(101_750));
+        assert_eq!(parse("0.531"), Some(531));
+        assert_eq!(parse("98"), Some(98_000));
+        assert_eq!(parse("98."), Some(98_000));
+    }
+
+    #[test]
+    fn parse_mhz_rejects_malformed() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse(".5"), None);
+        assert_eq!(parse("101.7525"), None);
+        assert_eq!(parse("101.7x"), None);
+        assert_eq!(parse("1o1.7"), None);
+        assert_eq!(parse("-1.0"), None);
+        assert_eq!(parse("4294968.0"), None);
+    }
+
+    #[test]
+    fn display_rounds_to_precision() {
+        let frequency = Frequency::from_khz(101_695);
+        assert_eq!(text(format_args!("{}", frequency)), "101.70");
+        assert_eq!(text(format_args!("{:.1}", frequency)), "101.7");
+        assert_eq!(text(format_args!("{:.3}", frequency)), "101.695");
+        assert_eq!(text(format_args!("{:.5}", frequency)), "101.695");
+        assert_eq!(
+            text(format_args!("{:.0}", Frequency::from_khz(99_500))),
+            "100"
+        );
+        assert_eq!(
+            text(format_args!("{:.2}", Frequency::from_khz(531))),
+            "0.53"
+        );
+    }
+
+    #[test]
+    fn display_pads_to_width() {
+        let frequency = Frequency::from_khz(98_000);
+        assert_eq!(text(format_args!("{:8}|", frequency)), "   98.00|");
+        assert_eq!(text(format_args!("{:<8}|", frequency)), "98.00   |");
+        assert_eq!(text(format_args!("{:>8.1}|", frequency)), "    98.0|");
+        assert_eq!(text(format_args!("{:3}", frequency)), "98.00");
+    }
+
+    #[test]
+    fn driver_mhz_round_trip() {
+        assert_eq!(Frequency::from_mhz_f32(101.7).khz(), 101_700);
+        assert_eq!(Frequency::from_mhz_f32(101.69999).khz(), 101_700);
+        assert_eq!(Frequency::from_mhz_f32(-1.0).khz(), 0);
+        assert_eq!(Frequency::from_khz(87_500).mhz_f32(), 87.5);
+    }
+}
@@ -0,0 +1,207 @@
+//! NMEA GPS receiver on a spare UART.
+//!
+//! [`gps_task`] reads NMEA 0183 sentences byte by byte, verifies their
+//! checksum and decodes `GGA` sentences from any talker (`$GPGGA`, `$GNGGA`,
+//! ...). The latest position is kept in a shared cell so other subsystems can
+//! stamp their data with [`fix`], which only returns positions that are recent
+//! enough to trust.
+//!
+//! Only survey records use the position. The RDS decoder does not follow
+//! alternative frequencies (AF), and choosing a regional variant by location
+//! would also need the transmitter sites, so that is not done.
+
+use core::cell::Cell;
+use core::fmt;
+
+use embassy_nrf::uarte;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_time::{Duration, Instant};
+use log::warn;
+
+use crate::buffers::{self, Buffer};
+use crate::health::{self, Counter};
+use crate::monitor;
+
+/// Age after which a position is considered stale.
+pub const FIX_TIMEOUT: Duration = Duration::from_secs(5);
+
+const MAX_SENTENCE_LEN: usize = 96;
+
+/// Angle in millionths of a degree, positive north/east.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Degrees(pub i32);
+
+impl fmt::Display for Degrees {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let value = self.0.unsigned_abs();
+        write!(f, "{sign}{}.{:06}", value / 1_000_000, value % 1_000_000)
+    }
+}
+
+/// A decoded position fix.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Fix {
+    pub latitude: Degrees,
+    pub longitude: Degrees,
+    /// Number of satellites used for the fix.
+    pub satellites: u8,
+    /// UTC time of the fix as `hhmmss`.
+    pub utc: u32,
+}
+
+/// Receiver state shown by `gps status`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GpsStatus {
+    /// Last valid fix and when it was received.
+    pub last_fix: Option<(Fix, Instant)>,
+    /// Sentences that passed the checksum.
+    pub sentences: u32,
+    /// Sentences dropped because of a bad checksum or framing.
+    pub errors: u32,
+}
+
+static STATUS: Mutex<ThreadModeRawMutex, Cell<GpsStatus>> = Mutex::new(Cell::new(GpsStatus {
+    last_fix: None,
+    sentences: 0,
+    errors: 0,
+}));
+
+/// Current receiver state.
+pub fn status() -> GpsStatus {
+    STATUS.lock(|status| status.get())
+}
+
+/// Latest position if it is younger than [`FIX_TIMEOUT`].
+pub fn fix() -> Option<Fix> {
+    match status().last_fix {
+        Some((fix, at)) if at.elapsed() < FIX_TIMEOUT => Some(fix),
+        _ => None,
+    }
+}
+
+fn update(f: impl FnOnce(&mut GpsStatus)) {
+    STATUS.lock(|status| {
+        let mut value = status.get();
+        f(&mut value);
+        status.set(value);
+    });
+}
+
+fn hex_digit(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|digit| digit as u8)
+}
+
+/// Strip `$` and `*hh`, verify the checksum and return the sentence body.
+fn checked_body(line: &[u8]) -> Option<&[u8]> {
+    let line = line.strip_prefix(b"$")?;
+    let star = line.iter().position(|&b| b == b'*')?;
+    let (body, tail) = line.split_at(star);
+    let expected = hex_digit(*tail.get(1)?)? << 4 | hex_digit(*tail.get(2)?)?;
+    let actual = body.iter().fold(0u8, |acc, &b| acc ^ b);
+    (actual == expected).then_some(body)
+}
+
+/// Parse a decimal string into a fixed-point value with `scale` fractional digits.
+fn parse_fixed(field: &str, scale: u32) -> Option<i64> {
+    let (int, frac) = field.split_once('.').unwrap_or((field, ""));
+    let mut value: i64 = int.parse().ok()?;
+    let mut digits = frac.bytes();
+    for _ in 0..scale {
+        let digit = match digits.next() {
+            Some(b) if b.is_ascii_digit() => (b - b'0') as i64,
+            Some(_) => return None,
+            None => 0,
+        };
+        value = value * 10 + digit;
+    }
+    Some(value)
+}
+
+/// Convert an NMEA `(d)ddmm.mmmm` coordinate and hemisphere into [`Degrees`].
+fn parse_coordinate(field: &str, hemisphere: &str) -> Option<Degrees> {
+    let raw = parse_fixed(field, 6)?;
+    let degrees = raw / 100_000_000;
+    let minutes = raw % 100_000_000;
+    let value = (degrees * 1_000_000 + minutes / 60) as i32;
+    match hemisphere {
+        "N" | "E" => Some(Degrees(value)),
+        "S" | "W" => Some(Degrees(-value)),
+        _ => None,
+    }
+}
+
+/// Decode a `GGA` sentence body. Returns `Some(None)` for a valid sentence
+/// without a fix and `None` for anything that is not a well-formed `GGA`.
+fn parse_gga(body: &str) -> Option<Option<Fix>> {
+    let mut fields = body.split(',');
+    if !fields.next()?.ends_with("GGA") {
+        return None;
+    }
+    let utc = fields.next()?;
+    let (lat, lat_hemi) = (fields.next()?, fields.next()?);
+    let (lon, lon_hemi) = (fields.next()?, fields.next()?);
+    let quality: u8 = fields.next()?.parse().ok()?;
+    let satellites = fields.next()?.parse().unwrap_or(0);
+    if quality == 0 {
+        return Some(None);
+    }
+    Some(Some(Fix {
+        latitude: parse_coordinate(lat, lat_hemi)?,
+        longitude: parse_coordinate(lon, lon_hemi)?,
+        satellites,
+        utc: (parse_fixed(utc, 0)?) as u32,
+    }))
+}
+
+fn handle_sentence(line: &[u8]) {
+    let Some(body) = checked_body(line).and_then(|body| core::str::from_utf8(body).ok()) else {
+        update(|status| status.errors = status.errors.wrapping_add(1));
+        return;
+    };
+    let fix = parse_gga(body);
+    update(|status| {
+        status.sentences = status.sentences.wrapping_add(1);
+        if let Some(Some(fix)) = fix {
+            status.last_fix = Some((fix, Instant::now()));
+        }
+    });
+}
+
+/// Receive NMEA sentences from the GPS module and track the latest fix.
+#[embassy_executor::task]
+pub async fn gps_task(mut rx: uarte::UarteRx<'static>) {
+    let mut line: heapless::Vec<u8, MAX_SENTENCE_LEN> = heapless::Vec::new();
+    let mut byte = [0u8; 1];
+    let heartbeat = monitor::register("gps");
+    loop {
+        let result = rx.read(&mut byte).await;
+        heartbeat.beat();
+        if let Err(err) = result {
+            health::add(Counter::UartErrors, 1);
+            warn!("GPS UART read failed: {:?}", err);
+            continue;
+        }
+        match byte[0] {
+            b'$' => {
+                line.clear();
+                let _ = line.push(b'$');
+            }
+            b'\r' | b'\n' => {
+                if !line.is_empty() {
+                    handle_sentence(&line);
+                    line.clear();
+                }
+            }
+            _ if line.is_empty() => {}
+            b => {
+                if line.push(b).is_err() {
+                    buffers::overflowed(Buffer::GpsSentence);
+                    update(|status| status.errors = status.errors.wrapping_add(1));
+                    line.clear();
+                }
+            }
+        }
+    }
+}
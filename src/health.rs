@@ -0,0 +1,74 @@
+//! Health counters.
+//!
+//! Failures the firmware recovers from on its own leave no trace once they
+//! are handled: a failed chip transaction is reported once, a dropped event
+//! is gone. Each kind is counted here since boot, `health` summarizes the
+//! counters and a [`SystemNotify::HealthWarning`] is published once a
+//! counter reaches its [`Counter::threshold`], so a degrading unit shows up
+//! before users notice.
+//!
+//! The tuner driver does not retry failed I2C transactions, so the I2C
+//! counter counts failed ones. There is no hardware watchdog to report near
+//! misses of; `tasks` shows how long ago each task was last alive instead.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::events::{self, Origin, SystemNotify};
+
+/// A kind of recovered failure.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Counter {
+    /// I2C transactions with the tuner that failed.
+    I2cErrors,
+    /// Events dropped because the event channel was full.
+    EventDrops,
+    /// Notifications displaced before a subscriber read them.
+    NotificationLag,
+    /// Read errors on the console or GPS UART.
+    UartErrors,
+}
+
+impl Counter {
+    pub const ALL: [Counter; 4] = [
+        Counter::I2cErrors,
+        Counter::EventDrops,
+        Counter::NotificationLag,
+        Counter::UartErrors,
+    ];
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Counter::I2cErrors => "I2C errors",
+            Counter::EventDrops => "event drops",
+            Counter::NotificationLag => "notification lag",
+            Counter::UartErrors => "UART errors",
+        }
+    }
+
+    /// Count at which a warning is published.
+    pub const fn threshold(&self) -> u32 {
+        match self {
+            Counter::I2cErrors => 10,
+            Counter::EventDrops => 20,
+            Counter::NotificationLag => 50,
+            Counter::UartErrors => 10,
+        }
+    }
+}
+
+static COUNTS: [AtomicU32; Counter::ALL.len()] = [const { AtomicU32::new(0) }; Counter::ALL.len()];
+
+/// Add `n` to `counter`, warning when it reaches its threshold. Thread mode
+/// only, as the warning is published.
+pub fn add(counter: Counter, n: u32) {
+    let before = COUNTS[counter as usize].fetch_add(n, Ordering::Relaxed);
+    let threshold = counter.threshold();
+    if before < threshold && before.saturating_add(n) >= threshold {
+        events::notify_immediate(Origin::System, SystemNotify::HealthWarning(counter));
+    }
+}
+
+/// Count of `counter` since boot.
+pub fn count(counter: Counter) -> u32 {
+    COUNTS[counter as usize].load(Ordering::Relaxed)
+}
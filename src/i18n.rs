@@ -0,0 +1,150 @@
+//! Translated user interface strings.
+//!
+//! Human-readable messages are looked up by [`Text`] in the language chosen
+//! with `lang` and stored in [`UiSettings`](crate::settings::UiSettings).
+//! Strings are plain fragments without placeholders: callers keep formatting
+//! numbers and names with `write!` and put the fragments around them, so no
+//! runtime format parser is needed. Technical terms (RSSI, SNR, PI, PS) and
+//! units stay untranslated.
+
+use serde::{Deserialize, Serialize};
+
+use crate::settings;
+
+/// Languages of the user interface.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    En,
+    De,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::En, Language::De];
+
+    /// ISO 639-1 code, as used by the `lang` command.
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::De => "de",
+        }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Language::En => "English",
+            Language::De => "Deutsch",
+        }
+    }
+}
+
+/// A translatable message fragment.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Text {
+    SwitchedToAm,
+    SwitchedToFm,
+    RadioOff,
+    TunedTo,
+    Default,
+    PropertiesRead,
+    DifferFromDefaults,
+    Found,
+    BandSkipped,
+    ScanDone,
+    ReceptionProfile,
+    Applied,
+    Station,
+    Remembered,
+    Pilot,
+    Present,
+    Absent,
+    StereoBlend,
+    FrequencyOffset,
+    Multipath,
+    DeviationNotReported,
+    Stereo,
+    Transferred,
+    Bytes,
+    Error,
+    Notification,
+    EmergencyAlert,
+    AcknowledgeAlert,
+    AlertAcknowledged,
+    RadioNotResponding,
+    Attempt,
+    RetryingIn,
+    Health,
+    Reached,
+    SeeHealth,
+    ProbeHeader,
+    NoStation,
+    NotProbed,
+}
+
+impl Text {
+    /// English and German text.
+    const fn translations(&self) -> [&'static str; 2] {
+        match self {
+            Text::SwitchedToAm => ["Switched to AM mode", "AM-Empfang aktiv"],
+            Text::SwitchedToFm => ["Switched to FM mode", "UKW-Empfang aktiv"],
+            Text::RadioOff => ["Radio powered off", "Radio ausgeschaltet"],
+            Text::TunedTo => ["Tuned to frequency", "Abgestimmt auf"],
+            Text::Default => ["default", "Standard"],
+            Text::PropertiesRead => ["properties read", "Eigenschaften gelesen"],
+            Text::DifferFromDefaults => ["differ from defaults", "weichen vom Standard ab"],
+            Text::Found => ["Found", "Gefunden"],
+            Text::BandSkipped => [
+                "band not supported, skipped",
+                "Band nicht unterstützt, übersprungen",
+            ],
+            Text::ScanDone => ["Scan done:", "Suchlauf fertig:"],
+            Text::ReceptionProfile => ["Reception profile", "Empfangsprofil"],
+            Text::Applied => ["applied", "aktiviert"],
+            Text::Station => ["Station", "Sender"],
+            Text::Remembered => ["remembered", "gespeichert"],
+            Text::Pilot => ["Pilot", "Pilotton"],
+            Text::Present => ["present", "vorhanden"],
+            Text::Absent => ["absent", "fehlt"],
+            Text::StereoBlend => ["Stereo blend", "Stereo-Überblendung"],
+            Text::FrequencyOffset => ["Frequency offset", "Frequenzablage"],
+            Text::Multipath => ["multipath", "Mehrwege"],
+            Text::DeviationNotReported => [
+                "Deviation and pilot level: not reported by this tuner",
+                "Hub und Pilotpegel: von diesem Tuner nicht gemeldet",
+            ],
+            Text::Stereo => ["stereo", "Stereo"],
+            Text::Transferred => ["Transferred", "Übertragen:"],
+            Text::Bytes => ["bytes", "Bytes"],
+            Text::Error => ["Error", "Fehler"],
+            Text::Notification => ["Notification", "Meldung"],
+            Text::EmergencyAlert => ["EMERGENCY ALERT", "NOTFALLWARNUNG"],
+            Text::AcknowledgeAlert => [
+                "press a button or enter 'alert ack'",
+                "Taste drücken oder 'alert ack' eingeben",
+            ],
+            Text::AlertAcknowledged => ["Alert acknowledged", "Warnung bestätigt"],
+            Text::RadioNotResponding => ["Radio not responding", "Radio antwortet nicht"],
+            Text::Attempt => ["attempt", "Versuch"],
+            Text::RetryingIn => ["retrying in", "neuer Versuch in"],
+            Text::Health => ["Health", "Zustand"],
+            Text::Reached => ["reached", "erreicht"],
+            Text::SeeHealth => ["see health", "siehe health"],
+            // Column widths match the probe rows.
+            Text::ProbeHeader => [
+                "      MHz  RSSI  SNR  Multipath  Stereo",
+                "      MHz  RSSI  SNR   Mehrwege  Stereo",
+            ],
+            Text::NoStation => ["no station", "kein Sender"],
+            Text::NotProbed => ["not probed", "nicht geprüft"],
+        }
+    }
+
+    /// The text in `language`.
+    pub const fn get(&self, language: Language) -> &'static str {
+        self.translations()[language as usize]
+    }
+}
+
+/// The current user interface language.
+pub fn language() -> Language {
+    settings::get().ui.language
+}
@@ -0,0 +1,184 @@
+//! Register-level trace of the tuner's I2C traffic.
+//!
+//! The tuner's bus device is wrapped in [`Traced`]. While `trace i2c on` is
+//! set, every transaction with the chip is logged after it completes: the
+//! address, the command bytes written and the response bytes read, in hex.
+//! Logging goes out over the UART, which is far slower than the bus, so at
+//! most [`MAX_PER_SECOND`] transactions are logged per second and the number
+//! skipped is reported with the next logged one.
+//!
+//! Tracing is meant for debugging protocol issues and is not persisted.
+//! Failed transactions are counted in [`health`](crate::health) either way.
+
+use core::cell::Cell;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_time::{Duration, Instant};
+use embedded_hal_async::i2c::{ErrorType, I2c, Operation, SevenBitAddress};
+use log::info;
+
+use crate::health::{self, Counter};
+
+/// Transactions logged per second at most.
+pub const MAX_PER_SECOND: u32 = 20;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Transactions logged and skipped in the current second.
+#[derive(Copy, Clone)]
+struct Window {
+    start: Instant,
+    logged: u32,
+    skipped: u32,
+}
+
+static WINDOW: Mutex<ThreadModeRawMutex, Cell<Window>> = Mutex::new(Cell::new(Window {
+    start: Instant::from_ticks(0),
+    logged: 0,
+    skipped: 0,
+}));
+
+/// Whether transactions are logged.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Start or stop logging transactions.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether the next transaction may be logged, with the number skipped
+/// since the last one that was.
+fn admit() -> Option<u32> {
+    WINDOW.lock(|window| {
+        let mut w = window.get();
+        let now = Instant::now();
+        if now - w.start >= Duration::from_secs(1) {
+            w.start = now;
+            w.logged = 0;
+        }
+        let admitted = if w.logged < MAX_PER_SECOND {
+            w.logged += 1;
+            Some(core::mem::take(&mut w.skipped))
+        } else {
+            w.skipped += 1;
+            None
+        };
+        window.set(w);
+        admitted
+    })
+}
+
+/// Bytes as space separated hex.
+struct Hex<'a>(&'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{:02X}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// The operations of a transaction, writes marked `W` and reads `R`.
+struct Operations<'a, 'b>(&'a [Operation<'b>]);
+
+impl fmt::Display for Operations<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for operation in self.0 {
+            match operation {
+                Operation::Write(bytes) => write!(f, " W {}", Hex(bytes))?,
+                Operation::Read(bytes) => write!(f, " R {}", Hex(bytes))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Count a failed transaction and log a completed one, if tracing and within
+/// the rate limit.
+fn log<E: fmt::Debug>(address: u8, operations: &[Operation<'_>], result: &Result<(), E>) {
+    if result.is_err() {
+        health::add(Counter::I2cErrors, 1);
+    }
+    if !is_enabled() {
+        return;
+    }
+    let Some(skipped) = admit() else {
+        return;
+    };
+    if skipped > 0 {
+        info!("I2C: {} transactions not logged", skipped);
+    }
+    match result {
+        Ok(()) => info!("I2C {:02X}{}", address, Operations(operations)),
+        Err(err) => info!(
+            "I2C {:02X}{} failed: {:?}",
+            address,
+            Operations(operations),
+            err
+        ),
+    }
+}
+
+/// I2C device logging its transactions while tracing is enabled.
+pub struct Traced<I> {
+    inner: I,
+}
+
+impl<I> Traced<I> {
+    pub const fn new(inner: I) -> Self {
+        Self { inner }
+    }
+}
+
+impl<I: ErrorType> ErrorType for Traced<I> {
+    type Error = I::Error;
+}
+
+impl<I: I2c> I2c for Traced<I> {
+    async fn read(&mut self, address: SevenBitAddress, read: &mut [u8]) -> Result<(), Self::Error> {
+        let result = self.inner.read(address, read).await;
+        log(address, &[Operation::Read(read)], &result);
+        result
+    }
+
+    async fn write(&mut self, address: SevenBitAddress, write: &[u8]) -> Result<(), Self::Error> {
+        let result = self.inner.write(address, write).await;
+        log(address, &[Operation::Write(write)], &result);
+        result
+    }
+
+    async fn write_read(
+        &mut self,
+        address: SevenBitAddress,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let result = self.inner.write_read(address, write, read).await;
+        log(
+            address,
+            &[Operation::Write(write), Operation::Read(read)],
+            &result,
+        );
+        result
+    }
+
+    async fn transaction(
+        &mut self,
+        address: SevenBitAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let result = self.inner.transaction(address, operations).await;
+        log(address, operations, &result);
+        result
+    }
+}
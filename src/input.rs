@@ -0,0 +1,85 @@
+//! Front panel buttons and rotary encoder.
+//!
+//! Every button runs its own [`button_task`] that sends a fixed
+//! [`SystemEvent`] on press and keeps repeating it while the button is held.
+//! [`encoder_task`] decodes a quadrature encoder into
+//! [`SystemEvent::RadioTuneStep`] events and multiplies the step when the knob
+//! is spun fast. Timing for both is read from [`settings::InputSettings`] on
+//! every press, so changes apply immediately. A button press only
+//! acknowledges a latched emergency alert, or snoozes a ringing alarm.
+
+use embassy_futures::select::{Either, select};
+use embassy_nrf::gpio::Input;
+use embassy_time::{Duration, Instant, Timer};
+
+use crate::alarm;
+use crate::alert;
+use crate::events::{self, Origin, SystemEvent};
+use crate::kiosk;
+use crate::monitor;
+use crate::settings;
+
+/// Debounce time applied after every button edge.
+const DEBOUNCE: Duration = Duration::from_millis(20);
+
+/// Send `event` on press and auto-repeat it while the button is held.
+///
+/// Buttons are active low.
+#[embassy_executor::task(pool_size = 4)]
+pub async fn button_task(mut button: Input<'static>, event: SystemEvent) {
+    let heartbeat = monitor::register("button");
+    loop {
+        button.wait_for_low().await;
+        heartbeat.beat();
+        Timer::after(DEBOUNCE).await;
+        if button.is_high() {
+            continue;
+        }
+        if alert::acknowledge() || alarm::snooze() {
+            button.wait_for_high().await;
+            Timer::after(DEBOUNCE).await;
+            continue;
+        }
+        if kiosk::blocks(&event) {
+            button.wait_for_high().await;
+            Timer::after(DEBOUNCE).await;
+            continue;
+        }
+        let config = settings::get().input;
+        events::event_send(Origin::Input, event).await;
+        let mut hold = Duration::from_millis(config.repeat_delay_ms as u64);
+        while let Either::Second(_) = select(button.wait_for_high(), Timer::after(hold)).await {
+            events::event_send(Origin::Input, event).await;
+            hold = Duration::from_millis(config.repeat_interval_ms as u64);
+        }
+        Timer::after(DEBOUNCE).await;
+    }
+}
+
+/// Decode a quadrature encoder into tuning steps with acceleration.
+///
+/// Each falling edge of `a` is one detent; the level of `b` at that moment
+/// gives the direction.
+#[embassy_executor::task]
+pub async fn encoder_task(mut a: Input<'static>, b: Input<'static>) {
+    let heartbeat = monitor::register("encoder");
+    let mut last_detent = Instant::now();
+    loop {
+        a.wait_for_falling_edge().await;
+        heartbeat.beat();
+        let direction: i16 = if b.is_high() { 1 } else { -1 };
+        let config = settings::get().input;
+        let now = Instant::now();
+        let fast = now - last_detent < Duration::from_millis(config.accel_threshold_ms as u64);
+        last_detent = now;
+        let steps = if fast {
+            direction * config.accel_multiplier as i16
+        } else {
+            direction
+        };
+        let event = SystemEvent::RadioTuneStep(steps);
+        if !kiosk::blocks(&event) {
+            events::event_try_send(Origin::Input, event);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Interactive tuning.
+//!
+//! `interactive` switches the CLI task from reading command lines to reading
+//! single keys: the left and right arrow keys step the frequency by one
+//! channel and the up and down arrow keys the volume, at once and without
+//! Enter, until Esc, `q` or Ctrl-C hands the terminal back to the parser.
+//!
+//! Terminals send the arrow keys as escape sequences (`ESC [ A` for up), so a
+//! lone Esc can only be told apart from the start of a sequence by nothing
+//! following it: the CLI task waits [`ESCAPE_TIMEOUT`] for the next byte
+//! while [`Keys::pending`] and then calls [`Keys::timeout`].
+
+use embassy_time::Duration;
+
+use crate::events::SystemEvent;
+
+/// Time after an Esc within which the rest of an escape sequence arrives.
+pub const ESCAPE_TIMEOUT: Duration = Duration::from_millis(50);
+
+const ESC: u8 = 0x1b;
+const CTRL_C: u8 = 0x03;
+
+/// Keys acted on in interactive mode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    Right,
+    Left,
+    /// Leave interactive mode.
+    Exit,
+}
+
+impl Key {
+    /// Event sent for the key.
+    pub const fn event(self) -> Option<SystemEvent> {
+        match self {
+            Key::Up => Some(SystemEvent::RadioVolumeUp),
+            Key::Down => Some(SystemEvent::RadioVolumeDown),
+            Key::Right => Some(SystemEvent::RadioTuneStep(1)),
+            Key::Left => Some(SystemEvent::RadioTuneStep(-1)),
+            Key::Exit => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum State {
+    Idle,
+    /// Esc read, a sequence may follow.
+    Escape,
+    /// Inside a control sequence, before its final byte.
+    Sequence,
+}
+
+/// Decoder of keys from input bytes.
+pub struct Keys {
+    state: State,
+}
+
+impl Keys {
+    pub const fn new() -> Self {
+        Self { state: State::Idle }
+    }
+
+    /// Decode one input byte, returning the key it completes.
+    pub fn feed(&mut self, byte: u8) -> Option<Key> {
+        let (state, key) = match (self.state, byte) {
+            (State::Idle, ESC) => (State::Escape, None),
+            (State::Idle, b'q' | b'Q' | CTRL_C) => (State::Idle, Some(Key::Exit)),
+            (State::Idle, _) => (State::Idle, None),
+            (State::Escape, b'[' | b'O') => (State::Sequence, None),
+            // Esc followed by another key.
+            (State::Escape, _) => (State::Idle, Some(Key::Exit)),
+            // Parameters, as in `ESC [ 1 ; 5 C` for Ctrl-Right.
+            (State::Sequence, b'0'..=b'9' | b';') => (State::Sequence, None),
+            (State::Sequence, b'A') => (State::Idle, Some(Key::Up)),
+            (State::Sequence, b'B') => (State::Idle, Some(Key::Down)),
+            (State::Sequence, b'C') => (State::Idle, Some(Key::Right)),
+            (State::Sequence, b'D') => (State::Idle, Some(Key::Left)),
+            (State::Sequence, _) => (State::Idle, None),
+        };
+        self.state = state;
+        key
+    }
+
+    /// Whether an Esc was read that may start a sequence.
+    pub fn pending(&self) -> bool {
+        self.state == State::Escape
+    }
+
+    /// Nothing followed within [`ESCAPE_TIMEOUT`]: a lone Esc exits.
+    pub fn timeout(&mut self) -> Option<Key> {
+        let pending = self.pending();
+        self.state = State::Idle;
+        pending.then_some(Key::Exit)
+    }
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,47 @@
+//! Kiosk mode for unattended installations.
+//!
+//! With `kiosk on <code>`, the radio boots straight to the kiosk station and
+//! the controls are locked: the CLI only runs commands that change nothing
+//! (`status`, `version`, ...) and `kiosk unlock <code>`, and the buttons,
+//! encoder and touch pads only change the volume, which stays below the
+//! volume limit. Unlocking lasts until `kiosk lock` or the next reset. The
+//! settings live in [`KioskSettings`](crate::settings::KioskSettings).
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::events::SystemEvent;
+use crate::settings;
+
+/// Unlocked with the code since boot.
+static UNLOCKED: AtomicBool = AtomicBool::new(false);
+
+/// Whether kiosk mode is on and not unlocked.
+pub fn locked() -> bool {
+    settings::get().kiosk.enabled && !UNLOCKED.load(Ordering::Relaxed)
+}
+
+/// Unlock the controls if `code` is right. Returns whether it was.
+pub fn unlock(code: u32) -> bool {
+    let right = code == settings::get().kiosk.code;
+    if right {
+        UNLOCKED.store(true, Ordering::Relaxed);
+    }
+    right
+}
+
+/// Lock the controls again.
+pub fn lock() {
+    UNLOCKED.store(false, Ordering::Relaxed);
+}
+
+/// Whether the buttons, encoder and touch pads may not send `event`.
+pub fn blocks(event: &SystemEvent) -> bool {
+    locked()
+        && !matches!(
+            event,
+            SystemEvent::RadioVolumeUp
+                | SystemEvent::RadioVolumeDown
+                | SystemEvent::RadioMute
+                | SystemEvent::RadioUnmute
+        )
+}
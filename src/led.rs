@@ -0,0 +1,140 @@
+//! Status LED and signal strength LED strip.
+//!
+//! [`StatusLed`] drives the single board LED blinked by the radio loop,
+//! honouring the polarity in [`LedSettings`](crate::settings::LedSettings)
+//! so boards with the LED wired to VDD work without code changes. Its pin is
+//! chosen in the board pin map in `main.rs`.
+//!
+//! [`strip_task`] optionally drives a short WS2812 strip as a bar graph of
+//! the received signal strength: the number of lit LEDs follows the RSSI and
+//! each LED takes its colour from a red-yellow-green gradient along the bar.
+//! The WS2812 bit timing is generated by a PWM sequence at 16 MHz, so no CPU
+//! time is spent bit-banging. The bar follows the RSSI in the radio
+//! [`state`], so it updates on every tune and, with `poll rsq on`, on the
+//! periodic signal quality readings.
+
+use embassy_futures::select::{Either, select};
+use embassy_nrf::gpio::Output;
+use embassy_nrf::pwm::{SequenceConfig, SequencePwm, SingleSequenceMode, SingleSequencer};
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::Timer;
+
+use crate::monitor;
+use crate::settings;
+use crate::state;
+
+/// Longest supported strip.
+pub const MAX_STRIP_LEDS: u8 = 8;
+/// RSSI in dBµV that lights the whole bar.
+const FULL_SCALE_RSSI: u32 = 60;
+
+/// PWM counter top for one 1.25 µs WS2812 bit at 16 MHz.
+pub const WS2812_TOP: u16 = 20;
+/// Compare values of a 0 and a 1 bit; bit 15 inverts the polarity so the
+/// line is high at the start of each period.
+const BIT_0: u16 = 0x8000 | 7;
+const BIT_1: u16 = 0x8000 | 13;
+/// Low periods ending a frame, more than the 50 µs reset time.
+const RESET_BITS: usize = 48;
+const FRAME_WORDS: usize = MAX_STRIP_LEDS as usize * 24 + RESET_BITS;
+
+/// The board status LED.
+pub struct StatusLed {
+    pin: Output<'static>,
+}
+
+impl StatusLed {
+    pub fn new(pin: Output<'static>) -> Self {
+        let mut led = Self { pin };
+        led.set(false);
+        led
+    }
+
+    pub fn set(&mut self, on: bool) {
+        if on != settings::get().led.active_low {
+            self.pin.set_high();
+        } else {
+            self.pin.set_low();
+        }
+    }
+}
+
+static REFRESH: Signal<ThreadModeRawMutex, ()> = Signal::new();
+
+/// Redraw the strip after its settings changed.
+pub fn refresh() {
+    REFRESH.signal(());
+}
+
+/// Colour of LED `index` of a bar `len` LEDs long, as green, red, blue.
+fn gradient(index: u32, len: u32, brightness: u8) -> [u8; 3] {
+    // Red at the bottom, yellow halfway, green at the top.
+    let position = if len > 1 {
+        index * 510 / (len - 1)
+    } else {
+        510
+    };
+    let red = 510u32.saturating_sub(position).min(255);
+    let green = position.min(255);
+    let scale = |value: u32| (value * brightness.min(100) as u32 / 100) as u8;
+    [scale(green), scale(red), 0]
+}
+
+/// Encode the bar for `rssi` into PWM sequence words.
+fn render(words: &mut [u16; FRAME_WORDS], rssi: Option<u8>) {
+    let led = settings::get().led;
+    let len = led.strip_len.min(MAX_STRIP_LEDS) as u32;
+    let lit = rssi.map_or(0, |rssi| {
+        (rssi as u32 * len).div_ceil(FULL_SCALE_RSSI).min(len)
+    });
+    words.fill(0x8000);
+    for index in 0..len {
+        let colour = if index < lit {
+            gradient(index, len, led.brightness)
+        } else {
+            [0; 3]
+        };
+        for (byte_index, byte) in colour.iter().enumerate() {
+            for bit in 0..8 {
+                let word = index as usize * 24 + byte_index * 8 + bit;
+                words[word] = if byte & (0x80 >> bit) != 0 {
+                    BIT_1
+                } else {
+                    BIT_0
+                };
+            }
+        }
+    }
+}
+
+/// Show the signal strength on a WS2812 strip.
+///
+/// `pwm` must run at 16 MHz with a top of [`WS2812_TOP`] and common
+/// sequence load.
+#[embassy_executor::task]
+pub async fn strip_task(mut pwm: SequencePwm<'static>) {
+    let heartbeat = monitor::register("strip");
+    let mut state = state::receiver().unwrap();
+    // Nothing is received while the radio is off.
+    let signal = |state: state::RadioState| (state.mode != state::Mode::Off).then_some(state.rssi);
+    let mut words = [0x8000u16; FRAME_WORDS];
+    let mut rssi = signal(state.get().await);
+    loop {
+        render(&mut words, rssi);
+        let sequencer = SingleSequencer::new(&mut pwm, &words, SequenceConfig::default());
+        if sequencer.start(SingleSequenceMode::Times(1)).is_ok() {
+            // A full frame takes well under a millisecond.
+            Timer::after_millis(1).await;
+        }
+        drop(sequencer);
+        let rssi_before = rssi;
+        while rssi == rssi_before {
+            match select(state.changed(), REFRESH.wait()).await {
+                Either::First(current) => rssi = signal(current),
+                Either::Second(_) => break,
+            }
+        }
+        heartbeat.beat();
+    }
+}
@@ -0,0 +1,149 @@
+//! Volume leveling across stations.
+//!
+//! Stations are not equally loud, so jumping between them often means
+//! reaching for the volume. With `leveling on`, the radio loop learns a
+//! volume offset for each station from the volume changes made on the CLI,
+//! buttons or encoder within [`LEARN_WINDOW`] after tuning it, and applies it
+//! on every later retune. Changes made later move the volume of every station
+//! alike. Offsets are in `RX_VOLUME` steps and kept with the audio settings.
+//!
+//! The volume a station would have without its offset is worked out at each
+//! retune from the current volume and the offset of the station being left,
+//! so there is no separate reference volume to get out of step.
+
+use embassy_time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+use crate::events::Origin;
+use crate::frequency::Frequency;
+use crate::settings;
+use crate::volume;
+
+/// Stations with an offset; the oldest one is dropped when full.
+pub const MAX_STATIONS: usize = 16;
+/// Time after tuning within which volume changes teach the station's offset.
+pub const LEARN_WINDOW: Duration = Duration::from_secs(30);
+/// Largest offset learned, in steps either way.
+pub const MAX_OFFSET: i8 = 20;
+
+/// Volume offset of one station.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StationOffset {
+    pub frequency: Frequency,
+    /// `RX_VOLUME` steps added when the station is tuned.
+    pub offset: i8,
+}
+
+/// Leveling switch and the learned offsets.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Leveling {
+    pub enabled: bool,
+    /// Oldest first.
+    offsets: [Option<StationOffset>; MAX_STATIONS],
+}
+
+impl Leveling {
+    pub const fn new() -> Self {
+        Self {
+            enabled: false,
+            offsets: [None; MAX_STATIONS],
+        }
+    }
+
+    pub fn offsets(&self) -> impl Iterator<Item = &StationOffset> {
+        self.offsets.iter().flatten()
+    }
+
+    /// Offset of the station on `frequency`, zero if none was learned.
+    pub fn offset(&self, frequency: Frequency) -> i8 {
+        self.offsets()
+            .find(|station| station.frequency == frequency)
+            .map_or(0, |station| station.offset)
+    }
+
+    /// Set the offset of the station on `frequency`, forgetting it at zero.
+    pub fn set(&mut self, frequency: Frequency, offset: i8) {
+        self.offsets
+            .iter_mut()
+            .filter(|slot| slot.is_some_and(|station| station.frequency == frequency))
+            .for_each(|slot| *slot = None);
+        // Keep the entries packed, oldest first.
+        let mut packed = [None; MAX_STATIONS];
+        for (slot, station) in packed.iter_mut().zip(self.offsets().copied()) {
+            *slot = Some(station);
+        }
+        self.offsets = packed;
+        if offset == 0 {
+            return;
+        }
+        let station = StationOffset { frequency, offset };
+        match self.offsets.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => *slot = Some(station),
+            None => {
+                self.offsets.rotate_left(1);
+                self.offsets[MAX_STATIONS - 1] = Some(station);
+            }
+        }
+    }
+
+    /// Forget every offset.
+    pub fn clear(&mut self) {
+        self.offsets = [None; MAX_STATIONS];
+    }
+}
+
+impl Default for Leveling {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Leveling state of the radio loop.
+pub struct Leveler {
+    /// Station tuned last and when.
+    tuned: Option<(Frequency, Instant)>,
+}
+
+impl Leveler {
+    pub const fn new() -> Self {
+        Self { tuned: None }
+    }
+
+    /// The receiver was tuned to `frequency` with the volume at `current`.
+    /// Returns the volume to switch to, if leveling changes it.
+    pub fn retuned(&mut self, frequency: Frequency, current: u16) -> Option<u16> {
+        let leveling = settings::get().audio.leveling;
+        let previous = self.tuned.replace((frequency, Instant::now()));
+        if !leveling.enabled || previous.is_some_and(|(left, _)| left == frequency) {
+            return None;
+        }
+        let left = previous.map_or(0, |(left, _)| leveling.offset(left));
+        let base = current as i16 - left as i16;
+        let volume = (base + leveling.offset(frequency) as i16).clamp(0, volume::chip_max() as i16);
+        (volume as u16 != current).then_some(volume as u16)
+    }
+
+    /// The volume was changed from `previous` to `volume` by a command from
+    /// `origin`. Learns the offset of the station tuned last if it was tuned
+    /// within [`LEARN_WINDOW`], returning it.
+    pub fn adjusted(&mut self, origin: Origin, previous: u16, volume: u16) -> Option<i8> {
+        let (frequency, tuned_at) = self.tuned?;
+        if !settings::get().audio.leveling.enabled
+            || !matches!(origin, Origin::Console | Origin::Remote | Origin::Input)
+            || tuned_at.elapsed() > LEARN_WINDOW
+        {
+            return None;
+        }
+        let learned = settings::get().audio.leveling.offset(frequency) as i16;
+        let offset = (learned + volume as i16 - previous as i16)
+            .clamp(-MAX_OFFSET as i16, MAX_OFFSET as i16) as i8;
+        settings::update(|s| s.audio.leveling.set(frequency, offset));
+        Some(offset)
+    }
+}
+
+impl Default for Leveler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
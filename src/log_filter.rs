@@ -0,0 +1,224 @@
+//! Allow/deny rules for log targets.
+//!
+//! Rules are kept in [`LogSettings`](crate::settings::LogSettings) and checked
+//! by the serial logger against `record.target()`, which defaults to the
+//! module path (`si47x_radio::cli`, `si473x::rsq`, ...). A pattern matches a
+//! target and all modules below it; `*` matches any run of characters, so
+//! `embassy_*` silences every embassy crate. Deny rules win over allow rules.
+//! Once any allow rule exists, only targets matching one are logged.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// Number of rules that can be stored.
+pub const MAX_RULES: usize = 8;
+/// Longest pattern in bytes.
+pub const MAX_PATTERN: usize = 32;
+
+/// What happens to targets matching a rule.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    Allow,
+    Deny,
+}
+
+impl Action {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Action::Allow => "allow",
+            Action::Deny => "deny",
+        }
+    }
+}
+
+/// A pattern and the action for matching targets.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rule {
+    pub action: Action,
+    pattern: [u8; MAX_PATTERN],
+    len: u8,
+}
+
+impl Rule {
+    pub fn new(action: Action, pattern: &str) -> Result<Self, Error> {
+        if pattern.is_empty() || pattern.len() > MAX_PATTERN {
+            return Err(Error::InvalidArgument);
+        }
+        let mut bytes = [0u8; MAX_PATTERN];
+        bytes[..pattern.len()].copy_from_slice(pattern.as_bytes());
+        Ok(Self {
+            action,
+            pattern: bytes,
+            len: pattern.len() as u8,
+        })
+    }
+
+    pub fn pattern(&self) -> &str {
+        core::str::from_utf8(&self.pattern[..self.len as usize]).unwrap_or_default()
+    }
+
+    /// Whether the rule applies to `target` or one of its parent modules.
+    pub fn matches(&self, target: &str) -> bool {
+        let pattern = self.pattern().as_bytes();
+        let target = target.as_bytes();
+        (0..=target.len())
+            .filter(|&end| end == target.len() || target[end..].starts_with(b"::"))
+            .any(|end| glob(pattern, &target[..end]))
+    }
+}
+
+/// Match `text` against `pattern`, where `*` matches any run of bytes.
+fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it was tried at.
+    let mut retry = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            p += 1;
+            retry = Some((p, t));
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = retry {
+            p = star_p;
+            t = star_t + 1;
+            retry = Some((star_p, t));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Set of rules applied to every log record.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogFilter {
+    rules: [Option<Rule>; MAX_RULES],
+}
+
+impl LogFilter {
+    pub const fn new() -> Self {
+        Self {
+            rules: [None; MAX_RULES],
+        }
+    }
+
+    /// Add a rule, replacing an existing rule with the same pattern.
+    pub fn add(&mut self, rule: Rule) -> Result<(), Error> {
+        let index = self
+            .rules
+            .iter()
+            .position(|slot| slot.is_some_and(|r| r.pattern() == rule.pattern()))
+            .or_else(|| self.rules.iter().position(|slot| slot.is_none()))
+            .ok_or(Error::InvalidArgument)?;
+        self.rules[index] = Some(rule);
+        Ok(())
+    }
+
+    /// Remove the rule with `pattern`, returning whether one existed.
+    pub fn remove(&mut self, pattern: &str) -> bool {
+        let slot = self
+            .rules
+            .iter_mut()
+            .find(|slot| slot.is_some_and(|r| r.pattern() == pattern));
+        slot.map(|slot| slot.take()).is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.rules = [None; MAX_RULES];
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &Rule> {
+        self.rules.iter().flatten()
+    }
+
+    /// Whether records for `target` should be logged.
+    pub fn allows(&self, target: &str) -> bool {
+        let mut allowed = !self.rules().any(|rule| rule.action == Action::Allow);
+        for rule in self.rules().filter(|rule| rule.matches(target)) {
+            match rule.action {
+                Action::Deny => return false,
+                Action::Allow => allowed = true,
+            }
+        }
+        allowed
+    }
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str) -> Rule {
+        Rule::new(Action::Deny, pattern).unwrap()
+    }
+
+    #[test]
+    fn glob_wildcards() {
+        assert!(glob(b"embassy_*", b"embassy_nrf"));
+        assert!(glob(b"embassy_*", b"embassy_"));
+        assert!(glob(b"*rsq", b"si473x::rsq"));
+        assert!(glob(b"si*::*q", b"si473x::rsq"));
+        assert!(glob(b"a*b*c", b"aXbYbZc"));
+        assert!(glob(b"**", b""));
+        assert!(!glob(b"embassy_*", b"embassy"));
+        assert!(!glob(b"a*b", b"aXbY"));
+        assert!(!glob(b"cli", b"cl"));
+    }
+
+    #[test]
+    fn rule_matches_target_and_children() {
+        let cli = rule("si47x_radio::cli");
+        assert!(cli.matches("si47x_radio::cli"));
+        assert!(cli.matches("si47x_radio::cli::alias"));
+        assert!(!cli.matches("si47x_radio::client"));
+        assert!(!cli.matches("si47x_radio"));
+        // Patterns are anchored at the crate, not at any module.
+        assert!(!rule("cli").matches("si47x_radio::cli"));
+    }
+
+    #[test]
+    fn wildcard_rule_matches_children() {
+        let embassy = rule("embassy_*");
+        assert!(embassy.matches("embassy_nrf"));
+        assert!(embassy.matches("embassy_nrf::gpio"));
+        assert!(!embassy.matches("si47x_radio::embassy_x"));
+        assert!(rule("*::rsq").matches("si473x::rsq::poll"));
+    }
+
+    #[test]
+    fn invalid_patterns_are_refused() {
+        assert_eq!(Rule::new(Action::Allow, ""), Err(Error::InvalidArgument));
+        let long = [b'x'; MAX_PATTERN + 1];
+        let long = core::str::from_utf8(&long).unwrap();
+        assert_eq!(Rule::new(Action::Allow, long), Err(Error::InvalidArgument));
+    }
+
+    #[test]
+    fn deny_wins_and_allow_restricts() {
+        let mut filter = LogFilter::new();
+        assert!(filter.allows("si473x::rsq"));
+        filter.add(rule("si473x")).unwrap();
+        assert!(!filter.allows("si473x::rsq"));
+        assert!(filter.allows("si47x_radio::cli"));
+        filter
+            .add(Rule::new(Action::Allow, "si47x_radio::cli").unwrap())
+            .unwrap();
+        assert!(filter.allows("si47x_radio::cli"));
+        assert!(!filter.allows("si47x_radio::scan"));
+        filter
+            .add(Rule::new(Action::Allow, "si473x").unwrap())
+            .unwrap();
+        // Adding the pattern again replaced the deny rule.
+        assert!(filter.allows("si473x::rsq"));
+        assert!(filter.remove("si473x"));
+        assert!(!filter.remove("si473x"));
+    }
+}
@@ -2,109 +2,1304 @@
 #![no_main]
 
 use embassy_executor::Spawner;
-use embassy_futures::yield_now;
-use embassy_nrf::gpio::{Level, Output, OutputDrive};
-use embassy_nrf::peripherals::{SERIAL0, SERIAL1};
+use embassy_futures::select::{Either4, select4};
+use embassy_nrf::gpio::{Input, Level, Output, OutputDrive, Pull};
+use embassy_nrf::nvmc::Nvmc;
+use embassy_nrf::peripherals::{SERIAL0, SERIAL1, SERIAL2, SERIAL3};
+use embassy_nrf::pwm::{self, SimplePwm};
 use embassy_nrf::{bind_interrupts, uarte};
-use embassy_time::Timer;
+use embassy_time::{Duration, Instant, Timer, with_deadline, with_timeout};
 use log::{info, warn};
 use panic_probe as _;
 
 use embassy_nrf::twim::{self, Twim};
 use static_cell::ConstStaticCell;
 
+pub mod alarm;
+pub mod alert;
+pub mod alias;
+pub mod balance;
+pub mod bandplan;
+pub mod blink;
+pub mod board;
+pub mod brownout;
+pub mod buffers;
+pub mod bus;
+pub mod buzzer;
+pub mod calibration;
+pub mod capture;
+pub mod checksum;
 mod cli;
+pub mod clock;
+mod commands;
+pub mod config;
 pub mod console;
+pub mod diag;
+pub mod error;
 pub mod events;
+pub mod frequency;
+pub mod gps;
+pub mod i18n;
+pub mod i2c_trace;
+pub mod input;
+pub mod interactive;
+pub mod kiosk;
+pub mod led;
+pub mod leveling;
+pub mod log_filter;
+pub mod monitor;
+pub mod mute;
+pub mod patch;
+pub mod poller;
+pub mod probe;
+pub mod properties;
+pub mod ramp;
+pub mod rds;
+pub mod rds_db;
+pub mod record;
+pub mod reset;
+pub mod scan;
+pub mod schema;
+pub mod seek;
 mod serial_logger;
+pub mod service;
+pub mod settings;
+pub mod sleep;
+pub mod stack;
+pub mod startup;
+pub mod state;
+pub mod stats;
+pub mod stereo;
+pub mod storage;
+pub mod survey;
+pub mod telemetry;
+pub mod touch;
+pub mod trace;
+pub mod transfer;
+pub mod tune_lock;
+pub mod version;
+pub mod volume;
+use error::Error;
 use si473x::Si47xxDevice;
 
+/// FM channel spacing used for step tuning, in kHz.
+const FM_CHANNEL_STEP_KHZ: u32 = config::REGION.fm_step_khz();
+/// FM band limits.
+const FM_BAND: (frequency::Frequency, frequency::Frequency) = config::REGION.fm_band();
+/// Delay before retrying a tuner that failed to come up, doubled on every
+/// failure up to [`RADIO_RETRY_MAX`].
+const RADIO_RETRY_MIN: Duration = Duration::from_secs(1);
+const RADIO_RETRY_MAX: Duration = Duration::from_secs(64);
+
 bind_interrupts!(struct Irqs {
+    CLOCK_POWER => brownout::InterruptHandler;
     SERIAL0 => uarte::InterruptHandler<SERIAL0>;
     SERIAL1 => twim::InterruptHandler<SERIAL1>;
+    SERIAL2 => uarte::InterruptHandler<SERIAL2>;
+    SERIAL3 => uarte::InterruptHandler<SERIAL3>;
 });
 
 #[embassy_executor::main]
 async fn main(spawner: Spawner) {
-    let p = embassy_nrf::init(Default::default());
-    let mut led = Output::new(p.P0_28, Level::Low, OutputDrive::Standard);
+    stack::paint();
+    let mut p = embassy_nrf::init(Default::default());
+    let board = board::detect(p.P1_11.into(), p.P1_12.into()).await;
+    let led_pin = if board.led_alt {
+        p.P0_29.into()
+    } else {
+        p.P0_28.into()
+    };
+    let led_pin = Output::new(led_pin, Level::Low, OutputDrive::Standard);
 
     let mut config = uarte::Config::default();
     config.parity = uarte::Parity::EXCLUDED;
     config.baudrate = uarte::Baudrate::BAUD115200;
     let uart: uarte::Uarte<'static> = uarte::Uarte::new(p.SERIAL0, p.P0_22, p.P0_20, Irqs, config);
     let (tx, rx) = uart.split();
+    console::rtt_init();
     console::stdout_init(tx);
     serial_logger::init().unwrap();
+    storage::init(Nvmc::new(p.NVMC));
+    let settings_loaded = settings::load();
+    match settings_loaded {
+        Ok(true) => info!("Loaded stored settings"),
+        Ok(false) => {}
+        Err(err) => warn!("Failed to load settings: {}", err),
+    }
+    // Polarity is a setting, so wrap the pin once settings are loaded.
+    let mut led = led::StatusLed::new(led_pin);
+    if settings_loaded.is_err() {
+        blink::show(&mut led, blink::BootFailure::Settings, 3).await;
+    }
+    if let Err(err) = stats::load() {
+        warn!("Failed to load statistics: {}", err);
+    }
+    if let Err(err) = rds_db::load() {
+        warn!("Failed to load RDS stations: {}", err);
+    }
+    let replay = trace::init().unwrap_or_else(|err| {
+        warn!("Failed to start event trace: {}", err);
+        None
+    });
+
+    let mut config = uarte::Config::default();
+    config.parity = uarte::Parity::EXCLUDED;
+    config.baudrate = uarte::Baudrate::BAUD9600;
+    let gps_rx = uarte::UarteRx::new(p.SERIAL2, Irqs, p.P1_05, config);
+
+    let remote_rx = if config::REMOTE_CLI {
+        let mut config = uarte::Config::default();
+        config.parity = uarte::Parity::EXCLUDED;
+        config.baudrate = uarte::Baudrate::BAUD115200;
+        let uart = uarte::Uarte::new(p.SERIAL3, p.P1_02, p.P1_01, Irqs, config);
+        let (tx, rx) = uart.split();
+        console::remote_init(tx);
+        Some(rx)
+    } else {
+        None
+    };
 
+    if !bus::idle(p.P1_14.reborrow(), p.P1_13.reborrow()).await {
+        blink::halt(&mut led, blink::BootFailure::Bus).await;
+    }
     let config = twim::Config::default();
     static RAM_BUFFER: ConstStaticCell<[u8; 16]> = ConstStaticCell::new([0; 16]);
     let twi = Twim::new(p.SERIAL1, Irqs, p.P1_14, p.P1_13, config, RAM_BUFFER.take());
+    let i2c_bus = bus::init(twi);
     let reset_pin = Output::new(p.P1_03, Level::High, OutputDrive::Standard);
-    let mut radio_dev: Si47xxDevice<_, _> = Si47xxDevice::new(twi, reset_pin);
-    radio_dev.reset().await;
-    radio_dev.init_fm().await.expect("Radio init failed");
-    warn!("Radio initialized!");
-    let revision = radio_dev
-        .revision_get()
-        .await
-        .expect("Failed to get revision");
-    radio_dev.sound_on().await.expect("Failed to unmute sound");
+    let mut radio_dev: Si47xxDevice<_, _> =
+        Si47xxDevice::new(i2c_trace::Traced::new(bus::device(i2c_bus)), reset_pin);
 
-    let _ = spawner.spawn(cli::my_task(rx));
-    yield_now().await;
+    // Tasks subscribing to notifications, waited for before publishing any.
+    let mut subscribers = 0;
+    if spawner.spawn(cli::my_task(rx)).is_ok() {
+        subscribers += 1;
+    }
+    if let Some(rx) = remote_rx
+        && spawner.spawn(cli::remote_task(rx)).is_ok()
+    {
+        subscribers += 1;
+    }
+    let _ = spawner.spawn(capture::capture_task());
+    let _ = spawner.spawn(settings::commit_task());
+    brownout::init(Irqs);
+    let _ = spawner.spawn(brownout::brownout_task());
+    if config::ALARM {
+        let _ = spawner.spawn(alarm::alarm_task());
+    }
+    if config::ALERT {
+        let _ = spawner.spawn(alert::alert_task());
+    }
+    if let Some(back) = replay {
+        let _ = spawner.spawn(trace::replay_task(back));
+    }
+    let _ = spawner.spawn(sleep::sleep_task());
+    if spawner.spawn(stats::stats_task()).is_ok() {
+        subscribers += 1;
+    }
+    let buzzer_pwm = SimplePwm::new_1ch(p.PWM0, p.P1_08, &Default::default());
+    let _ = spawner.spawn(buzzer::buzzer_task(buzzer_pwm));
+    if board.strip {
+        let mut strip_config = pwm::Config::default();
+        strip_config.prescaler = pwm::Prescaler::Div1;
+        strip_config.max_duty = led::WS2812_TOP;
+        strip_config.sequence_load = pwm::SequenceLoad::Common;
+        match pwm::SequencePwm::new_1ch(p.PWM1, p.P1_10, strip_config) {
+            Ok(strip) => {
+                let _ = spawner.spawn(led::strip_task(strip));
+            }
+            Err(err) => warn!("LED strip PWM not available: {:?}", err),
+        }
+    }
+    if config::GPS {
+        let _ = spawner.spawn(gps::gps_task(gps_rx));
+    }
+    if board.buttons {
+        let buttons = [
+            (
+                Input::new(p.P0_23, Pull::Up),
+                events::SystemEvent::RadioVolumeUp,
+            ),
+            (
+                Input::new(p.P0_24, Pull::Up),
+                events::SystemEvent::RadioVolumeDown,
+            ),
+            (
+                Input::new(p.P0_08, Pull::Up),
+                events::SystemEvent::RadioSeekUp,
+            ),
+            (
+                Input::new(p.P0_09, Pull::Up),
+                events::SystemEvent::RadioSeekDown,
+            ),
+        ];
+        for (button, event) in buttons {
+            let _ = spawner.spawn(input::button_task(button, event));
+        }
+    }
+    if board.encoder {
+        let _ = spawner.spawn(input::encoder_task(
+            Input::new(p.P1_06, Pull::Up),
+            Input::new(p.P1_07, Pull::Up),
+        ));
+    }
+    #[cfg(feature = "touch")]
+    if board.touch {
+        let _ = spawner.spawn(touch::touch_task(
+            p.TIMER1,
+            p.PPI_CH0,
+            [
+                events::SystemEvent::RadioVolumeUp,
+                events::SystemEvent::RadioVolumeDown,
+                events::SystemEvent::RadioSeekUp,
+                events::SystemEvent::RadioSeekDown,
+            ],
+        ));
+    }
 
-    let mut radio = radio_dev.fm().await.expect("Failed to switch to FM mode");
+    startup::wait_subscribed(subscribers).await;
     let notification_publisher = events::notify_publisher().unwrap();
+
+    // A tuner that does not come up is retried with a growing delay while
+    // the other tasks run, instead of needing a reboot.
+    let mut attempt: u8 = 0;
+    let mut retry = RADIO_RETRY_MIN;
+    let (revision, patched) = loop {
+        attempt = attempt.saturating_add(1);
+        let powered = async {
+            radio_dev.reset().await;
+            let patched = match patch::stored() {
+                Ok(Some(info)) => {
+                    let applied = async {
+                        if !patch::verify(&info)? {
+                            return Err(Error::Checksum);
+                        }
+                        radio_dev.power_up_patch().await?;
+                        for line in patch::lines(&info) {
+                            radio_dev.patch_line(&line?).await?;
+                        }
+                        Ok::<(), Error>(())
+                    }
+                    .await;
+                    match applied {
+                        Ok(()) => Some(info),
+                        Err(err) => {
+                            warn!("Firmware patch not applied: {}", err);
+                            radio_dev.reset().await;
+                            None
+                        }
+                    }
+                }
+                Ok(None) => None,
+                Err(err) => {
+                    warn!("Failed to read firmware patch: {}", err);
+                    None
+                }
+            };
+            // The patch-mode power-up already selects FM; powering up again
+            // would clear the patch from the tuner's RAM.
+            if patched.is_none() {
+                radio_dev.init_fm().await?;
+            }
+            let revision = radio_dev.revision_get().await?;
+            radio_dev.sound_on().await?;
+            Ok::<_, Error>((revision, patched))
+        }
+        .await;
+        match powered {
+            Ok(powered) => break powered,
+            Err(err) => {
+                warn!(
+                    "Radio init attempt {} failed: {}, retrying in {} s",
+                    attempt,
+                    err,
+                    retry.as_secs()
+                );
+                notification_publisher
+                    .publish(events::SystemNotify::RadioInitFailed {
+                        attempt,
+                        retry_s: retry.as_secs() as u16,
+                    })
+                    .await;
+                let _ =
+                    with_timeout(retry, blink::repeat(&mut led, blink::BootFailure::Radio)).await;
+                led.set(false);
+                retry = (retry * 2).min(RADIO_RETRY_MAX);
+            }
+        }
+    };
+    warn!("Radio initialized!");
+
+    let mut radio = match radio_dev.fm().await {
+        Ok(radio) => radio,
+        Err(err) => {
+            warn!("Failed to switch to FM: {}", Error::from(err));
+            blink::halt(&mut led, blink::BootFailure::Radio).await;
+        }
+    };
+    if let Some(info) = patched {
+        patch::mark_applied();
+        info!("Applied firmware patch, {} bytes", info.length);
+    }
+    if radio
+        .property_set(
+            properties::FM_RDS_CONFIG.id,
+            properties::FM_RDS_CONFIG_ENABLE,
+        )
+        .await
+        .is_err()
+    {
+        warn!("Failed to enable RDS");
+    }
+    // Settings persist across reboots, so bring the chip in line with them.
+    let profile = settings::get().reception.profile;
+    for &(property, value) in profile.values() {
+        if radio.property_set(property.id, value).await.is_err() {
+            warn!("Failed to apply {:?} profile", profile);
+            break;
+        }
+    }
+    for (property, value) in seek::properties(settings::get().seek.sensitivity) {
+        if radio.property_set(property.id, value).await.is_err() {
+            warn!("Failed to apply seek sensitivity");
+            break;
+        }
+    }
+    poller::set_enabled(poller::Source::Stereo, settings::get().stereo.enabled);
+    let region = [
+        (properties::FM_DEEMPHASIS, config::REGION.fm_deemphasis()),
+        // The seek properties count FM in 10 kHz units.
+        (
+            properties::FM_SEEK_BAND_BOTTOM,
+            (FM_BAND.0.khz() / 10) as u16,
+        ),
+        (properties::FM_SEEK_BAND_TOP, (FM_BAND.1.khz() / 10) as u16),
+        (
+            properties::FM_SEEK_FREQ_SPACING,
+            (FM_CHANNEL_STEP_KHZ / 10) as u16,
+        ),
+    ];
+    for (property, value) in region {
+        if radio.property_set(property.id, value).await.is_err() {
+            warn!("Failed to apply {:?} region", config::REGION);
+            break;
+        }
+    }
+    // A kiosk boots straight to its station.
+    let kiosk = settings::get().kiosk;
+    let startup_frequency = kiosk
+        .station
+        .filter(|_| kiosk.enabled)
+        .or(config::STARTUP_FREQUENCY)
+        .filter(|&frequency| settings::get().tune_lock.permits(frequency));
+    if let Some(frequency) = startup_frequency
+        && radio
+            .tune_frequency(calibration::to_chip(scan::Band::Fm, frequency))
+            .await
+            .is_err()
+    {
+        warn!("Failed to tune startup frequency");
+    }
+    let mut balance = balance::Balance::new(settings::get().audio.balance);
+    if radio
+        .property_set(properties::RX_HARD_MUTE.id, balance.bits())
+        .await
+        .is_err()
+    {
+        warn!("Failed to apply balance");
+    }
     notification_publisher
         .publish(events::SystemNotify::RadioFmOn)
         .await;
-    yield_now().await;
     notification_publisher
         .publish(events::SystemNotify::RevisionInfo(revision))
         .await;
-    yield_now().await;
-    let tune_status = radio
-        .tune_status_get()
-        .await
-        .expect("Failed to get tune status");
-    notification_publisher
-        .publish(events::SystemNotify::TuneStatus(tune_status))
-        .await;
+    match radio.tune_status_get().await.map_err(Error::from) {
+        Ok(status) => {
+            notification_publisher
+                .publish(events::SystemNotify::TuneStatus(calibration::from_chip(
+                    scan::Band::Fm,
+                    status,
+                )))
+                .await
+        }
+        Err(err) => {
+            warn!("Failed to get tune status: {}", err);
+            notification_publisher
+                .publish(events::SystemNotify::Error(err))
+                .await;
+        }
+    }
 
+    let heartbeat = monitor::register("radio");
+    // Event received while coalescing volume changes, handled next iteration.
+    // Starts with the configured volume or the volume limit, as the chip
+    // powers up at full volume.
+    let mut pending = Some((
+        events::Origin::Radio,
+        match config::STARTUP_VOLUME_PCT {
+            Some(level) => events::SystemEvent::RadioVolumeSet(level),
+            None => events::SystemEvent::RadioVolumeLimit,
+        },
+    ));
+    let mut rds_decoder = rds::Decoder::new();
+    let mut volume_ramp: Option<ramp::Ramp> = None;
+    let mut timed_mute: Option<mute::TimedMute> = None;
+    let mut leveler = leveling::Leveler::new();
+    // Muted by an event; the balance modulation pauses meanwhile.
+    let mut audio_muted = false;
+    // Mute state to restore once step tuning rests, and when.
+    let mut step_mute: Option<(u16, Instant)> = None;
+    startup::set_radio_ready();
     loop {
-        led.set_high();
+        heartbeat.beat();
+        led.set(true);
         Timer::after_millis(300).await;
-        led.set_low();
+        led.set(false);
         Timer::after_millis(300).await;
-        let event = events::event_receive().await;
-        info!("Received event: {:?}", event);
-        match event {
-            events::SystemEvent::RadioVolumeUp => {
-                radio.volume_up().await.expect("Volume up failed");
+        // Number of the service request waiting for the result, if it is one.
+        let (origin, event, requested) = match pending.take() {
+            Some((origin, event)) => (origin, event, None),
+            None => loop {
+                // Run periodic reads while waiting for the next event.
+                let due = poller::next_due();
+                let ramp_at = volume_ramp.map_or(Instant::MAX, |ramp| ramp.next_step());
+                let mute_at = timed_mute.map_or(Instant::MAX, |mute| mute.next_tick());
+                let balance_at = balance
+                    .next_toggle()
+                    .filter(|_| !audio_muted && step_mute.is_none())
+                    .unwrap_or(Instant::MAX);
+                let step_mute_at = step_mute.map_or(Instant::MAX, |(_, at)| at);
+                let deadline = due
+                    .map_or(Instant::MAX, |(_, at)| at)
+                    .min(ramp_at)
+                    .min(mute_at)
+                    .min(balance_at)
+                    .min(step_mute_at);
+                match select4(
+                    events::event_receive(),
+                    service::receive(),
+                    Timer::at(deadline),
+                    poller::changed(),
+                )
+                .await
+                {
+                    Either4::First((origin, event)) => break (origin, event, None),
+                    Either4::Second((number, event)) => {
+                        break (events::Origin::Service, event, Some(number));
+                    }
+                    Either4::Third(_) => {}
+                    Either4::Fourth(_) => continue,
+                }
+                heartbeat.beat();
+                if let Some((restore, at)) = step_mute
+                    && at <= Instant::now()
+                {
+                    // Step tuning rested: unmute and report where it ended.
+                    step_mute = None;
+                    if radio
+                        .property_set(properties::RX_HARD_MUTE.id, restore)
+                        .await
+                        .is_err()
+                    {
+                        warn!("Failed to unmute after step tuning");
+                    }
+                    if let Ok(status) = radio.tune_status_get().await {
+                        notification_publisher
+                            .publish(events::SystemNotify::TuneStatus(calibration::from_chip(
+                                scan::Band::Fm,
+                                status,
+                            )))
+                            .await;
+                    }
+                    continue;
+                }
+                if let Some(ramp) = volume_ramp.as_mut()
+                    && ramp.next_step() <= Instant::now()
+                {
+                    let (volume, done) = ramp.step(Instant::now());
+                    match radio
+                        .property_set(properties::RX_VOLUME.id, volume)
+                        .await
+                        .map_err(Error::from)
+                    {
+                        Err(err) => {
+                            warn!("Volume ramp stopped: {}", err);
+                            volume_ramp = None;
+                        }
+                        Ok(()) if done => {
+                            volume_ramp = None;
+                            notification_publisher
+                                .publish(events::SystemNotify::VolumeChanged(volume::percent(
+                                    volume,
+                                )))
+                                .await;
+                        }
+                        Ok(()) => {}
+                    }
+                    continue;
+                }
+                if balance_at <= Instant::now() {
+                    let bits = balance.toggle(Instant::now());
+                    if radio
+                        .property_set(properties::RX_HARD_MUTE.id, bits)
+                        .await
+                        .is_err()
+                    {
+                        warn!("Balance stopped, failed to set mute");
+                        balance = balance::Balance::new(0);
+                    }
+                    continue;
+                }
+                if let Some(mute) = timed_mute.as_mut()
+                    && mute.next_tick() <= Instant::now()
+                {
+                    match mute.tick(Instant::now()) {
+                        Some(left) => {
+                            notification_publisher
+                                .publish(events::SystemNotify::MuteCountdown(left))
+                                .await;
+                            continue;
+                        }
+                        None => {
+                            info!("Timed mute over");
+                            timed_mute = None;
+                            break (
+                                events::Origin::Radio,
+                                events::SystemEvent::RadioUnmute,
+                                None,
+                            );
+                        }
+                    }
+                }
+                let Some((source, _)) = due else {
+                    continue;
+                };
+                let polled = async {
+                    match source {
+                        poller::Source::Rsq => {
+                            let rsq = radio.rsq_status_get().await?;
+                            let quality = diag::FmDiagnostics {
+                                pilot: rsq.pilot,
+                                stereo_blend: rsq.stereo_blend,
+                                frequency_offset: rsq.frequency_offset,
+                                rssi: rsq.rssi,
+                                snr: rsq.snr,
+                                multipath: rsq.multipath,
+                            };
+                            notification_publisher
+                                .publish(events::SystemNotify::SignalQuality(quality))
+                                .await;
+                        }
+                        poller::Source::Rds => {
+                            while let Some((blocks, errors)) = radio.rds_group_get().await? {
+                                let group = rds::Group { blocks, errors };
+                                for update in rds_decoder.feed(group) {
+                                    match update {
+                                        rds::RdsUpdate::Pi(pi) => {
+                                            notification_publisher
+                                                .publish(events::SystemNotify::RdsPi(pi))
+                                                .await;
+                                            if let Some(station) = rds_db::find_pi(pi) {
+                                                notification_publisher
+                                                    .publish(events::SystemNotify::RdsStation(
+                                                        station,
+                                                    ))
+                                                    .await;
+                                            }
+                                        }
+                                        rds::RdsUpdate::Ps(ps) => {
+                                            notification_publisher
+                                                .publish(events::SystemNotify::RdsPs(ps))
+                                                .await;
+                                            let status = calibration::from_chip(
+                                                scan::Band::Fm,
+                                                radio.tune_status_get().await?,
+                                            );
+                                            // Keep draining the FIFO even if the
+                                            // station cannot be stored.
+                                            if let Some(pi) = rds_decoder.pi()
+                                                && let Err(err) = rds_db::learn(rds_db::Station {
+                                                    pi,
+                                                    frequency: status.frequency,
+                                                    ps,
+                                                })
+                                            {
+                                                warn!("Failed to remember station: {}", err);
+                                            }
+                                        }
+                                        rds::RdsUpdate::Pty(alert::ALARM_PTY) => {
+                                            let status = calibration::from_chip(
+                                                scan::Band::Fm,
+                                                radio.tune_status_get().await?,
+                                            );
+                                            alert::raise(rds_decoder.pi(), status.frequency);
+                                        }
+                                        rds::RdsUpdate::Pty(_) => {}
+                                        rds::RdsUpdate::Clock(time) => clock::sync(time),
+                                    }
+                                }
+                            }
+                        }
+                        poller::Source::Survey => {
+                            let tune_status = calibration::from_chip(
+                                scan::Band::Fm,
+                                radio.tune_status_get().await?,
+                            );
+                            survey::record(&tune_status)?;
+                        }
+                        poller::Source::Telemetry => {
+                            let rsq = radio.rsq_status_get().await?;
+                            telemetry::emit(rsq.rssi, rsq.snr, rsq.stereo_blend > 0);
+                        }
+                        poller::Source::Stereo => {
+                            let rsq = radio.rsq_status_get().await?;
+                            if let Some(mono) = stereo::decide(rsq.snr) {
+                                for (property, value) in stereo::properties(mono) {
+                                    radio.property_set(property.id, value).await?;
+                                }
+                                stereo::set_forced(mono);
+                                info!(
+                                    "SNR {} dB, {}",
+                                    rsq.snr,
+                                    if mono {
+                                        "forcing mono"
+                                    } else {
+                                        "back to stereo"
+                                    }
+                                );
+                            }
+                        }
+                    }
+                    Ok::<(), Error>(())
+                }
+                .await;
+                if let Err(err) = polled
+                    && poller::state(source).failures == 0
+                {
+                    // Later failures only stretch the interval.
+                    warn!("Polling {} failed: {}", source.name(), err);
+                }
+                poller::complete(source, polled.is_ok());
+            },
+        };
+        heartbeat.beat();
+        info!("Received event from {}: {:?}", origin.name(), event);
+        notification_publisher.set_origin(origin);
+        trace::record(&event);
+        service::clear_abort();
+        let operation = event.operation();
+        if operation.is_some() {
+            notification_publisher
+                .publish(events::SystemNotify::Progress(operation))
+                .await;
+        }
+        // Rapid step tuning stays muted until it rests for the debounce time.
+        let step_debounce = settings::get().audio.step_mute_ms;
+        if step_debounce > 0 && matches!(event, events::SystemEvent::RadioTuneStep(_)) {
+            if step_mute.is_none()
+                && let Ok(previous) = radio.property_get(properties::RX_HARD_MUTE.id).await
+                && radio
+                    .property_set(properties::RX_HARD_MUTE.id, properties::RX_HARD_MUTE_BOTH)
+                    .await
+                    .is_ok()
+            {
+                step_mute = Some((previous, Instant::now()));
             }
-            events::SystemEvent::RadioVolumeDown => {
-                radio.volume_down().await.expect("Volume down failed");
+            if let Some((_, at)) = step_mute.as_mut() {
+                *at = Instant::now() + Duration::from_millis(step_debounce as u64);
             }
-            events::SystemEvent::RadioSetFrequency(freq) => {
-                let tune_status = radio
-                    .tune_frequency(freq)
-                    .await
-                    .expect("Set frequency failed");
-                notification_publisher
-                    .publish(events::SystemNotify::TuneStatus(tune_status))
-                    .await;
+        }
+        // Hard-mute around retuning so the inter-station noise is not heard.
+        let restore_mute =
+            if settings::get().audio.tune_mute && event.is_tuning() && step_mute.is_none() {
+                radio.property_get(properties::RX_HARD_MUTE.id).await.ok()
+            } else {
+                None
+            };
+        if restore_mute.is_some() {
+            let _ = radio
+                .property_set(properties::RX_HARD_MUTE.id, properties::RX_HARD_MUTE_BOTH)
+                .await;
+        }
+        let result = async {
+            match event {
+                events::SystemEvent::RadioVolumeUp | events::SystemEvent::RadioVolumeDown => {
+                    volume_ramp = None;
+                    // Merge a burst of steps into a single volume write. The
+                    // window runs from the first step, so a continuous turn
+                    // still writes once per window.
+                    let step = |event: events::SystemEvent| match event {
+                        events::SystemEvent::RadioVolumeUp => Some(1),
+                        events::SystemEvent::RadioVolumeDown => Some(-1),
+                        _ => None,
+                    };
+                    let deadline = Instant::now()
+                        + Duration::from_millis(settings::get().audio.volume_coalesce_ms as u64);
+                    let mut delta: i16 = step(event).unwrap_or(0);
+                    while let Ok((next_origin, next)) =
+                        with_deadline(deadline, events::event_receive()).await
+                    {
+                        match step(next) {
+                            Some(next_step) => delta += next_step,
+                            None => {
+                                pending = Some((next_origin, next));
+                                break;
+                            }
+                        }
+                    }
+                    let current = radio.property_get(properties::RX_VOLUME.id).await?;
+                    let volume =
+                        (current as i16 + delta).clamp(0, volume::chip_max() as i16) as u16;
+                    radio.property_set(properties::RX_VOLUME.id, volume).await?;
+                    notification_publisher
+                        .publish(events::SystemNotify::VolumeChanged(volume::percent(volume)))
+                        .await;
+                    if let Some(offset) = leveler.adjusted(origin, current, volume) {
+                        info!("Station volume offset now {}", offset);
+                    }
+                }
+                events::SystemEvent::RadioVolumeSet(level) => {
+                    if level > 100 {
+                        return Err(Error::InvalidArgument);
+                    }
+                    volume_ramp = None;
+                    let previous = radio.property_get(properties::RX_VOLUME.id).await?;
+                    let volume = volume::chip_level(level);
+                    radio.property_set(properties::RX_VOLUME.id, volume).await?;
+                    notification_publisher
+                        .publish(events::SystemNotify::VolumeChanged(volume::percent(volume)))
+                        .await;
+                    if let Some(offset) = leveler.adjusted(origin, previous, volume) {
+                        info!("Station volume offset now {}", offset);
+                    }
+                }
+                events::SystemEvent::RadioVolumeRamp { level, duration_ms } => {
+                    if level > 100 {
+                        return Err(Error::InvalidArgument);
+                    }
+                    let current = radio.property_get(properties::RX_VOLUME.id).await?;
+                    volume_ramp = Some(ramp::Ramp::new(
+                        current,
+                        volume::chip_level(level),
+                        Duration::from_millis(duration_ms as u64),
+                    ));
+                }
+                events::SystemEvent::RadioVolumeRampStop => volume_ramp = None,
+                events::SystemEvent::RadioBalance => {
+                    balance = balance::Balance::new(settings::get().audio.balance);
+                    if !audio_muted {
+                        match step_mute.as_mut() {
+                            Some((restore, _)) => *restore = balance.bits(),
+                            None => {
+                                radio
+                                    .property_set(properties::RX_HARD_MUTE.id, balance.bits())
+                                    .await?
+                            }
+                        }
+                    }
+                }
+                events::SystemEvent::RadioVolumeLimit => {
+                    let current = radio.property_get(properties::RX_VOLUME.id).await?;
+                    let limit = volume::chip_max();
+                    if current > limit {
+                        volume_ramp = None;
+                        radio.property_set(properties::RX_VOLUME.id, limit).await?;
+                        notification_publisher
+                            .publish(events::SystemNotify::VolumeChanged(volume::percent(limit)))
+                            .await;
+                    } else {
+                        // Nothing to announce, but the snapshot needs the level.
+                        state::update(|state| state.volume = volume::percent(current));
+                    }
+                }
+                events::SystemEvent::RadioMute
+                | events::SystemEvent::RadioUnmute
+                | events::SystemEvent::RadioMuteFor(_) => {
+                    let muted = event != events::SystemEvent::RadioUnmute;
+                    let value = if muted {
+                        properties::RX_HARD_MUTE_BOTH
+                    } else {
+                        balance.bits()
+                    };
+                    // Applied once step tuning rests if it keeps the audio muted.
+                    match step_mute.as_mut() {
+                        Some((restore, _)) => *restore = value,
+                        None => {
+                            radio
+                                .property_set(properties::RX_HARD_MUTE.id, value)
+                                .await?
+                        }
+                    }
+                    audio_muted = muted;
+                    notification_publisher
+                        .publish(if muted {
+                            events::SystemNotify::RadioMute
+                        } else {
+                            events::SystemNotify::RadioUnmute
+                        })
+                        .await;
+                    timed_mute = match event {
+                        events::SystemEvent::RadioMuteFor(seconds) => {
+                            Some(mute::TimedMute::new(Duration::from_secs(seconds as u64)))
+                        }
+                        _ => None,
+                    };
+                }
+                events::SystemEvent::RadioSetFrequency(freq) => {
+                    if !(FM_BAND.0..=FM_BAND.1).contains(&freq) {
+                        return Err(Error::InvalidFrequency);
+                    }
+                    if !settings::get().tune_lock.permits(freq) {
+                        return Err(Error::Locked);
+                    }
+                    let tune_status = calibration::from_chip(
+                        scan::Band::Fm,
+                        radio
+                            .tune_frequency(calibration::to_chip(scan::Band::Fm, freq))
+                            .await?,
+                    );
+                    notification_publisher
+                        .publish(events::SystemNotify::TuneStatus(tune_status))
+                        .await;
+                }
+                events::SystemEvent::RadioTuneStep(steps) => {
+                    let current =
+                        calibration::from_chip(scan::Band::Fm, radio.tune_status_get().await?);
+                    let target = current
+                        .frequency
+                        .offset(steps as i32 * FM_CHANNEL_STEP_KHZ as i32)
+                        .clamp(FM_BAND.0, FM_BAND.1);
+                    let frequency = settings::get()
+                        .tune_lock
+                        .constrain(current.frequency, target)
+                        .filter(|frequency| (FM_BAND.0..=FM_BAND.1).contains(frequency))
+                        .ok_or(Error::Locked)?;
+                    let tune_status = calibration::from_chip(
+                        scan::Band::Fm,
+                        radio
+                            .tune_frequency(calibration::to_chip(scan::Band::Fm, frequency))
+                            .await?,
+                    );
+                    // Reported once the steps rest when they keep the audio muted.
+                    if step_mute.is_none() {
+                        notification_publisher
+                            .publish(events::SystemNotify::TuneStatus(tune_status))
+                            .await;
+                    }
+                }
+                events::SystemEvent::RadioSeekUp => {
+                    let lock = settings::get().tune_lock;
+                    let start =
+                        calibration::from_chip(scan::Band::Fm, radio.tune_status_get().await?)
+                            .frequency;
+                    let mut last = start;
+                    let mut wrapped = false;
+                    // Seek past stations outside the tuning lock until one
+                    // inside it is found or the whole band was searched.
+                    let tune_status = loop {
+                        let status = calibration::from_chip(scan::Band::Fm, radio.seek_up().await?);
+                        if lock.permits(status.frequency) {
+                            break status;
+                        }
+                        wrapped |= status.frequency <= last;
+                        last = status.frequency;
+                        let err = if service::abort_requested() {
+                            Error::Aborted
+                        } else if wrapped && status.frequency >= start {
+                            Error::Locked
+                        } else {
+                            continue;
+                        };
+                        radio
+                            .tune_frequency(calibration::to_chip(scan::Band::Fm, start))
+                            .await?;
+                        return Err(err);
+                    };
+                    info!("Seeked up: {:?}", tune_status);
+                    buzzer::beep(buzzer::Beep::Seek);
+                    notification_publisher
+                        .publish(events::SystemNotify::TuneStatus(tune_status))
+                        .await;
+                }
+                events::SystemEvent::RadioSeekDown => {
+                    let lock = settings::get().tune_lock;
+                    let start =
+                        calibration::from_chip(scan::Band::Fm, radio.tune_status_get().await?)
+                            .frequency;
+                    // The tuner only seeks upwards, so step down a channel at
+                    // a time, wrapping to the top of the band, until a valid
+                    // station is found or the whole band was searched.
+                    let mut frequency = start;
+                    let mut wrapped = false;
+                    let tune_status = loop {
+                        if service::abort_requested() {
+                            radio
+                                .tune_frequency(calibration::to_chip(scan::Band::Fm, start))
+                                .await?;
+                            return Err(Error::Aborted);
+                        }
+                        frequency = if frequency <= FM_BAND.0 {
+                            wrapped = true;
+                            FM_BAND.1
+                        } else {
+                            frequency
+                                .offset(-(FM_CHANNEL_STEP_KHZ as i32))
+                                .max(FM_BAND.0)
+                        };
+                        let status = calibration::from_chip(
+                            scan::Band::Fm,
+                            radio
+                                .tune_frequency(calibration::to_chip(scan::Band::Fm, frequency))
+                                .await?,
+                        );
+                        if status.valid && lock.permits(status.frequency) {
+                            break status;
+                        }
+                        if wrapped && frequency <= start {
+                            // Nothing found; stay where the seek started.
+                            break calibration::from_chip(
+                                scan::Band::Fm,
+                                radio
+                                    .tune_frequency(calibration::to_chip(scan::Band::Fm, start))
+                                    .await?,
+                            );
+                        }
+                    };
+                    info!("Seeked down: {:?}", tune_status);
+                    buzzer::beep(buzzer::Beep::Seek);
+                    notification_publisher
+                        .publish(events::SystemNotify::TuneStatus(tune_status))
+                        .await;
+                }
+                events::SystemEvent::RadioSeekBest => {
+                    let lock = settings::get().tune_lock;
+                    let start =
+                        calibration::from_chip(scan::Band::Fm, radio.tune_status_get().await?)
+                            .frequency;
+                    let up = calibration::from_chip(scan::Band::Fm, radio.seek_up().await?);
+                    let up = (up.valid && up.frequency != start && lock.permits(up.frequency))
+                        .then_some(up);
+                    // The tuner only seeks upwards, so the nearest station
+                    // below is found by stepping down a channel at a time.
+                    let mut down = None;
+                    let mut frequency = start;
+                    while frequency > FM_BAND.0 {
+                        if service::abort_requested() {
+                            radio
+                                .tune_frequency(calibration::to_chip(scan::Band::Fm, start))
+                                .await?;
+                            return Err(Error::Aborted);
+                        }
+                        frequency = frequency
+                            .offset(-(FM_CHANNEL_STEP_KHZ as i32))
+                            .max(FM_BAND.0);
+                        let status = calibration::from_chip(
+                            scan::Band::Fm,
+                            radio
+                                .tune_frequency(calibration::to_chip(scan::Band::Fm, frequency))
+                                .await?,
+                        );
+                        if status.valid && lock.permits(status.frequency) {
+                            down = Some(status);
+                            break;
+                        }
+                    }
+                    let best = match (up, down) {
+                        (Some(up), Some(down)) if (down.snr, down.rssi) > (up.snr, up.rssi) => {
+                            Some(down)
+                        }
+                        (up, down) => up.or(down),
+                    };
+                    info!("Best of {:?} and {:?}", up, down);
+                    // The receiver is still on the station below, if it won.
+                    let tune_status = match best {
+                        Some(best) if Some(best) == down => best,
+                        best => calibration::from_chip(
+                            scan::Band::Fm,
+                            radio
+                                .tune_frequency(calibration::to_chip(
+                                    scan::Band::Fm,
+                                    best.map_or(start, |best| best.frequency),
+                                ))
+                                .await?,
+                        ),
+                    };
+                    buzzer::beep(buzzer::Beep::Seek);
+                    notification_publisher
+                        .publish(events::SystemNotify::TuneStatus(tune_status))
+                        .await;
+                }
+                events::SystemEvent::RadioPropertyDump | events::SystemEvent::RadioPropertyDiff => {
+                    let modified_only = event == events::SystemEvent::RadioPropertyDiff;
+                    let mut modified = 0;
+                    for property in properties::CURATED {
+                        if service::abort_requested() {
+                            return Err(Error::Aborted);
+                        }
+                        let value = properties::PropertyValue {
+                            property: *property,
+                            value: radio.property_get(property.id).await?,
+                        };
+                        if value.is_modified() {
+                            modified += 1;
+                        }
+                        if !modified_only || value.is_modified() {
+                            notification_publisher
+                                .publish(events::SystemNotify::Property(value))
+                                .await;
+                        }
+                    }
+                    notification_publisher
+                        .publish(events::SystemNotify::PropertyDumpDone {
+                            total: properties::CURATED.len() as u8,
+                            modified,
+                        })
+                        .await;
+                }
+                events::SystemEvent::RadioScan { all_bands } => {
+                    let start =
+                        calibration::from_chip(scan::Band::Fm, radio.tune_status_get().await?);
+                    let bands: &[scan::Band] = if all_bands {
+                        &scan::Band::ALL
+                    } else {
+                        &[scan::Band::Fm]
+                    };
+                    scan::clear();
+                    let mut aborted = false;
+                    let mut full = false;
+                    'bands: for &band in bands {
+                        if band != scan::Band::Fm {
+                            // Only the FM receiver is brought up by this firmware.
+                            notification_publisher
+                                .publish(events::SystemNotify::ScanBandSkipped(band))
+                                .await;
+                            continue;
+                        }
+                        // A seek starts at the channel above, so the bottom
+                        // channel is checked by the tune itself.
+                        let mut status = calibration::from_chip(
+                            scan::Band::Fm,
+                            radio
+                                .tune_frequency(calibration::to_chip(scan::Band::Fm, FM_BAND.0))
+                                .await?,
+                        );
+                        loop {
+                            let result = scan::ScanResult {
+                                band,
+                                frequency: status.frequency,
+                                rssi: status.rssi,
+                                snr: status.snr,
+                            };
+                            if status.valid {
+                                if scan::push(result).is_err() {
+                                    // Stop rather than silently drop stations.
+                                    full = true;
+                                    break 'bands;
+                                }
+                                notification_publisher
+                                    .publish(events::SystemNotify::ScanStation(result))
+                                    .await;
+                            }
+                            if service::abort_requested() {
+                                aborted = true;
+                                break 'bands;
+                            }
+                            let next =
+                                calibration::from_chip(scan::Band::Fm, radio.seek_up().await?);
+                            if next.frequency <= status.frequency {
+                                // Seek wrapped around the band edge.
+                                break;
+                            }
+                            status = next;
+                        }
+                    }
+                    let tune_status = calibration::from_chip(
+                        scan::Band::Fm,
+                        radio
+                            .tune_frequency(calibration::to_chip(scan::Band::Fm, start.frequency))
+                            .await?,
+                    );
+                    notification_publisher
+                        .publish(events::SystemNotify::ScanDone)
+                        .await;
+                    notification_publisher
+                        .publish(events::SystemNotify::TuneStatus(tune_status))
+                        .await;
+                    if full {
+                        return Err(Error::Full);
+                    }
+                    if aborted {
+                        return Err(Error::Aborted);
+                    }
+                }
+                events::SystemEvent::RadioProbe => {
+                    let frequencies = probe::frequencies();
+                    let lock = settings::get().tune_lock;
+                    if frequencies
+                        .iter()
+                        .any(|&frequency| !lock.permits(frequency))
+                    {
+                        return Err(Error::Locked);
+                    }
+                    let start =
+                        calibration::from_chip(scan::Band::Fm, radio.tune_status_get().await?);
+                    let mut aborted = false;
+                    for (index, &frequency) in frequencies.iter().enumerate() {
+                        if service::abort_requested() {
+                            aborted = true;
+                            break;
+                        }
+                        let status = calibration::from_chip(
+                            scan::Band::Fm,
+                            radio
+                                .tune_frequency(calibration::to_chip(scan::Band::Fm, frequency))
+                                .await?,
+                        );
+                        Timer::after(probe::DWELL).await;
+                        let rsq = radio.rsq_status_get().await?;
+                        probe::record(
+                            index,
+                            probe::Reading {
+                                valid: status.valid,
+                                rssi: rsq.rssi,
+                                snr: rsq.snr,
+                                multipath: rsq.multipath,
+                                stereo_blend: rsq.stereo_blend,
+                            },
+                        );
+                    }
+                    let tune_status = calibration::from_chip(
+                        scan::Band::Fm,
+                        radio
+                            .tune_frequency(calibration::to_chip(scan::Band::Fm, start.frequency))
+                            .await?,
+                    );
+                    notification_publisher
+                        .publish(events::SystemNotify::ProbeDone)
+                        .await;
+                    notification_publisher
+                        .publish(events::SystemNotify::TuneStatus(tune_status))
+                        .await;
+                    if aborted {
+                        return Err(Error::Aborted);
+                    }
+                }
+                events::SystemEvent::RadioProfile(profile) => {
+                    for &(property, value) in profile.values() {
+                        radio.property_set(property.id, value).await?;
+                    }
+                    settings::update(|s| s.reception.profile = profile);
+                    if stereo::is_forced() {
+                        // The profile overwrote the forced blend thresholds.
+                        for (property, value) in stereo::properties(true) {
+                            radio.property_set(property.id, value).await?;
+                        }
+                    }
+                    notification_publisher
+                        .publish(events::SystemNotify::ProfileApplied(profile))
+                        .await;
+                }
+                events::SystemEvent::RadioSeekSensitivity => {
+                    for (property, value) in seek::properties(settings::get().seek.sensitivity) {
+                        radio.property_set(property.id, value).await?;
+                    }
+                }
+                events::SystemEvent::RadioStereo => {
+                    if stereo::is_forced() && !settings::get().stereo.enabled {
+                        for (property, value) in stereo::properties(false) {
+                            radio.property_set(property.id, value).await?;
+                        }
+                        stereo::set_forced(false);
+                    }
+                }
+                events::SystemEvent::RadioDiagFm => {
+                    let rsq = radio.rsq_status_get().await?;
+                    let diagnostics = diag::FmDiagnostics {
+                        pilot: rsq.pilot,
+                        stereo_blend: rsq.stereo_blend,
+                        frequency_offset: rsq.frequency_offset,
+                        rssi: rsq.rssi,
+                        snr: rsq.snr,
+                        multipath: rsq.multipath,
+                    };
+                    notification_publisher
+                        .publish(events::SystemNotify::FmDiagnostics(diagnostics))
+                        .await;
+                }
+                _ => {
+                    info!("Event not handled in main loop");
+                }
             }
-            events::SystemEvent::RadioSeekUp => {
-                let tune_status = radio.seek_up().await.expect("Seek up failed");
-                info!("Seeked up: {:?}", tune_status);
+            Ok::<(), Error>(())
+        }
+        .await;
+        if operation.is_some() {
+            notification_publisher
+                .publish(events::SystemNotify::Progress(None))
+                .await;
+        }
+        if let Some(previous) = restore_mute
+            && radio
+                .property_set(properties::RX_HARD_MUTE.id, previous)
+                .await
+                .is_err()
+        {
+            warn!("Failed to restore mute state");
+        }
+        let tuned = if event.is_tuning() {
+            // Show the last known name until PS is decoded again.
+            rds_decoder.reset();
+            let status = radio
+                .tune_status_get()
+                .await
+                .map(|status| calibration::from_chip(scan::Band::Fm, status))
+                .map_err(Error::from);
+            if let Ok(status) = status
+                && let Some(station) = rds_db::find_frequency(status.frequency)
+            {
                 notification_publisher
-                    .publish(events::SystemNotify::TuneStatus(tune_status))
+                    .publish(events::SystemNotify::RdsStation(station))
                     .await;
             }
-            _ => {
-                info!("Event not handled in main loop");
+            // Level the volume of the new station, unless a ramp sets it.
+            if let Ok(status) = status
+                && volume_ramp.is_none()
+                && let Ok(current) = radio.property_get(properties::RX_VOLUME.id).await
+                && let Some(volume) = leveler.retuned(status.frequency, current)
+            {
+                match radio.property_set(properties::RX_VOLUME.id, volume).await {
+                    Ok(()) => {
+                        notification_publisher
+                            .publish(events::SystemNotify::VolumeChanged(volume::percent(volume)))
+                            .await
+                    }
+                    Err(_) => warn!("Failed to level the volume"),
+                }
+            }
+            Some(status)
+        } else {
+            None
+        };
+        if let Some(number) = requested {
+            service::complete(
+                number,
+                match (result, tuned) {
+                    (Err(err), _) => Err(err),
+                    (Ok(()), Some(status)) => status.map(service::Outcome::Tuned),
+                    (Ok(()), None) => Ok(service::Outcome::Done),
+                },
+            );
+        }
+        if let Err(err) = result {
+            warn!("{:?} failed: {}", event, err);
+            if err != Error::Aborted {
+                buzzer::beep(buzzer::Beep::Error);
             }
+            notification_publisher
+                .publish(events::SystemNotify::Error(err))
+                .await;
         }
+        notification_publisher.set_origin(events::Origin::Radio);
     }
 }
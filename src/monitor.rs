@@ -0,0 +1,82 @@
+//! Lightweight task liveness monitor.
+//!
+//! Each long-running task registers itself once with [`register`] and calls
+//! [`Heartbeat::beat`] whenever it wakes up to do work. The `tasks` CLI
+//! command walks the registry with [`for_each`] to show how long ago every
+//! task was last seen, which makes a task stuck on an await easy to spot in
+//! the field. Tasks that are legitimately idle (waiting for input) simply
+//! report an old timestamp.
+
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_time::Instant;
+use log::error;
+
+use crate::buffers::{self, Buffer};
+
+/// Maximum number of tasks that can register: the 20 spawned by `main`, one
+/// per button, and room for a few more.
+pub const MAX_TASKS: usize = 24;
+
+/// Liveness record of a registered task.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TaskInfo {
+    /// Name given at registration.
+    pub name: &'static str,
+    /// Time of the last heartbeat.
+    pub last_alive: Instant,
+    /// Number of heartbeats since registration.
+    pub beats: u32,
+}
+
+static TASKS: Mutex<ThreadModeRawMutex, RefCell<heapless::Vec<TaskInfo, MAX_TASKS>>> =
+    Mutex::new(RefCell::new(heapless::Vec::new()));
+
+/// Handle used by a task to report that it is alive.
+#[derive(Debug, Copy, Clone)]
+pub struct Heartbeat(Option<usize>);
+
+impl Heartbeat {
+    /// Record that the task is alive now.
+    pub fn beat(&self) {
+        let Some(index) = self.0 else {
+            return;
+        };
+        TASKS.lock(|tasks| {
+            if let Some(task) = tasks.borrow_mut().get_mut(index) {
+                task.last_alive = Instant::now();
+                task.beats = task.beats.wrapping_add(1);
+            }
+        });
+    }
+}
+
+/// Register a task under `name` and return its heartbeat handle.
+///
+/// If the registry is full the task is not tracked and its heartbeats are
+/// ignored; raise [`MAX_TASKS`] when adding a task logs this error.
+pub fn register(name: &'static str) -> Heartbeat {
+    let task = TaskInfo {
+        name,
+        last_alive: Instant::now(),
+        beats: 0,
+    };
+    TASKS.lock(|tasks| {
+        let mut tasks = tasks.borrow_mut();
+        match tasks.push(task) {
+            Ok(()) => Heartbeat(Some(tasks.len() - 1)),
+            Err(_) => {
+                buffers::overflowed(Buffer::Tasks);
+                error!("Task monitor full, not tracking {}", name);
+                Heartbeat(None)
+            }
+        }
+    })
+}
+
+/// Call `f` for every registered task, in registration order.
+pub fn for_each(mut f: impl FnMut(&TaskInfo)) {
+    TASKS.lock(|tasks| tasks.borrow().iter().for_each(&mut f));
+}
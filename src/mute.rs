@@ -0,0 +1,59 @@
+//! Timed mute.
+//!
+//! A [`TimedMute`] keeps the audio muted for a given time, for example to
+//! skip a commercial break. Like a volume [`Ramp`](crate::ramp::Ramp) it is
+//! owned by the radio loop, which wakes up at [`TimedMute::next_tick`] and
+//! publishes the remaining seconds returned by [`TimedMute::tick`] as a
+//! countdown until the time is up and audio is unmuted again. Any other mute
+//! or unmute cancels the timer.
+
+use embassy_time::{Duration, Instant};
+
+/// Time between countdown notifications.
+const TICK: Duration = Duration::from_secs(1);
+/// Longest timed mute, in seconds.
+pub const MAX_SECONDS: u16 = 60 * 60;
+
+/// Parse a mute duration such as `30s`, `2m` or `45`, in seconds.
+pub fn parse_duration(text: &str) -> Option<u16> {
+    let (digits, scale) = if let Some(minutes) = text.strip_suffix('m') {
+        (minutes, 60)
+    } else {
+        (text.strip_suffix('s').unwrap_or(text), 1)
+    };
+    let seconds = digits.parse::<u16>().ok()?.checked_mul(scale)?;
+    (1..=MAX_SECONDS).contains(&seconds).then_some(seconds)
+}
+
+/// A mute that ends by itself.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TimedMute {
+    until: Instant,
+    next: Instant,
+}
+
+impl TimedMute {
+    /// Mute for `duration`, starting now.
+    pub fn new(duration: Duration) -> Self {
+        let now = Instant::now();
+        Self {
+            until: now + duration,
+            next: now,
+        }
+    }
+
+    /// When [`tick`](Self::tick) should be called next.
+    pub fn next_tick(&self) -> Instant {
+        self.next
+    }
+
+    /// Whole seconds left, rounded up, or `None` once the time is up.
+    pub fn tick(&mut self, now: Instant) -> Option<u16> {
+        if now >= self.until {
+            return None;
+        }
+        let left = self.until - now;
+        self.next = (now + TICK).min(self.until);
+        Some(left.as_millis().div_ceil(1000).min(u16::MAX as u64) as u16)
+    }
+}
@@ -0,0 +1,169 @@
+//! Si47xx firmware patch stored in flash.
+//!
+//! Some features (SSB on the Si4735/Si4732, assorted firmware fixes) need a
+//! patch loaded into the tuner's RAM after every power-up. The patch is kept
+//! in [`storage::PATCH`](crate::storage::PATCH) and applied during startup
+//! when present: the tuner is powered up once in patch mode, which also
+//! selects FM, so no later power-up clears the patch again. It only counts as
+//! applied once the FM receiver is up.
+//!
+//! The host installs a patch with `patch upload <length> <crc>` and then sends
+//! the image over XMODEM/YMODEM (see [`transfer`](crate::transfer)). The image
+//! is the concatenation of the 8-byte command lines of the vendor patch and
+//! `crc` is its CRC-32; block padding beyond `length` is ignored. The header
+//! is only written once the image is complete and its checksum matches, so an
+//! interrupted upload leaves no patch behind.
+
+use core::cell::Cell;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+
+use crate::checksum::Crc32;
+use crate::error::Error;
+use crate::storage::PATCH;
+use crate::transfer::Sink;
+
+/// Size of one patch command line.
+pub const LINE_SIZE: usize = 8;
+
+/// Marks a valid header ("S47P").
+const MAGIC: u32 = 0x5034_3753;
+const HEADER_SIZE: u32 = 16;
+/// Bytes read at a time while verifying the image.
+const CHUNK_SIZE: usize = 64;
+
+/// Largest patch image that fits into the partition.
+pub const CAPACITY: u32 = PATCH.size - HEADER_SIZE;
+
+/// Description of a stored patch.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PatchInfo {
+    /// Image size in bytes.
+    pub length: u32,
+    /// CRC-32 of the image.
+    pub crc: u32,
+}
+
+impl PatchInfo {
+    /// Number of command lines in the image.
+    pub const fn lines(&self) -> u32 {
+        self.length / LINE_SIZE as u32
+    }
+}
+
+static APPLIED: Mutex<ThreadModeRawMutex, Cell<bool>> = Mutex::new(Cell::new(false));
+
+/// Record that the stored patch was loaded into the tuner.
+pub fn mark_applied() {
+    APPLIED.lock(|applied| applied.set(true));
+}
+
+/// Whether the stored patch was loaded into the tuner since power-up.
+pub fn is_applied() -> bool {
+    APPLIED.lock(|applied| applied.get())
+}
+
+/// The stored patch, if any.
+pub fn stored() -> Result<Option<PatchInfo>, Error> {
+    let mut header = [0u8; HEADER_SIZE as usize];
+    PATCH.read(0, &mut header)?;
+    let word = |index: usize| {
+        u32::from_le_bytes([
+            header[index * 4],
+            header[index * 4 + 1],
+            header[index * 4 + 2],
+            header[index * 4 + 3],
+        ])
+    };
+    let info = PatchInfo {
+        length: word(1),
+        crc: word(2),
+    };
+    let valid = word(0) == MAGIC
+        && info.length <= CAPACITY
+        && (info.length as usize).is_multiple_of(LINE_SIZE);
+    Ok(valid.then_some(info))
+}
+
+/// Recompute the checksum of the stored image.
+pub fn verify(info: &PatchInfo) -> Result<bool, Error> {
+    let mut crc = Crc32::new();
+    let mut chunk = [0u8; CHUNK_SIZE];
+    let mut offset = 0;
+    while offset < info.length {
+        let len = (info.length - offset).min(CHUNK_SIZE as u32) as usize;
+        PATCH.read(HEADER_SIZE + offset, &mut chunk[..len])?;
+        crc.update(&chunk[..len]);
+        offset += len as u32;
+    }
+    Ok(crc.finish() == info.crc)
+}
+
+/// Command lines of the stored image, in the order they must be sent.
+pub fn lines(info: &PatchInfo) -> impl Iterator<Item = Result<[u8; LINE_SIZE], Error>> {
+    (0..info.lines()).map(|index| {
+        let mut line = [0u8; LINE_SIZE];
+        PATCH.read(HEADER_SIZE + index * LINE_SIZE as u32, &mut line)?;
+        Ok(line)
+    })
+}
+
+/// Remove the stored patch.
+pub fn erase() -> Result<(), Error> {
+    PATCH.erase_all()?;
+    Ok(())
+}
+
+/// Upload in progress, fed by a file transfer.
+pub struct Upload {
+    info: PatchInfo,
+    received: u32,
+    crc: Crc32,
+}
+
+impl Upload {
+    /// Erase the partition and prepare for `length` bytes with checksum `crc`.
+    pub fn begin(length: u32, crc: u32) -> Result<Self, Error> {
+        if length == 0 || length > CAPACITY || !(length as usize).is_multiple_of(LINE_SIZE) {
+            return Err(Error::InvalidArgument);
+        }
+        erase()?;
+        Ok(Self {
+            info: PatchInfo { length, crc },
+            received: 0,
+            crc: Crc32::new(),
+        })
+    }
+}
+
+impl Sink for Upload {
+    fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        let len = data.len().min((self.info.length - self.received) as usize);
+        if len == 0 {
+            return Ok(());
+        }
+        let data = &data[..len];
+        // Transfer blocks are multiples of 128 bytes and images multiples of
+        // 8, so every write stays word-aligned.
+        PATCH.write(HEADER_SIZE + self.received, data)?;
+        self.crc.update(data);
+        self.received += len as u32;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        if self.received != self.info.length {
+            return Err(Error::Transfer);
+        }
+        if self.crc.finish() != self.info.crc {
+            return Err(Error::Checksum);
+        }
+        let mut header = [0xFFu8; HEADER_SIZE as usize];
+        header[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        header[4..8].copy_from_slice(&self.info.length.to_le_bytes());
+        header[8..12].copy_from_slice(&self.info.crc.to_le_bytes());
+        PATCH.write(0, &header)?;
+        Ok(())
+    }
+}
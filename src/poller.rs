@@ -0,0 +1,166 @@
+//! Scheduling of periodic chip reads.
+//!
+//! Only the radio loop talks to the tuner, so all periodic reads go through
+//! this coordinator instead of separate tasks queueing events for it. Each
+//! [`Source`] has an enable flag and an interval; the radio loop asks
+//! [`next_due`] which source to read next, waits for that deadline or the
+//! next event, performs the read and reports the outcome with [`complete`].
+//! Failing sources back off exponentially, up to [`MAX_BACKOFF`] times their
+//! interval, and recover on the first successful read.
+
+use core::cell::Cell;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant};
+
+/// Largest multiple of the interval a failing source is delayed by.
+pub const MAX_BACKOFF: u32 = 16;
+
+/// A periodic chip read.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// Received signal quality, published as a notification.
+    Rsq,
+    /// RDS group FIFO.
+    Rds,
+    /// Survey sample written to flash.
+    Survey,
+    /// SNR read for the [`stereo`](crate::stereo) override.
+    Stereo,
+    /// Signal quality line of the [`telemetry`](crate::telemetry) stream.
+    Telemetry,
+}
+
+impl Source {
+    pub const ALL: [Source; 5] = [
+        Source::Rsq,
+        Source::Rds,
+        Source::Survey,
+        Source::Stereo,
+        Source::Telemetry,
+    ];
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Source::Rsq => "rsq",
+            Source::Rds => "rds",
+            Source::Survey => "survey",
+            Source::Stereo => "stereo",
+            Source::Telemetry => "telemetry",
+        }
+    }
+
+    const fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Configuration and bookkeeping of one source.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SourceState {
+    pub enabled: bool,
+    pub interval: Duration,
+    /// Consecutive failed reads.
+    pub failures: u8,
+    /// Successful reads since boot.
+    pub reads: u32,
+    /// When the source was last read, successfully or not.
+    pub last_read: Option<Instant>,
+}
+
+impl SourceState {
+    const fn new(enabled: bool, interval: Duration) -> Self {
+        Self {
+            enabled,
+            interval,
+            failures: 0,
+            reads: 0,
+            last_read: None,
+        }
+    }
+
+    /// Interval stretched by the current backoff.
+    pub fn effective_interval(&self) -> Duration {
+        let factor = 1u32 << self.failures.min(MAX_BACKOFF.ilog2() as u8);
+        self.interval * factor
+    }
+
+    fn due(&self) -> Instant {
+        match self.last_read {
+            Some(at) => at + self.effective_interval(),
+            None => Instant::now(),
+        }
+    }
+}
+
+static SOURCES: Mutex<ThreadModeRawMutex, Cell<[SourceState; Source::ALL.len()]>> =
+    Mutex::new(Cell::new([
+        SourceState::new(false, Duration::from_secs(1)),
+        SourceState::new(true, Duration::from_millis(250)),
+        SourceState::new(false, Duration::from_secs(10)),
+        SourceState::new(false, Duration::from_secs(1)),
+        SourceState::new(false, Duration::from_secs(1)),
+    ]));
+/// Wakes the radio loop when the schedule changed.
+static CHANGED: Signal<ThreadModeRawMutex, ()> = Signal::new();
+
+/// Current state of `source`.
+pub fn state(source: Source) -> SourceState {
+    SOURCES.lock(|sources| sources.get()[source.index()])
+}
+
+fn modify(source: Source, f: impl FnOnce(&mut SourceState)) {
+    SOURCES.lock(|sources| {
+        let mut all = sources.get();
+        f(&mut all[source.index()]);
+        sources.set(all);
+    });
+}
+
+/// Enable or disable `source`. A newly enabled source is read right away.
+pub fn set_enabled(source: Source, enabled: bool) {
+    modify(source, |state| {
+        if enabled && !state.enabled {
+            state.last_read = None;
+            state.failures = 0;
+        }
+        state.enabled = enabled;
+    });
+    CHANGED.signal(());
+}
+
+/// Change the interval of `source`.
+pub fn set_interval(source: Source, interval: Duration) {
+    modify(source, |state| state.interval = interval);
+    CHANGED.signal(());
+}
+
+/// The enabled source to read next and when it is due.
+pub fn next_due() -> Option<(Source, Instant)> {
+    let sources = SOURCES.lock(|sources| sources.get());
+    Source::ALL
+        .into_iter()
+        .filter(|source| sources[source.index()].enabled)
+        .map(|source| (source, sources[source.index()].due()))
+        .min_by_key(|&(_, due)| due)
+}
+
+/// Record the outcome of reading `source`.
+pub fn complete(source: Source, ok: bool) {
+    modify(source, |state| {
+        state.last_read = Some(Instant::now());
+        if ok {
+            state.failures = 0;
+            state.reads = state.reads.wrapping_add(1);
+        } else {
+            state.failures = state.failures.saturating_add(1);
+        }
+    });
+}
+
+/// Wait until the schedule was changed by [`set_enabled`] or [`set_interval`].
+pub async fn changed() {
+    CHANGED.wait().await
+}
@@ -0,0 +1,97 @@
+//! Signal check of a few known stations.
+//!
+//! `probe 88.1 94.5 101.7` stores the frequencies with [`set`] and sends
+//! [`SystemEvent::RadioProbe`]. The radio loop tunes each one in turn, lets
+//! the signal quality readings settle for [`DWELL`], records them with
+//! [`record`] and returns to the station it started on, so a handful of
+//! stations can be compared without a full scan. The readings stay until the
+//! next probe.
+//!
+//! [`SystemEvent::RadioProbe`]: crate::events::SystemEvent::RadioProbe
+
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_time::Duration;
+
+use crate::frequency::Frequency;
+
+/// Most frequencies probed at once.
+pub const MAX_FREQUENCIES: usize = 8;
+/// Time on each frequency before its signal quality is read.
+pub const DWELL: Duration = Duration::from_millis(250);
+
+/// Signal quality read on one frequency.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Reading {
+    /// The tuner reports a valid station.
+    pub valid: bool,
+    /// Received signal strength in dBµV.
+    pub rssi: u8,
+    /// Signal-to-noise ratio in dB.
+    pub snr: u8,
+    pub multipath: u8,
+    /// Stereo share of the blend, in percent.
+    pub stereo_blend: u8,
+}
+
+/// A probed frequency and its reading, `None` until it was tuned.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Probe {
+    pub frequency: Frequency,
+    pub reading: Option<Reading>,
+}
+
+static PROBES: Mutex<ThreadModeRawMutex, RefCell<heapless::Vec<Probe, MAX_FREQUENCIES>>> =
+    Mutex::new(RefCell::new(heapless::Vec::new()));
+
+/// Replace the frequencies to probe, dropping the previous readings.
+pub fn set(frequencies: &[Frequency]) {
+    PROBES.lock(|probes| {
+        let mut probes = probes.borrow_mut();
+        probes.clear();
+        for &frequency in frequencies.iter().take(MAX_FREQUENCIES) {
+            let _ = probes.push(Probe {
+                frequency,
+                reading: None,
+            });
+        }
+    });
+}
+
+/// The frequencies to probe, in the order given.
+pub fn frequencies() -> heapless::Vec<Frequency, MAX_FREQUENCIES> {
+    PROBES.lock(|probes| {
+        probes
+            .borrow()
+            .iter()
+            .map(|probe| probe.frequency)
+            .collect()
+    })
+}
+
+/// Store the reading of probe `index`.
+pub fn record(index: usize, reading: Reading) {
+    PROBES.lock(|probes| {
+        if let Some(probe) = probes.borrow_mut().get_mut(index) {
+            probe.reading = Some(reading);
+        }
+    });
+}
+
+/// Copy of the probes and their readings.
+pub fn probes() -> heapless::Vec<Probe, MAX_FREQUENCIES> {
+    PROBES.lock(|probes| probes.borrow().clone())
+}
+
+/// Index of the probe with the best signal-to-noise ratio, the stronger
+/// signal breaking ties.
+pub fn best(probes: &[Probe]) -> Option<usize> {
+    probes
+        .iter()
+        .enumerate()
+        .filter_map(|(index, probe)| probe.reading.map(|reading| (index, reading)))
+        .max_by_key(|(_, reading)| (reading.snr, reading.rssi))
+        .map(|(index, _)| index)
+}
@@ -0,0 +1,178 @@
+//! Si47xx property table.
+//!
+//! Names, identifiers and power-up defaults (from AN332) of the properties
+//! that matter most when comparing boards. [`CURATED`] is what `prop dump`
+//! reads back from the chip; `prop diff` reports entries whose value differs
+//! from [`Property::default`]. [`Profile`] bundles related reception
+//! properties so they can be switched with one command.
+
+use serde::{Deserialize, Serialize};
+
+/// A chip property with its power-up default.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Property {
+    /// Property identifier as used by `SET_PROPERTY`/`GET_PROPERTY`.
+    pub id: u16,
+    /// Datasheet name.
+    pub name: &'static str,
+    /// Value after power-up.
+    pub default: u16,
+}
+
+impl Property {
+    pub const fn new(id: u16, name: &'static str, default: u16) -> Self {
+        Self { id, name, default }
+    }
+
+    /// The property paired with its power-up default, for bulk writes.
+    pub const fn at_default(self) -> (Property, u16) {
+        (self, self.default)
+    }
+}
+
+/// A property together with the value read from the chip.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PropertyValue {
+    pub property: Property,
+    pub value: u16,
+}
+
+impl PropertyValue {
+    /// Whether the value differs from the power-up default.
+    pub const fn is_modified(&self) -> bool {
+        self.value != self.property.default
+    }
+}
+
+pub const GPO_IEN: Property = Property::new(0x0001, "GPO_IEN", 0x0000);
+pub const REFCLK_FREQ: Property = Property::new(0x0201, "REFCLK_FREQ", 0x8000);
+pub const REFCLK_PRESCALE: Property = Property::new(0x0202, "REFCLK_PRESCALE", 0x0001);
+pub const FM_DEEMPHASIS: Property = Property::new(0x1100, "FM_DEEMPHASIS", 0x0002);
+pub const FM_CHANNEL_FILTER: Property = Property::new(0x1102, "FM_CHANNEL_FILTER", 0x0000);
+pub const FM_ANTENNA_INPUT: Property = Property::new(0x1107, "FM_ANTENNA_INPUT", 0x0000);
+pub const FM_MAX_TUNE_ERROR: Property = Property::new(0x1108, "FM_MAX_TUNE_ERROR", 0x001E);
+pub const FM_SOFT_MUTE_MAX_ATTENUATION: Property =
+    Property::new(0x1302, "FM_SOFT_MUTE_MAX_ATTENUATION", 0x0010);
+pub const FM_SOFT_MUTE_SNR_THRESHOLD: Property =
+    Property::new(0x1303, "FM_SOFT_MUTE_SNR_THRESHOLD", 0x0004);
+pub const FM_SEEK_BAND_BOTTOM: Property = Property::new(0x1400, "FM_SEEK_BAND_BOTTOM", 0x222E);
+pub const FM_SEEK_BAND_TOP: Property = Property::new(0x1401, "FM_SEEK_BAND_TOP", 0x2A30);
+pub const FM_SEEK_FREQ_SPACING: Property = Property::new(0x1402, "FM_SEEK_FREQ_SPACING", 0x000A);
+pub const FM_SEEK_TUNE_SNR_THRESHOLD: Property =
+    Property::new(0x1403, "FM_SEEK_TUNE_SNR_THRESHOLD", 0x0003);
+pub const FM_SEEK_TUNE_RSSI_THRESHOLD: Property =
+    Property::new(0x1404, "FM_SEEK_TUNE_RSSI_THRESHOLD", 0x0014);
+pub const FM_RDS_CONFIG: Property = Property::new(0x1502, "FM_RDS_CONFIG", 0x0000);
+pub const FM_BLEND_RSSI_STEREO_THRESHOLD: Property =
+    Property::new(0x1800, "FM_BLEND_RSSI_STEREO_THRESHOLD", 0x0031);
+pub const FM_BLEND_RSSI_MONO_THRESHOLD: Property =
+    Property::new(0x1801, "FM_BLEND_RSSI_MONO_THRESHOLD", 0x001E);
+pub const FM_BLEND_SNR_STEREO_THRESHOLD: Property =
+    Property::new(0x1804, "FM_BLEND_SNR_STEREO_THRESHOLD", 0x001B);
+pub const FM_BLEND_SNR_MONO_THRESHOLD: Property =
+    Property::new(0x1805, "FM_BLEND_SNR_MONO_THRESHOLD", 0x000E);
+pub const FM_HICUT_SNR_HIGH_THRESHOLD: Property =
+    Property::new(0x1A00, "FM_HICUT_SNR_HIGH_THRESHOLD", 0x0018);
+pub const FM_HICUT_SNR_LOW_THRESHOLD: Property =
+    Property::new(0x1A01, "FM_HICUT_SNR_LOW_THRESHOLD", 0x000F);
+pub const FM_HICUT_CUTOFF_FREQ: Property = Property::new(0x1A05, "FM_HICUT_CUTOFF_FREQ", 0x0000);
+pub const RX_VOLUME: Property = Property::new(0x4000, "RX_VOLUME", 0x003F);
+pub const RX_HARD_MUTE: Property = Property::new(0x4001, "RX_HARD_MUTE", 0x0000);
+
+/// `FM_RDS_CONFIG` value enabling RDS and keeping every group in the FIFO;
+/// the decoder checks the error level of the blocks it uses.
+pub const FM_RDS_CONFIG_ENABLE: u16 = 0xFF01;
+
+/// `RX_HARD_MUTE` value muting both channels.
+pub const RX_HARD_MUTE_BOTH: u16 = 0x0003;
+
+/// Properties read by `prop dump` and `prop diff`, in display order.
+pub const CURATED: &[Property] = &[
+    GPO_IEN,
+    REFCLK_FREQ,
+    REFCLK_PRESCALE,
+    FM_DEEMPHASIS,
+    FM_CHANNEL_FILTER,
+    FM_ANTENNA_INPUT,
+    FM_MAX_TUNE_ERROR,
+    FM_SOFT_MUTE_MAX_ATTENUATION,
+    FM_SOFT_MUTE_SNR_THRESHOLD,
+    FM_SEEK_BAND_BOTTOM,
+    FM_SEEK_BAND_TOP,
+    FM_SEEK_FREQ_SPACING,
+    FM_SEEK_TUNE_SNR_THRESHOLD,
+    FM_SEEK_TUNE_RSSI_THRESHOLD,
+    FM_RDS_CONFIG,
+    FM_BLEND_RSSI_STEREO_THRESHOLD,
+    FM_BLEND_RSSI_MONO_THRESHOLD,
+    FM_BLEND_SNR_STEREO_THRESHOLD,
+    FM_BLEND_SNR_MONO_THRESHOLD,
+    FM_HICUT_SNR_HIGH_THRESHOLD,
+    FM_HICUT_SNR_LOW_THRESHOLD,
+    FM_HICUT_CUTOFF_FREQ,
+    RX_VOLUME,
+    RX_HARD_MUTE,
+];
+
+/// Reception profile bundling blend, soft-mute and hi-cut thresholds.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Profile {
+    /// Weak, distant stations: blend to mono, soft-mute and cut highs early.
+    Dx,
+    /// Power-up defaults.
+    Normal,
+    /// Strong local stations: keep stereo and full bandwidth as long as possible.
+    Local,
+}
+
+impl Profile {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Profile::Dx => "DX",
+            Profile::Normal => "Normal",
+            Profile::Local => "Local",
+        }
+    }
+
+    /// Property values written when the profile is selected.
+    pub const fn values(&self) -> &'static [(Property, u16)] {
+        match self {
+            Profile::Dx => PROFILE_DX,
+            Profile::Normal => PROFILE_NORMAL,
+            Profile::Local => PROFILE_LOCAL,
+        }
+    }
+}
+
+const PROFILE_DX: &[(Property, u16)] = &[
+    (FM_BLEND_RSSI_STEREO_THRESHOLD, 55),
+    (FM_BLEND_RSSI_MONO_THRESHOLD, 40),
+    (FM_BLEND_SNR_STEREO_THRESHOLD, 35),
+    (FM_BLEND_SNR_MONO_THRESHOLD, 25),
+    (FM_SOFT_MUTE_MAX_ATTENUATION, 20),
+    (FM_SOFT_MUTE_SNR_THRESHOLD, 8),
+    (FM_HICUT_SNR_HIGH_THRESHOLD, 30),
+    (FM_HICUT_SNR_LOW_THRESHOLD, 20),
+];
+
+const PROFILE_NORMAL: &[(Property, u16)] = &[
+    FM_BLEND_RSSI_STEREO_THRESHOLD.at_default(),
+    FM_BLEND_RSSI_MONO_THRESHOLD.at_default(),
+    FM_BLEND_SNR_STEREO_THRESHOLD.at_default(),
+    FM_BLEND_SNR_MONO_THRESHOLD.at_default(),
+    FM_SOFT_MUTE_MAX_ATTENUATION.at_default(),
+    FM_SOFT_MUTE_SNR_THRESHOLD.at_default(),
+    FM_HICUT_SNR_HIGH_THRESHOLD.at_default(),
+    FM_HICUT_SNR_LOW_THRESHOLD.at_default(),
+];
+
+const PROFILE_LOCAL: &[(Property, u16)] = &[
+    (FM_BLEND_RSSI_STEREO_THRESHOLD, 30),
+    (FM_BLEND_RSSI_MONO_THRESHOLD, 20),
+    (FM_BLEND_SNR_STEREO_THRESHOLD, 15),
+    (FM_BLEND_SNR_MONO_THRESHOLD, 8),
+    (FM_SOFT_MUTE_MAX_ATTENUATION, 8),
+    (FM_SOFT_MUTE_SNR_THRESHOLD, 2),
+    (FM_HICUT_SNR_HIGH_THRESHOLD, 15),
+    (FM_HICUT_SNR_LOW_THRESHOLD, 8),
+];
@@ -0,0 +1,53 @@
+//! Gradual volume changes.
+//!
+//! A [`Ramp`] moves `RX_VOLUME` linearly from one level to another over a
+//! given time. The radio loop owns the active ramp: it wakes up at
+//! [`Ramp::next_step`] alongside the poller deadlines, writes the level
+//! returned by [`Ramp::step`] and drops the ramp once it is done, so events
+//! keep being handled while the volume moves. Any other volume change cancels
+//! the ramp.
+
+use embassy_time::{Duration, Instant};
+
+/// A linear volume change in chip volume steps.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Ramp {
+    from: u16,
+    to: u16,
+    start: Instant,
+    duration: Duration,
+    next: Instant,
+}
+
+impl Ramp {
+    /// Ramp from `from` to `to`, starting now.
+    pub fn new(from: u16, to: u16, duration: Duration) -> Self {
+        let start = Instant::now();
+        Self {
+            from,
+            to,
+            start,
+            duration,
+            next: start,
+        }
+    }
+
+    /// When [`step`](Self::step) should be called next.
+    pub fn next_step(&self) -> Instant {
+        self.next
+    }
+
+    /// Level to set now. Returns the level and whether the ramp is done.
+    pub fn step(&mut self, now: Instant) -> (u16, bool) {
+        let elapsed = now.saturating_duration_since(self.start);
+        if elapsed >= self.duration {
+            return (self.to, true);
+        }
+        let delta = self.to as i64 - self.from as i64;
+        let progress = delta * elapsed.as_millis() as i64 / self.duration.as_millis() as i64;
+        // One wake-up per volume step.
+        let interval = self.duration / delta.unsigned_abs().max(1) as u32;
+        self.next = (now + interval).min(self.start + self.duration);
+        ((self.from as i64 + progress) as u16, false)
+    }
+}
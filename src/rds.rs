@@ -0,0 +1,217 @@
+//! RDS group decoding.
+//!
+//! The radio loop drains the tuner's RDS FIFO on every
+//! [`poller::Source::Rds`](crate::poller::Source::Rds) poll and feeds
+//! each group to a [`Decoder`], which reports the program identification
+//! (PI) code as soon as it is seen and the program service (PS) name once all
+//! four segments of group 0A/0B have arrived. The tuner keeps every group in
+//! its FIFO, so the decoder drops a group when a block it needs could not be
+//! corrected. Changes of the programme type (PTY) are reported too, so alarm
+//! announcements can be acted on. Clock time from group 4A is passed on as
+//! received, once all its blocks are intact. Learned stations are kept in
+//! [`rds_db`](crate::rds_db).
+
+use core::fmt;
+
+use heapless::Vec;
+
+use crate::buffers::{self, Buffer};
+
+/// Program service name, restricted to printable ASCII.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct ProgramService([u8; 8]);
+
+impl ProgramService {
+    /// Wrap raw PS characters, replacing anything outside printable ASCII
+    /// (the RDS character set differs from Latin-1 above 0x7F) with `?`.
+    pub fn new(mut chars: [u8; 8]) -> Self {
+        for c in chars.iter_mut() {
+            if !(0x20..=0x7E).contains(c) {
+                *c = b'?';
+            }
+        }
+        Self(chars)
+    }
+
+    pub const fn as_bytes(&self) -> &[u8; 8] {
+        &self.0
+    }
+
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.0).unwrap_or_default()
+    }
+}
+
+impl fmt::Debug for ProgramService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl fmt::Display for ProgramService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Block error level the tuner reports for a block it could not correct.
+const UNCORRECTABLE: u8 = 3;
+
+/// One group as read from the tuner's RDS FIFO.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Group {
+    /// Blocks A to D.
+    pub blocks: [u16; 4],
+    /// Block error levels as in `FM_RDS_STATUS`: two bits per block, block A
+    /// in the top bits, from 0 (no errors) to 3 (uncorrectable).
+    pub errors: u8,
+}
+
+impl Group {
+    /// Whether block `index` (0 for A to 3 for D) was received or corrected.
+    pub const fn is_usable(&self, index: usize) -> bool {
+        (self.errors >> (6 - 2 * index)) & 0x3 != UNCORRECTABLE
+    }
+}
+
+/// Clock time and date broadcast in group 4A.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ClockTime {
+    /// Modified Julian Day.
+    pub mjd: u32,
+    /// UTC hour.
+    pub hour: u8,
+    /// UTC minute.
+    pub minute: u8,
+    /// Local time offset from UTC in half hours.
+    pub offset: i8,
+}
+
+impl ClockTime {
+    fn decode(b: u16, c: u16, d: u16) -> Option<Self> {
+        let hour = (((c & 0x1) << 4) | (d >> 12)) as u8;
+        let minute = ((d >> 6) & 0x3F) as u8;
+        if hour > 23 || minute > 59 {
+            return None;
+        }
+        let half_hours = (d & 0x1F) as i8;
+        Some(Self {
+            mjd: (((b & 0x3) as u32) << 15) | (c >> 1) as u32,
+            hour,
+            minute,
+            offset: if d & 0x20 != 0 {
+                -half_hours
+            } else {
+                half_hours
+            },
+        })
+    }
+}
+
+/// Information extracted from an RDS group.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RdsUpdate {
+    /// A new PI code is being received.
+    Pi(u16),
+    /// The programme type has changed.
+    Pty(u8),
+    /// The PS name is complete or has changed.
+    Ps(ProgramService),
+    /// Clock time was received.
+    Clock(ClockTime),
+}
+
+/// Decoder state for the currently tuned station.
+#[derive(Debug, Copy, Clone)]
+pub struct Decoder {
+    pi: Option<u16>,
+    pty: Option<u8>,
+    ps: [u8; 8],
+    /// Bit per two-character PS segment received since the last report.
+    segments: u8,
+    reported: Option<ProgramService>,
+}
+
+impl Decoder {
+    pub const fn new() -> Self {
+        Self {
+            pi: None,
+            pty: None,
+            ps: [b' '; 8],
+            segments: 0,
+            reported: None,
+        }
+    }
+
+    /// Forget everything, e.g. after retuning.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// PI code of the current station, once received.
+    pub const fn pi(&self) -> Option<u16> {
+        self.pi
+    }
+
+    /// Decode one group.
+    pub fn feed(&mut self, group: Group) -> Vec<RdsUpdate, 3> {
+        let mut updates = Vec::new();
+        // Block A names the station and block B the group type, so nothing
+        // can be taken from a group without them.
+        if !group.is_usable(0) || !group.is_usable(1) {
+            return updates;
+        }
+        let [a, b, c, d] = group.blocks;
+        if self.pi != Some(a) {
+            self.reset();
+            self.pi = Some(a);
+            push(&mut updates, RdsUpdate::Pi(a));
+        }
+        // Every group type carries the programme type in block B.
+        let pty = ((b >> 5) & 0x1F) as u8;
+        if self.pty != Some(pty) {
+            self.pty = Some(pty);
+            push(&mut updates, RdsUpdate::Pty(pty));
+        }
+        let group_type = b >> 12;
+        let version_b = b & 0x0800 != 0;
+        if group_type == 4 && !version_b {
+            // The time spans blocks B to D; a wrong digit would set the clock.
+            if group.is_usable(2)
+                && group.is_usable(3)
+                && let Some(time) = ClockTime::decode(b, c, d)
+            {
+                push(&mut updates, RdsUpdate::Clock(time));
+            }
+            return updates;
+        }
+        if group_type != 0 || !group.is_usable(3) {
+            return updates;
+        }
+        let segment = (b & 0x3) as usize;
+        self.ps[segment * 2..segment * 2 + 2].copy_from_slice(&d.to_be_bytes());
+        self.segments |= 1 << segment;
+        if self.segments == 0b1111 {
+            self.segments = 0;
+            let ps = ProgramService::new(self.ps);
+            if self.reported != Some(ps) {
+                self.reported = Some(ps);
+                push(&mut updates, RdsUpdate::Ps(ps));
+            }
+        }
+        updates
+    }
+}
+
+/// Add `update` to the updates of a group, counting an overflow.
+fn push(updates: &mut Vec<RdsUpdate, 3>, update: RdsUpdate) {
+    if updates.push(update).is_err() {
+        buffers::overflowed(Buffer::RdsUpdates);
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
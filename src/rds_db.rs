@@ -0,0 +1,176 @@
+//! Learned RDS stations.
+//!
+//! Maps PI codes to the PS name and frequency they were last heard with, so
+//! a station name can be shown right after retuning, long before the PS name
+//! is decoded again. The table is cached in RAM and persisted as an
+//! append-only log in [`storage::RDS_DB`](crate::storage::RDS_DB): each change
+//! appends one record, later records win, and the page is rewritten from the
+//! cache once it fills up.
+
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use heapless::Vec;
+
+use crate::error::Error;
+use crate::frequency::Frequency;
+use crate::rds::ProgramService;
+use crate::storage::RDS_DB;
+
+/// Stations kept; the least recently learned one is dropped when full.
+pub const MAX_STATIONS: usize = 64;
+
+const RECORD_SIZE: u32 = 16;
+/// Frequencies are stored in units of 10 kHz.
+const FREQUENCY_UNIT_KHZ: u32 = 10;
+
+/// A learned station.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Station {
+    pub pi: u16,
+    pub frequency: Frequency,
+    pub ps: ProgramService,
+}
+
+impl Station {
+    fn to_record(self) -> [u8; RECORD_SIZE as usize] {
+        let mut record = [0u8; RECORD_SIZE as usize];
+        let frequency =
+            ((self.frequency.khz() + FREQUENCY_UNIT_KHZ / 2) / FREQUENCY_UNIT_KHZ) as u16;
+        record[0..2].copy_from_slice(&self.pi.to_le_bytes());
+        record[2..4].copy_from_slice(&frequency.to_le_bytes());
+        record[4..12].copy_from_slice(self.ps.as_bytes());
+        record
+    }
+
+    fn from_record(record: &[u8; RECORD_SIZE as usize]) -> Option<Self> {
+        if record[0..4].iter().all(|&b| b == 0xFF) {
+            return None;
+        }
+        let mut ps = [0u8; 8];
+        ps.copy_from_slice(&record[4..12]);
+        Some(Self {
+            pi: u16::from_le_bytes([record[0], record[1]]),
+            frequency: Frequency::from_khz(
+                u16::from_le_bytes([record[2], record[3]]) as u32 * FREQUENCY_UNIT_KHZ,
+            ),
+            ps: ProgramService::new(ps),
+        })
+    }
+}
+
+/// Whether two frequencies denote the same FM channel.
+fn same_channel(a: Frequency, b: Frequency) -> bool {
+    a.khz().abs_diff(b.khz()) < 50
+}
+
+struct Table {
+    /// Oldest first.
+    stations: Vec<Station, MAX_STATIONS>,
+    /// Next free record in the partition.
+    offset: u32,
+}
+
+impl Table {
+    fn upsert(&mut self, station: Station) {
+        self.stations.retain(|s| s.pi != station.pi);
+        if self.stations.is_full() {
+            self.stations.remove(0);
+        }
+        let _ = self.stations.push(station);
+    }
+}
+
+static TABLE: Mutex<ThreadModeRawMutex, RefCell<Table>> = Mutex::new(RefCell::new(Table {
+    stations: Vec::new(),
+    offset: 0,
+}));
+
+/// Load the table from flash. Call once during startup.
+pub fn load() -> Result<(), Error> {
+    TABLE.lock(|table| {
+        let mut table = table.borrow_mut();
+        let mut record = [0u8; RECORD_SIZE as usize];
+        table.offset = 0;
+        while table.offset < RDS_DB.size {
+            RDS_DB.read(table.offset, &mut record)?;
+            let Some(station) = Station::from_record(&record) else {
+                break;
+            };
+            table.upsert(station);
+            table.offset += RECORD_SIZE;
+        }
+        Ok(())
+    })
+}
+
+/// Remember `station`, writing to flash only if something changed.
+pub fn learn(station: Station) -> Result<(), Error> {
+    TABLE.lock(|table| {
+        let mut table = table.borrow_mut();
+        let known = table.stations.iter().any(|s| {
+            s.pi == station.pi && s.ps == station.ps && same_channel(s.frequency, station.frequency)
+        });
+        if known {
+            return Ok(());
+        }
+        table.upsert(station);
+        if table.offset + RECORD_SIZE <= RDS_DB.size {
+            RDS_DB.write(table.offset, &station.to_record())?;
+            table.offset += RECORD_SIZE;
+            return Ok(());
+        }
+        // Log full: compact it to one record per station.
+        RDS_DB.erase_all()?;
+        table.offset = 0;
+        for index in 0..table.stations.len() {
+            let record = table.stations[index].to_record();
+            RDS_DB.write(table.offset, &record)?;
+            table.offset += RECORD_SIZE;
+        }
+        Ok(())
+    })
+}
+
+/// Station with the given PI code.
+pub fn find_pi(pi: u16) -> Option<Station> {
+    TABLE.lock(|table| {
+        table
+            .borrow()
+            .stations
+            .iter()
+            .rev()
+            .find(|s| s.pi == pi)
+            .copied()
+    })
+}
+
+/// Station last heard on `frequency`.
+pub fn find_frequency(frequency: Frequency) -> Option<Station> {
+    TABLE.lock(|table| {
+        table
+            .borrow()
+            .stations
+            .iter()
+            .rev()
+            .find(|s| same_channel(s.frequency, frequency))
+            .copied()
+    })
+}
+
+/// Call `f` for each station, oldest first.
+pub fn for_each(mut f: impl FnMut(&Station)) {
+    TABLE.lock(|table| table.borrow().stations.iter().for_each(&mut f));
+}
+
+/// Forget all stations.
+pub fn clear() -> Result<(), Error> {
+    TABLE.lock(|table| {
+        let mut table = table.borrow_mut();
+        RDS_DB.erase_all()?;
+        table.stations.clear();
+        table.offset = 0;
+        Ok(())
+    })
+}
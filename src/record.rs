@@ -0,0 +1,453 @@
+//! Versioned postcard records in a flash partition.
+//!
+//! Settings and statistics are each stored as a single serialized value
+//! behind a 16 byte header holding a magic number, a layout version, the
+//! payload length, a sequence number and the CRC-32 of the payload. A record
+//! with another magic or version, for example one written by firmware with a
+//! different layout, is ignored on load instead of being misread.
+//!
+//! The partition is used as a log of fixed-size slots, the size of the
+//! caller's scratch buffer. [`save`] programs the slot after the newest
+//! record and, once a page is full, erases and continues in the next page,
+//! so in a partition of two or more pages the previous copy is never erased
+//! before a new one has been written. [`load`] takes the newest record whose
+//! CRC matches: a record torn by a reset or otherwise corrupted is skipped
+//! and the previous copy is used instead.
+//!
+//! [`to_bytes`] and [`from_bytes`] use the same layout for a record kept
+//! outside flash, such as a settings file sent over the console.
+
+use log::warn;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::checksum;
+use crate::error::Error;
+use crate::storage::{PAGE_SIZE, Partition};
+
+const HEADER_SIZE: usize = 16;
+/// Magic of a slot that has not been written since the last erase.
+const EMPTY: u32 = 0xFFFF_FFFF;
+
+/// Flash holding a record log: a [`Partition`], or RAM in tests.
+trait Area {
+    fn name(&self) -> &'static str;
+    /// Size in bytes, a multiple of [`PAGE_SIZE`].
+    fn size(&self) -> u32;
+    fn read(&self, offset: u32, buf: &mut [u8]) -> Result<(), Error>;
+    fn write(&self, offset: u32, data: &[u8]) -> Result<(), Error>;
+    fn erase_page(&self, page: u32) -> Result<(), Error>;
+}
+
+impl Area for Partition {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn size(&self) -> u32 {
+        self.size
+    }
+
+    fn read(&self, offset: u32, buf: &mut [u8]) -> Result<(), Error> {
+        Ok(Partition::read(self, offset, buf)?)
+    }
+
+    fn write(&self, offset: u32, data: &[u8]) -> Result<(), Error> {
+        Ok(Partition::write(self, offset, data)?)
+    }
+
+    fn erase_page(&self, page: u32) -> Result<(), Error> {
+        Ok(Partition::erase_page(self, page)?)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Header {
+    magic: u32,
+    version: u16,
+    len: u16,
+    /// Incremented with every record written to the partition.
+    sequence: u32,
+    crc: u32,
+}
+
+impl Header {
+    fn parse(buf: &[u8]) -> Self {
+        let word = |at: usize| u32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]]);
+        Self {
+            magic: word(0),
+            version: u16::from_le_bytes([buf[4], buf[5]]),
+            len: u16::from_le_bytes([buf[6], buf[7]]),
+            sequence: word(8),
+            crc: word(12),
+        }
+    }
+
+    fn write(&self, buf: &mut [u8]) {
+        buf[0..4].copy_from_slice(&self.magic.to_le_bytes());
+        buf[4..6].copy_from_slice(&self.version.to_le_bytes());
+        buf[6..8].copy_from_slice(&self.len.to_le_bytes());
+        buf[8..12].copy_from_slice(&self.sequence.to_le_bytes());
+        buf[12..16].copy_from_slice(&self.crc.to_le_bytes());
+    }
+
+    /// Whether `buf`, starting with this header, holds an intact record.
+    fn is_valid(&self, buf: &[u8]) -> bool {
+        let end = HEADER_SIZE + self.len as usize;
+        end <= buf.len() && checksum::crc32(&buf[HEADER_SIZE..end]) == self.crc
+    }
+}
+
+/// Slot of `size` bytes holding the newest record with `magic` and
+/// `version`, and its sequence number.
+fn newest(
+    partition: &impl Area,
+    magic: u32,
+    version: u16,
+    size: u32,
+) -> Result<Option<(u32, u32)>, Error> {
+    let mut newest: Option<(u32, u32)> = None;
+    let mut header = [0u8; HEADER_SIZE];
+    for index in 0..partition.size() / size {
+        partition.read(index * size, &mut header)?;
+        let header = Header::parse(&header);
+        if header.magic == magic
+            && header.version == version
+            && newest.is_none_or(|(_, sequence)| header.sequence > sequence)
+        {
+            newest = Some((index, header.sequence));
+        }
+    }
+    Ok(newest)
+}
+
+/// Read the newest intact record in `partition` into `T`, using `buf` as
+/// scratch space. `buf` must have the size the record was saved with.
+///
+/// Returns `None` if no record with `magic` and `version` was written, and
+/// fails with [`Error::Checksum`] if records were written but none is intact.
+pub fn load<T: DeserializeOwned>(
+    partition: &Partition,
+    magic: u32,
+    version: u16,
+    buf: &mut [u8],
+) -> Result<Option<T>, Error> {
+    load_from(partition, magic, version, buf)
+}
+
+fn load_from<T: DeserializeOwned>(
+    partition: &impl Area,
+    magic: u32,
+    version: u16,
+    buf: &mut [u8],
+) -> Result<Option<T>, Error> {
+    let size = buf.len() as u32;
+    let written = newest(partition, magic, version, size)?;
+    let mut valid: Option<(u32, u32)> = None;
+    for index in 0..partition.size() / size {
+        partition.read(index * size, buf)?;
+        let header = Header::parse(buf);
+        if header.magic == magic
+            && header.version == version
+            && header.is_valid(buf)
+            && valid.is_none_or(|(_, sequence)| header.sequence > sequence)
+        {
+            valid = Some((index, header.sequence));
+        }
+    }
+    let Some((index, sequence)) = valid else {
+        if written.is_some() {
+            warn!("No intact record in the {} partition", partition.name());
+            return Err(Error::Checksum);
+        }
+        return Ok(None);
+    };
+    if written.is_some_and(|(_, newest)| newest != sequence) {
+        warn!(
+            "Newest {} record is corrupt, rolled back to an older copy",
+            partition.name()
+        );
+    }
+    partition.read(index * size, buf)?;
+    let len = Header::parse(buf).len as usize;
+    Ok(postcard::from_bytes(&buf[HEADER_SIZE..HEADER_SIZE + len]).ok())
+}
+
+/// Serialize `value` with its header into `buf`, which is filled with
+/// `0xFF` beyond it. Returns the length of the record.
+fn encode<T: Serialize>(
+    magic: u32,
+    version: u16,
+    sequence: u32,
+    value: &T,
+    buf: &mut [u8],
+) -> Result<usize, Error> {
+    buf.fill(0xFF);
+    let len = postcard::to_slice(value, &mut buf[HEADER_SIZE..])
+        .map_err(|_| Error::InvalidArgument)?
+        .len();
+    Header {
+        magic,
+        version,
+        len: len as u16,
+        sequence,
+        crc: checksum::crc32(&buf[HEADER_SIZE..HEADER_SIZE + len]),
+    }
+    .write(buf);
+    Ok(HEADER_SIZE + len)
+}
+
+/// Serialize `value` as a standalone record into `buf`, for export as a
+/// file. Returns its length.
+pub fn to_bytes<T: Serialize>(
+    magic: u32,
+    version: u16,
+    value: &T,
+    buf: &mut [u8],
+) -> Result<usize, Error> {
+    encode(magic, version, 0, value, buf)
+}
+
+/// Read a record produced by [`to_bytes`]. Bytes after the record, such as
+/// transfer padding, are ignored. Fails with [`Error::InvalidArgument`] for
+/// another magic or version and with [`Error::Checksum`] if it is damaged.
+pub fn from_bytes<T: DeserializeOwned>(magic: u32, version: u16, buf: &[u8]) -> Result<T, Error> {
+    if buf.len() < HEADER_SIZE {
+        return Err(Error::InvalidArgument);
+    }
+    let header = Header::parse(buf);
+    if header.magic != magic || header.version != version {
+        return Err(Error::InvalidArgument);
+    }
+    if !header.is_valid(buf) {
+        return Err(Error::Checksum);
+    }
+    postcard::from_bytes(&buf[HEADER_SIZE..HEADER_SIZE + header.len as usize])
+        .map_err(|_| Error::InvalidArgument)
+}
+
+/// Store `value` as the newest record in `partition`, using `buf` as scratch
+/// space. `buf` must be a multiple of 4 bytes that divides [`PAGE_SIZE`].
+/// Fails with [`Error::InvalidArgument`] if `value` does not fit.
+pub fn save<T: Serialize>(
+    partition: &Partition,
+    magic: u32,
+    version: u16,
+    value: &T,
+    buf: &mut [u8],
+) -> Result<(), Error> {
+    store(partition, magic, version, value, buf, true).map(|_| ())
+}
+
+/// Like [`save`], but only if the next slot is still erased. A page erase
+/// takes tens of milliseconds, more than a failing supply may leave, so this
+/// is used on a power failure warning. Returns whether the record was
+/// written.
+pub fn save_without_erase<T: Serialize>(
+    partition: &Partition,
+    magic: u32,
+    version: u16,
+    value: &T,
+    buf: &mut [u8],
+) -> Result<bool, Error> {
+    store(partition, magic, version, value, buf, false)
+}
+
+fn store<T: Serialize>(
+    partition: &impl Area,
+    magic: u32,
+    version: u16,
+    value: &T,
+    buf: &mut [u8],
+    erase: bool,
+) -> Result<bool, Error> {
+    let size = buf.len() as u32;
+    let slots = partition.size() / size;
+    let per_page = PAGE_SIZE / size;
+    let newest = newest(partition, magic, version, size)?;
+    let sequence = newest.map_or(0, |(_, sequence)| sequence.wrapping_add(1));
+    let len = encode(magic, version, sequence, value, buf)?;
+    let mut index = newest.map_or(0, |(index, _)| (index + 1) % slots);
+    let mut magic_at = [0u8; 4];
+    partition.read(index * size, &mut magic_at)?;
+    if u32::from_le_bytes(magic_at) != EMPTY {
+        if !erase {
+            return Ok(false);
+        }
+        // The page holds older records; it never holds the newest one unless
+        // the partition is a single page.
+        let page = index / per_page;
+        partition.erase_page(page)?;
+        index = page * per_page;
+    }
+    partition.write(index * size, &buf[..len.next_multiple_of(4)])?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::RefCell;
+
+    use super::*;
+
+    const MAGIC: u32 = 0x5453_4554;
+    const SLOT: usize = 256;
+    /// Slots in the two pages of [`Ram`].
+    const SLOTS: u32 = 2 * PAGE_SIZE / SLOT as u32;
+
+    /// Two erased pages standing in for a partition.
+    struct Ram(RefCell<[u8; 2 * PAGE_SIZE as usize]>);
+
+    impl Ram {
+        fn new() -> Self {
+            Self(RefCell::new([0xFF; 2 * PAGE_SIZE as usize]))
+        }
+
+        /// Flip a payload byte of the record in slot `index`.
+        fn corrupt(&self, index: u32) {
+            self.0.borrow_mut()[index as usize * SLOT + HEADER_SIZE] ^= 0xFF;
+        }
+
+        fn is_empty(&self, index: u32) -> bool {
+            let start = index as usize * SLOT;
+            self.0.borrow()[start..start + SLOT]
+                .iter()
+                .all(|&b| b == 0xFF)
+        }
+    }
+
+    impl Area for Ram {
+        fn name(&self) -> &'static str {
+            "ram"
+        }
+
+        fn size(&self) -> u32 {
+            2 * PAGE_SIZE
+        }
+
+        fn read(&self, offset: u32, buf: &mut [u8]) -> Result<(), Error> {
+            let offset = offset as usize;
+            buf.copy_from_slice(&self.0.borrow()[offset..offset + buf.len()]);
+            Ok(())
+        }
+
+        fn write(&self, offset: u32, data: &[u8]) -> Result<(), Error> {
+            let offset = offset as usize;
+            let mut ram = self.0.borrow_mut();
+            let target = &mut ram[offset..offset + data.len()];
+            assert!(
+                target.iter().all(|&b| b == 0xFF),
+                "programmed without erase"
+            );
+            target.copy_from_slice(data);
+            Ok(())
+        }
+
+        fn erase_page(&self, page: u32) -> Result<(), Error> {
+            let start = (page * PAGE_SIZE) as usize;
+            self.0.borrow_mut()[start..start + PAGE_SIZE as usize].fill(0xFF);
+            Ok(())
+        }
+    }
+
+    fn save(ram: &Ram, value: u32) {
+        store(ram, MAGIC, 1, &value, &mut [0u8; SLOT], true).unwrap();
+    }
+
+    fn load(ram: &Ram) -> Result<Option<u32>, Error> {
+        load_from(ram, MAGIC, 1, &mut [0u8; SLOT])
+    }
+
+    fn newest_slot(ram: &Ram) -> Option<(u32, u32)> {
+        newest(ram, MAGIC, 1, SLOT as u32).unwrap()
+    }
+
+    #[test]
+    fn empty_partition_holds_nothing() {
+        assert_eq!(load(&Ram::new()), Ok(None));
+    }
+
+    #[test]
+    fn records_fill_consecutive_slots() {
+        let ram = Ram::new();
+        for value in 10..13 {
+            save(&ram, value);
+        }
+        assert_eq!(newest_slot(&ram), Some((2, 2)));
+        assert_eq!(load(&ram), Ok(Some(12)));
+        assert!(ram.is_empty(3));
+    }
+
+    #[test]
+    fn full_partition_erases_the_older_page() {
+        let ram = Ram::new();
+        for value in 0..=SLOTS {
+            save(&ram, value);
+        }
+        // The record after the last slot went to the start of the erased
+        // first page; the second page still holds the previous copies.
+        assert_eq!(newest_slot(&ram), Some((0, SLOTS)));
+        assert!(ram.is_empty(1));
+        assert_eq!(load(&ram), Ok(Some(SLOTS)));
+        ram.corrupt(0);
+        assert_eq!(load(&ram), Ok(Some(SLOTS - 1)));
+    }
+
+    #[test]
+    fn corrupt_newest_record_rolls_back() {
+        let ram = Ram::new();
+        save(&ram, 1);
+        save(&ram, 2);
+        ram.corrupt(1);
+        assert_eq!(load(&ram), Ok(Some(1)));
+        // The next record still follows the corrupt one.
+        save(&ram, 3);
+        assert_eq!(newest_slot(&ram), Some((2, 2)));
+        assert_eq!(load(&ram), Ok(Some(3)));
+    }
+
+    #[test]
+    fn no_intact_record_is_an_error() {
+        let ram = Ram::new();
+        save(&ram, 1);
+        ram.corrupt(0);
+        assert_eq!(load(&ram), Err(Error::Checksum));
+    }
+
+    #[test]
+    fn other_version_is_ignored() {
+        let ram = Ram::new();
+        store(&ram, MAGIC, 2, &7u32, &mut [0u8; SLOT], true).unwrap();
+        assert_eq!(load(&ram), Ok(None));
+    }
+
+    #[test]
+    fn save_without_erase_needs_an_erased_slot() {
+        let ram = Ram::new();
+        assert_eq!(
+            store(&ram, MAGIC, 1, &1u32, &mut [0u8; SLOT], false),
+            Ok(true)
+        );
+        for value in 2..=SLOTS {
+            save(&ram, value);
+        }
+        assert_eq!(
+            store(&ram, MAGIC, 1, &0u32, &mut [0u8; SLOT], false),
+            Ok(false)
+        );
+        assert_eq!(load(&ram), Ok(Some(SLOTS)));
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let mut buf = [0u8; SLOT];
+        let len = to_bytes(MAGIC, 1, &0x1234_5678u32, &mut buf).unwrap();
+        assert_eq!(from_bytes::<u32>(MAGIC, 1, &buf), Ok(0x1234_5678));
+        assert_eq!(
+            from_bytes::<u32>(MAGIC, 2, &buf),
+            Err(Error::InvalidArgument)
+        );
+        buf[len - 1] ^= 0xFF;
+        assert_eq!(from_bytes::<u32>(MAGIC, 1, &buf), Err(Error::Checksum));
+    }
+}
@@ -0,0 +1,65 @@
+//! Controlled resets.
+//!
+//! [`restart`] brings the firmware down in order instead of leaving it to a
+//! power cycle: audio is muted, pending settings and the statistics are
+//! written, and captured console output is given time to reach flash before
+//! the system reset. UART output is written blocking, so everything printed
+//! before the reset has left the console by then.
+//!
+//! For [`Target::BootloaderUf2`] and [`Target::BootloaderSerial`] a magic
+//! value is left in `GPREGRET`, which survives the system reset; the UF2
+//! bootloader checks it at startup and stays in the chosen DFU mode instead
+//! of starting the application.
+
+use embassy_nrf::pac;
+use embassy_time::{Duration, Timer};
+use log::{info, warn};
+
+use crate::capture;
+use crate::events::{self, Origin, SystemEvent};
+use crate::settings;
+use crate::stats;
+
+/// `GPREGRET` value making the bootloader enter UF2 mass storage DFU.
+const DFU_MAGIC_UF2: u8 = 0x57;
+/// `GPREGRET` value making the bootloader enter serial DFU only.
+const DFU_MAGIC_SERIAL: u8 = 0x4E;
+/// Time for the radio loop to mute before the reset.
+const MUTE_DELAY: Duration = Duration::from_millis(50);
+
+/// What runs after the reset.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// Start this firmware again.
+    Application,
+    /// Stay in the bootloader, presenting a UF2 drive.
+    BootloaderUf2,
+    /// Stay in the bootloader, accepting serial DFU only.
+    BootloaderSerial,
+}
+
+/// Persist state, flush the console and reset into `target`.
+pub async fn restart(target: Target) -> ! {
+    info!("Resetting into {:?}", target);
+    events::event_try_send(Origin::System, SystemEvent::RadioMute);
+    match settings::flush() {
+        Ok(true) => info!("Settings written"),
+        Ok(false) => {}
+        Err(err) => warn!("Failed to save settings: {}", err),
+    }
+    if let Err(err) = stats::save() {
+        warn!("Failed to save statistics: {}", err);
+    }
+    Timer::after(MUTE_DELAY).await;
+    if capture::is_enabled() {
+        // The capture task writes buffered output at least this often.
+        Timer::after(capture::FLUSH_INTERVAL + MUTE_DELAY).await;
+    }
+    let magic = match target {
+        Target::Application => 0,
+        Target::BootloaderUf2 => DFU_MAGIC_UF2,
+        Target::BootloaderSerial => DFU_MAGIC_SERIAL,
+    };
+    pac::POWER.gpregret(0).write(|w| w.set_gpregret(magic));
+    cortex_m::peripheral::SCB::sys_reset()
+}
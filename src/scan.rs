@@ -0,0 +1,198 @@
+//! Band scan results.
+//!
+//! A scan is run by the radio loop on [`SystemEvent::RadioScan`]: it walks
+//! each requested [`Band`] with repeated seeks and stores every valid station
+//! with [`push`]. A scan that finds more than [`MAX_RESULTS`] stations stops
+//! with [`Error::Full`] and keeps the ones found so far. The table survives
+//! until the next scan so it can be listed or exported later. Bands the tuner
+//! cannot reach in the current build are skipped and reported with
+//! [`SystemNotify::ScanBandSkipped`].
+//!
+//! [`write_csv`] and [`CsvExport`] render the table as CSV for spreadsheets,
+//! on the console or as a file sent over XMODEM. FM stations whose name is in
+//! the [`rds_db`] carry their PI code and PS name.
+//!
+//! [`SystemEvent::RadioScan`]: crate::events::SystemEvent::RadioScan
+//! [`SystemNotify::ScanBandSkipped`]: crate::events::SystemNotify::ScanBandSkipped
+
+use core::cell::RefCell;
+use core::fmt::{self, Write};
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use serde::{Deserialize, Serialize};
+
+use crate::buffers::{self, Buffer};
+use crate::error::Error;
+use crate::frequency::Frequency;
+use crate::rds_db;
+use crate::transfer;
+
+/// Maximum number of stations kept from one scan.
+pub const MAX_RESULTS: usize = 48;
+
+/// Broadcast bands known to the scanner.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Band {
+    Fm,
+    Am,
+    Sw,
+}
+
+impl Band {
+    /// Bands in the order an all-bands scan visits them.
+    pub const ALL: [Band; 3] = [Band::Fm, Band::Am, Band::Sw];
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Band::Fm => "FM",
+            Band::Am => "AM",
+            Band::Sw => "SW",
+        }
+    }
+
+    /// Look up a band by its name, ignoring case.
+    pub fn from_name(name: &str) -> Option<Band> {
+        Band::ALL
+            .into_iter()
+            .find(|band| band.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// A station found by a scan.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ScanResult {
+    pub band: Band,
+    pub frequency: Frequency,
+    /// Received signal strength in dBµV.
+    pub rssi: u8,
+    /// Signal-to-noise ratio in dB.
+    pub snr: u8,
+}
+
+static RESULTS: Mutex<ThreadModeRawMutex, RefCell<heapless::Vec<ScanResult, MAX_RESULTS>>> =
+    Mutex::new(RefCell::new(heapless::Vec::new()));
+
+/// Drop the results of the previous scan.
+pub fn clear() {
+    RESULTS.lock(|results| results.borrow_mut().clear());
+}
+
+/// Store a found station. Fails when the table is full.
+pub fn push(result: ScanResult) -> Result<(), ScanResult> {
+    RESULTS
+        .lock(|results| results.borrow_mut().push(result))
+        .inspect_err(|_| buffers::overflowed(Buffer::ScanResults))
+}
+
+/// Number of stored stations in `band`.
+pub fn count(band: Band) -> usize {
+    RESULTS.lock(|results| results.borrow().iter().filter(|r| r.band == band).count())
+}
+
+/// Call `f` for every stored station, in scan order.
+pub fn for_each(mut f: impl FnMut(&ScanResult)) {
+    RESULTS.lock(|results| results.borrow().iter().for_each(&mut f));
+}
+
+/// Header line of the CSV export.
+const CSV_HEADER: &str = "band,frequency,rssi,snr,pi,ps\r\n";
+/// Room for the longest CSV line, a PS name of quotes included.
+const CSV_LINE_LEN: usize = 64;
+
+/// Write one CSV line for `result`.
+fn write_csv_row(writer: &mut impl Write, result: &ScanResult) -> fmt::Result {
+    match result.band {
+        Band::Fm => write!(writer, "FM,{:.2}", result.frequency)?,
+        band => write!(writer, "{},{}", band.name(), result.frequency.khz())?,
+    }
+    write!(writer, ",{},{},", result.rssi, result.snr)?;
+    let station = rds_db::find_frequency(result.frequency).filter(|_| result.band == Band::Fm);
+    if let Some(station) = station {
+        write!(writer, "{:04X},\"", station.pi)?;
+        for c in station.ps.as_str().chars() {
+            if c == '"' {
+                writer.write_str("\"\"")?;
+            } else {
+                writer.write_char(c)?;
+            }
+        }
+        writer.write_str("\"")?;
+    } else {
+        writer.write_str(",")?;
+    }
+    writer.write_str("\r\n")
+}
+
+/// Write the stored stations as CSV, header first.
+pub fn write_csv(writer: &mut impl Write) -> fmt::Result {
+    writer.write_str(CSV_HEADER)?;
+    let mut result = Ok(());
+    for_each(|station| {
+        if result.is_ok() {
+            result = write_csv_row(writer, station);
+        }
+    });
+    result
+}
+
+/// The stored stations as a CSV file for [`transfer::send`], rendered a line
+/// at a time.
+pub struct CsvExport {
+    /// Index of the next station to render.
+    next: usize,
+    header: bool,
+    line: heapless::String<CSV_LINE_LEN>,
+    position: usize,
+}
+
+impl CsvExport {
+    pub const fn new() -> Self {
+        Self {
+            next: 0,
+            header: false,
+            line: heapless::String::new(),
+            position: 0,
+        }
+    }
+
+    /// Render the next line, returning false after the last one.
+    fn render(&mut self) -> bool {
+        self.line.clear();
+        self.position = 0;
+        if !self.header {
+            self.header = true;
+            let _ = self.line.push_str(CSV_HEADER);
+            return true;
+        }
+        let Some(station) = RESULTS.lock(|results| results.borrow().get(self.next).copied()) else {
+            return false;
+        };
+        self.next += 1;
+        let _ = write_csv_row(&mut self.line, &station);
+        true
+    }
+}
+
+impl Default for CsvExport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl transfer::Source for CsvExport {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut len = 0;
+        while len < buf.len() {
+            if self.position == self.line.len() && !self.render() {
+                break;
+            }
+            let line = &self.line.as_bytes()[self.position..];
+            let n = line.len().min(buf.len() - len);
+            buf[len..len + n].copy_from_slice(&line[..n]);
+            self.position += n;
+            len += n;
+        }
+        Ok(len)
+    }
+}
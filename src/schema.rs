@@ -0,0 +1,159 @@
+//! Machine-readable description of the CLI.
+//!
+//! `schema` prints [`COMMANDS`] as a single JSON document so host GUIs can
+//! build control panels without hard-coding the command set. The derive
+//! output of the `#[derive(Command)]` enums in `commands.rs` cannot be
+//! inspected at runtime, so `build.rs` reads the enums and generates the
+//! table: command and argument names as the derive forms them, doc comments
+//! as help and argument types as [`ArgKind`]. Text arguments whose help
+//! mentions MHz are frequencies.
+
+use core::fmt::{self, Write};
+
+/// Type of a command argument as parsed by the CLI.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ArgKind {
+    U8,
+    U16,
+    U32,
+    /// Decimal MHz with up to three decimals.
+    Mhz,
+    Str,
+    /// Option without a value, such as `--size`.
+    Flag,
+}
+
+impl ArgKind {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            ArgKind::U8 => "u8",
+            ArgKind::U16 => "u16",
+            ArgKind::U32 => "u32",
+            ArgKind::Mhz => "mhz",
+            ArgKind::Str => "string",
+            ArgKind::Flag => "flag",
+        }
+    }
+}
+
+/// A positional command argument.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Arg {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub help: &'static str,
+    pub optional: bool,
+}
+
+impl Arg {
+    pub const fn required(name: &'static str, kind: ArgKind, help: &'static str) -> Self {
+        Self {
+            name,
+            kind,
+            help,
+            optional: false,
+        }
+    }
+
+    pub const fn optional(name: &'static str, kind: ArgKind, help: &'static str) -> Self {
+        Self {
+            name,
+            kind,
+            help,
+            optional: true,
+        }
+    }
+}
+
+/// A command with its arguments or subcommands.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CommandSchema {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub args: &'static [Arg],
+    pub subcommands: &'static [CommandSchema],
+}
+
+impl CommandSchema {
+    /// Command without arguments.
+    pub const fn leaf(name: &'static str, help: &'static str) -> Self {
+        Self::with_args(name, help, &[])
+    }
+
+    pub const fn with_args(name: &'static str, help: &'static str, args: &'static [Arg]) -> Self {
+        Self {
+            name,
+            help,
+            args,
+            subcommands: &[],
+        }
+    }
+
+    pub const fn group(
+        name: &'static str,
+        help: &'static str,
+        subcommands: &'static [CommandSchema],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            args: &[],
+            subcommands,
+        }
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/schema.rs"));
+
+/// Write `text` as a JSON string literal.
+fn write_string(w: &mut impl Write, text: &str) -> fmt::Result {
+    w.write_char('"')?;
+    for c in text.chars() {
+        match c {
+            '"' => w.write_str("\\\"")?,
+            '\\' => w.write_str("\\\\")?,
+            c if c.is_control() => write!(w, "\\u{:04x}", c as u32)?,
+            c => w.write_char(c)?,
+        }
+    }
+    w.write_char('"')
+}
+
+fn write_command(w: &mut impl Write, command: &CommandSchema) -> fmt::Result {
+    w.write_str("{\"name\":")?;
+    write_string(w, command.name)?;
+    w.write_str(",\"help\":")?;
+    write_string(w, command.help)?;
+    w.write_str(",\"args\":[")?;
+    for (index, arg) in command.args.iter().enumerate() {
+        if index > 0 {
+            w.write_char(',')?;
+        }
+        w.write_str("{\"name\":")?;
+        write_string(w, arg.name)?;
+        write!(w, ",\"type\":\"{}\",\"help\":", arg.kind.name())?;
+        write_string(w, arg.help)?;
+        write!(w, ",\"optional\":{}}}", arg.optional)?;
+    }
+    w.write_str("],\"subcommands\":")?;
+    write_commands(w, command.subcommands)?;
+    w.write_char('}')
+}
+
+fn write_commands(w: &mut impl Write, commands: &[CommandSchema]) -> fmt::Result {
+    w.write_char('[')?;
+    for (index, command) in commands.iter().enumerate() {
+        if index > 0 {
+            w.write_char(',')?;
+        }
+        write_command(w, command)?;
+    }
+    w.write_char(']')
+}
+
+/// Write the whole command set as `{"version":1,"commands":[...]}`.
+pub fn write_json(w: &mut impl Write) -> fmt::Result {
+    w.write_str("{\"version\":1,\"commands\":")?;
+    write_commands(w, COMMANDS)?;
+    w.write_char('}')
+}
@@ -0,0 +1,58 @@
+//! Seek sensitivity.
+//!
+//! A seek stops on the first channel whose RSSI and SNR reach the seek
+//! thresholds of the receiver. The [`Sensitivity`] kept in
+//! [`SeekSettings`](crate::settings::SeekSettings) is turned into thresholds
+//! by [`properties`], written at boot and whenever it changes. Only the FM
+//! receiver is brought up, so only FM seeks can be tuned.
+
+use serde::{Deserialize, Serialize};
+
+use crate::properties::{self, Property};
+
+/// How weak a station a seek stops on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Sensitivity {
+    /// Strong stations only.
+    Low,
+    /// Power-up defaults.
+    Normal,
+    /// Weak stations too, at the cost of stopping on some noise.
+    High,
+}
+
+impl Sensitivity {
+    pub const ALL: [Sensitivity; 3] = [Sensitivity::Low, Sensitivity::Normal, Sensitivity::High];
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Sensitivity::Low => "low",
+            Sensitivity::Normal => "normal",
+            Sensitivity::High => "high",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|sensitivity| sensitivity.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Seek RSSI threshold in dBµV and SNR threshold in dB.
+pub const fn thresholds(sensitivity: Sensitivity) -> (u16, u16) {
+    match sensitivity {
+        Sensitivity::Low => (28, 8),
+        Sensitivity::Normal => (20, 3),
+        Sensitivity::High => (12, 1),
+    }
+}
+
+/// Property values implementing `sensitivity`.
+pub const fn properties(sensitivity: Sensitivity) -> [(Property, u16); 2] {
+    let (rssi, snr) = thresholds(sensitivity);
+    [
+        (properties::FM_SEEK_TUNE_RSSI_THRESHOLD, rssi),
+        (properties::FM_SEEK_TUNE_SNR_THRESHOLD, snr),
+    ]
+}
@@ -1,17 +1,150 @@
 //! Lightweight `log` backend that writes colorized messages over the serial console.
 //!
 //! The logger:
-//! - emits millisecond timestamps from `embassy_time::Instant`,
+//! - emits timestamps in the [`TimestampFormat`] selected with `log format`:
+//!   milliseconds since boot (default), `hh:mm:ss.mmm` since boot or the
+//!   wall-clock time received over RDS,
+//! - optionally tags each line with the module that logged it, which for this
+//!   firmware names the task (`main` is the radio loop),
 //! - colorizes levels with the escape sequences from `console::console_colors`,
 //! - caps verbosity at `Level::Info` (debug/trace are ignored),
-//! - writes through the shared UART writer provided by `console::stdout_get()`.
+//! - drops records whose target is rejected by the
+//!   [`LogFilter`](crate::log_filter::LogFilter) in the settings,
+//! - writes through the shared UART writer provided by `console::stdout_get()`
+//!   in thread mode. Records logged from interrupt handlers are formatted
+//!   into a bounded queue instead, so an ISR never waits for the UART; the
+//!   CLI task prints them with [`next_deferred`]. When the queue is full the
+//!   record is dropped and counted, see [`deferred_dropped`].
 
+use crate::buffers::{Buffer, Truncate};
+use crate::clock::{self, TimeOfDay};
 use crate::console;
 use crate::console::console_colors::{RED, RESET, WHITE, YELLOW};
+use crate::settings;
+use core::fmt;
 use core::fmt::Write as _;
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicU32, Ordering};
+use cortex_m::peripheral::SCB;
+use cortex_m::peripheral::scb::VectActive;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
 use embassy_time::Instant;
 use log::{Level, LevelFilter, Metadata, Record, SetLoggerError};
 
+/// How log lines are timestamped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// Milliseconds since boot.
+    Uptime,
+    /// `hh:mm:ss.mmm` since boot.
+    Clock,
+    /// Local `hh:mm:ss.mmm` from RDS clock time; uptime prefixed with `+`
+    /// until a station sent the time.
+    Rds,
+}
+
+impl TimestampFormat {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            TimestampFormat::Uptime => "ms",
+            TimestampFormat::Clock => "clock",
+            TimestampFormat::Rds => "rds",
+        }
+    }
+
+    const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => TimestampFormat::Clock,
+            2 => TimestampFormat::Rds,
+            _ => TimestampFormat::Uptime,
+        }
+    }
+}
+
+static FORMAT: AtomicU8 = AtomicU8::new(TimestampFormat::Uptime as u8);
+static SHOW_TASK: AtomicBool = AtomicBool::new(false);
+
+/// Select the timestamp format.
+pub fn set_format(format: TimestampFormat) {
+    FORMAT.store(format as u8, Ordering::Relaxed);
+}
+
+/// Current timestamp format.
+pub fn format() -> TimestampFormat {
+    TimestampFormat::from_u8(FORMAT.load(Ordering::Relaxed))
+}
+
+/// Include the task name in every line.
+pub fn set_show_task(show: bool) {
+    SHOW_TASK.store(show, Ordering::Relaxed);
+}
+
+/// Whether lines include the task name.
+pub fn show_task() -> bool {
+    SHOW_TASK.load(Ordering::Relaxed)
+}
+
+/// Records logged from interrupt context waiting to be printed.
+pub const DEFERRED_DEPTH: usize = 8;
+/// Longest deferred line; longer ones are truncated.
+pub const DEFERRED_LINE: usize = 160;
+
+/// A log line formatted in interrupt context.
+pub type DeferredLine = heapless::String<DEFERRED_LINE>;
+
+static DEFERRED: Channel<CriticalSectionRawMutex, DeferredLine, DEFERRED_DEPTH> = Channel::new();
+static DEFERRED_DROPPED: AtomicU32 = AtomicU32::new(0);
+
+/// Wait for the next record logged from interrupt context.
+pub async fn next_deferred() -> DeferredLine {
+    DEFERRED.receive().await
+}
+
+/// Number of queued records from interrupt context.
+pub fn deferred_len() -> usize {
+    DEFERRED.len()
+}
+
+/// Records from interrupt context dropped because the queue was full, reset
+/// by reading.
+pub fn deferred_dropped() -> u32 {
+    DEFERRED_DROPPED.swap(0, Ordering::Relaxed)
+}
+
+/// Timestamp of a log line in the current format.
+struct Timestamp(u64);
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match format() {
+            TimestampFormat::Uptime => write!(f, "{:012}", self.0),
+            TimestampFormat::Clock => write!(f, "{}", TimeOfDay(self.0)),
+            TimestampFormat::Rds => match clock::now() {
+                Some(time) => write!(f, "{}", time),
+                None => write!(f, "+{}", TimeOfDay(self.0)),
+            },
+        }
+    }
+}
+
+/// Task tag of a log line: the firmware module that logged, or the crate
+/// for dependencies.
+struct TaskTag<'a>(Option<&'a str>);
+
+impl fmt::Display for TaskTag<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(path) = self.0.filter(|_| show_task()) else {
+            return Ok(());
+        };
+        let name = match path.strip_prefix(env!("CARGO_CRATE_NAME")) {
+            Some(rest) => rest.strip_prefix("::").unwrap_or("main"),
+            None => path.split("::").next().unwrap_or(path),
+        };
+        let name = name.split("::").next().unwrap_or(name);
+        write!(f, "[{}] ", name)
+    }
+}
+
 struct SerialLogger;
 
 impl SerialLogger {
@@ -28,25 +161,43 @@ impl SerialLogger {
             Level::Trace => WHITE,
         }
     }
+
+    /// Format `record` as one line without the line break.
+    fn write_line(w: &mut impl fmt::Write, record: &Record) -> fmt::Result {
+        let timestamp = Timestamp(Instant::now().as_millis());
+        let level_color = SerialLogger::get_level_color(record.level());
+        write!(
+            w,
+            "{level_color}[{}] {}<{}> {}: {}{RESET}",
+            timestamp,
+            TaskTag(record.module_path()),
+            record.level(),
+            record.file().unwrap_or("unknown"),
+            record.args()
+        )
+    }
 }
 
 impl log::Log for SerialLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= Level::Info
+        metadata.level() <= Level::Info && settings::get().log.filter.allows(metadata.target())
     }
 
     fn log(&self, record: &Record) {
-        if self.enabled(record.metadata()) {
-            let seconds = Instant::now().as_millis();
-            let level_color = SerialLogger::get_level_color(record.level());
-            let _ = write!(
-                console::stdout_get(),
-                "{level_color}[{:012}] <{}> {}: {}{RESET}\r\n",
-                seconds,
-                record.level(),
-                record.file().unwrap_or("unknown"),
-                record.args()
-            );
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if SCB::vect_active() == VectActive::ThreadMode {
+            let mut out = console::stdout_get();
+            let _ = SerialLogger::write_line(&mut out, record);
+            let _ = out.write_str("\r\n");
+            return;
+        }
+        let mut line = DeferredLine::new();
+        // A full line is truncated, which is better than losing the record.
+        let _ = SerialLogger::write_line(&mut Truncate::new(&mut line, Buffer::LogLine), record);
+        if DEFERRED.try_send(line).is_err() {
+            DEFERRED_DROPPED.fetch_add(1, Ordering::Relaxed);
         }
     }
     fn flush(&self) {}
@@ -0,0 +1,134 @@
+//! Radio service.
+//!
+//! The radio loop in `main` is the only owner of the band handle, so chip
+//! access never interleaves no matter how many tasks control the radio.
+//! Events sent with [`events::event_send`](crate::events::event_send) are
+//! fire-and-forget, though: the sender cannot tell whether its event worked,
+//! and events from different controllers mix freely. Controllers that need
+//! either go through this service instead.
+//!
+//! [`request`] hands one event to the loop and waits for its [`Outcome`].
+//! Retuning requests report the tune status they ended on, which the typed
+//! [`Session`] methods such as [`Session::tune`] return directly. A
+//! [`Session`] keeps other service callers out for a sequence of requests,
+//! for example tuning a station and then setting its volume, so a second
+//! controller cannot slip its own requests in between. Buttons, the encoder
+//! and the CLI keep sending plain events, so they stay responsive while a
+//! session is open.
+//!
+//! Requests are numbered, so a caller that drops a request while waiting
+//! leaves no result behind for the next one; the event itself still runs to
+//! completion, as the radio loop never drops an operation halfway through a
+//! chip transaction. Long operations (scans, property dumps) instead stop at
+//! the next transaction boundary after [`abort`], leaving the receiver tuned
+//! and unmuted as before the operation.
+
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_sync::mutex::{Mutex, MutexGuard};
+use embassy_sync::signal::Signal;
+
+use crate::error::Error;
+use crate::events::SystemEvent;
+use crate::frequency::{Frequency, TuneStatus};
+
+/// Held by the caller owning the service; counts the requests made.
+static OWNER: Mutex<ThreadModeRawMutex, u8> = Mutex::new(0);
+/// Numbered requests waiting for the radio loop.
+static REQUESTS: Channel<ThreadModeRawMutex, (u8, SystemEvent), 1> = Channel::new();
+/// Number and result of the request handled last.
+static DONE: Signal<ThreadModeRawMutex, (u8, Result<Outcome, Error>)> = Signal::new();
+/// Set by [`abort`], checked by the radio loop between transactions.
+static ABORT: Signal<ThreadModeRawMutex, ()> = Signal::new();
+
+/// What the radio loop reports for a successful request.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Outcome {
+    /// The request was carried out.
+    Done,
+    /// The request retuned the receiver, which ended up as given.
+    Tuned(TuneStatus),
+}
+
+/// Exclusive use of the radio service until dropped.
+pub struct Session {
+    requests: MutexGuard<'static, ThreadModeRawMutex, u8>,
+}
+
+impl Session {
+    /// Have the radio loop handle `event` and wait for the result.
+    pub async fn request(&mut self, event: SystemEvent) -> Result<Outcome, Error> {
+        *self.requests = self.requests.wrapping_add(1);
+        let number = *self.requests;
+        REQUESTS.send((number, event)).await;
+        loop {
+            // Skip results of requests dropped by earlier callers.
+            let (done, result) = DONE.wait().await;
+            if done == number {
+                return result;
+            }
+        }
+    }
+
+    /// Tune to `frequency` and return the tune status reached.
+    pub async fn tune(&mut self, frequency: Frequency) -> Result<TuneStatus, Error> {
+        match self
+            .request(SystemEvent::RadioSetFrequency(frequency))
+            .await?
+        {
+            Outcome::Tuned(status) => Ok(status),
+            // Retuning requests always complete with a status.
+            Outcome::Done => Err(Error::InvalidArgument),
+        }
+    }
+
+    /// Set the volume in percent.
+    pub async fn set_volume(&mut self, percent: u8) -> Result<(), Error> {
+        self.request(SystemEvent::RadioVolumeSet(percent))
+            .await
+            .map(|_| ())
+    }
+
+    /// Unmute audio output.
+    pub async fn unmute(&mut self) -> Result<(), Error> {
+        self.request(SystemEvent::RadioUnmute).await.map(|_| ())
+    }
+}
+
+/// Wait until no other caller uses the service and take it over.
+pub async fn session() -> Session {
+    Session {
+        requests: OWNER.lock().await,
+    }
+}
+
+/// Have the radio loop handle `event` and wait for the result.
+pub async fn request(event: SystemEvent) -> Result<Outcome, Error> {
+    session().await.request(event).await
+}
+
+/// Stop the running scan or property dump at the next transaction boundary.
+pub fn abort() {
+    ABORT.signal(());
+}
+
+/// Receive the next request and its number. For the radio loop only.
+pub async fn receive() -> (u8, SystemEvent) {
+    REQUESTS.receive().await
+}
+
+/// Report the result of request `number`. For the radio loop only.
+pub fn complete(number: u8, result: Result<Outcome, Error>) {
+    DONE.signal((number, result));
+}
+
+/// Forget an abort that arrived while no operation was running. For the
+/// radio loop only, before it starts an operation.
+pub fn clear_abort() {
+    ABORT.reset();
+}
+
+/// Whether the running operation should stop. For the radio loop only.
+pub fn abort_requested() -> bool {
+    ABORT.try_take().is_some()
+}
@@ -0,0 +1,885 @@
+//! Runtime settings shared by all subsystems.
+//!
+//! Settings live in a single [`Settings`] value guarded by a blocking mutex.
+//! Readers take a copy with [`get`]; writers (usually CLI commands) modify it
+//! in place with [`update`]. Subsystems read the values they need each time
+//! they act, so changes take effect without restarting any task. The mutex is
+//! a critical section one because the logger reads its filter from any
+//! context.
+//!
+//! Settings are persisted as one postcard [`record`] in
+//! [`storage::SETTINGS`](crate::storage::SETTINGS), loaded with [`load`]
+//! during startup. [`commit_task`] writes them back once they stopped
+//! changing for [`COMMIT_DELAY`], so a burst of CLI commands costs a single
+//! record write. Records are CRC-protected and alternate between two pages,
+//! so a corrupted newest copy rolls back to the previous one at boot.
+//!
+//! postcard data does not describe itself, so a record is not one serialized
+//! [`Settings`] but a list of sections, one per field, each tagged with a
+//! [`Section`] id and layout version and carrying its own serialized bytes.
+//! A section whose layout changed is dropped on load and keeps its defaults
+//! while the other sections are restored; sections added by newer firmware
+//! are skipped and ones missing from older records keep their defaults.
+//!
+//! `settings export` sends the current settings as a record file over
+//! XMODEM ([`Export`]) and `settings import` replaces them with such a file
+//! ([`Import`]), with the same tolerance for sections of another layout.
+//! The tuning lock and kiosk mode are never imported, and the export leaves
+//! out the PIN and the kiosk unlock code; an import is refused while the
+//! tuning lock needs its PIN.
+
+use core::cell::Cell;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, with_timeout};
+use log::warn;
+use serde::de::{DeserializeOwned, SeqAccess, Visitor};
+use serde::ser::{self, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::alias::Aliases;
+use crate::clock::TimeZone;
+use crate::error::Error;
+use crate::frequency::Frequency;
+use crate::i18n::Language;
+use crate::leveling::Leveling;
+use crate::log_filter::LogFilter;
+use crate::monitor;
+use crate::properties::Profile;
+use crate::record;
+use crate::scan::Band;
+use crate::seek::Sensitivity;
+use crate::storage::SETTINGS as PARTITION;
+use crate::transfer::{Sink, Source};
+use crate::tune_lock::TuneLock;
+use crate::volume::VolumeCurve;
+
+/// Quiet time after the last change before settings are written to flash.
+pub const COMMIT_DELAY: Duration = Duration::from_secs(2);
+
+/// Marks a valid record ("S47C").
+const MAGIC: u32 = 0x4337_3453;
+/// Version of the section list. Layout changes of a single section bump the
+/// version of that [`Section`] instead.
+const VERSION: u16 = 28;
+/// Largest serialized record, header included.
+const RECORD_SIZE: usize = 1024;
+/// Largest serialized section.
+const SECTION_SIZE: usize = 512;
+
+/// Stored part of [`Settings`]. An id is never reused; bump the version
+/// whenever the layout of the section's type changes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Section {
+    id: u8,
+    version: u8,
+    name: &'static str,
+}
+
+impl Section {
+    const fn new(id: u8, version: u8, name: &'static str) -> Self {
+        Self { id, version, name }
+    }
+
+    /// Append `value` as this section to `seq`.
+    fn store<S: SerializeSeq, T: Serialize>(&self, seq: &mut S, value: &T) -> Result<(), S::Error> {
+        let mut buf = [0u8; SECTION_SIZE];
+        let body = postcard::to_slice(value, &mut buf)
+            .map_err(|_| ser::Error::custom("settings section too large"))?;
+        seq.serialize_element(&(self.id, self.version, Bytes(body)))
+    }
+
+    /// Decode `body` into `value` if it was stored as this section. Returns
+    /// whether `id` is this section's; `value` keeps its defaults if the
+    /// section was stored with another layout.
+    fn restore<T: DeserializeOwned>(
+        &self,
+        id: u8,
+        version: u8,
+        body: &[u8],
+        value: &mut T,
+    ) -> bool {
+        if id != self.id {
+            return false;
+        }
+        match postcard::from_bytes(body) {
+            Ok(stored) if version == self.version => *value = stored,
+            _ => warn!(
+                "Stored {} settings have another layout, using defaults",
+                self.name
+            ),
+        }
+        true
+    }
+}
+
+const INPUT: Section = Section::new(0, 1, "input");
+const AUDIO: Section = Section::new(1, 1, "audio");
+const RECEPTION: Section = Section::new(2, 1, "reception");
+const STEREO: Section = Section::new(3, 1, "stereo");
+const SEEK: Section = Section::new(4, 1, "seek");
+const CALIBRATION: Section = Section::new(5, 1, "calibration");
+const LOG: Section = Section::new(6, 1, "log");
+const NOTIFY: Section = Section::new(7, 1, "notify");
+const BEEP: Section = Section::new(8, 1, "beep");
+const UI: Section = Section::new(9, 1, "ui");
+const CLOCK: Section = Section::new(10, 1, "clock");
+const ALARM: Section = Section::new(11, 1, "alarm");
+const ALERT: Section = Section::new(12, 1, "alert");
+const LED: Section = Section::new(13, 1, "led");
+const KIOSK: Section = Section::new(14, 1, "kiosk");
+const TUNE_LOCK: Section = Section::new(15, 1, "tune lock");
+/// Number of sections written.
+const SECTIONS: usize = 16;
+
+/// Serialized section, stored as a length-prefixed byte string.
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/// Button and encoder behaviour.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputSettings {
+    /// Hold time before a pressed button starts auto-repeating, in ms.
+    pub repeat_delay_ms: u16,
+    /// Interval between auto-repeated presses, in ms.
+    pub repeat_interval_ms: u16,
+    /// Encoder detents closer together than this count as fast spinning, in ms.
+    pub accel_threshold_ms: u16,
+    /// Step multiplier applied while the encoder is spun fast.
+    pub accel_multiplier: u8,
+    /// Drop below the baseline count that makes a touch pad pressed, in percent.
+    pub touch_threshold_pct: u8,
+}
+
+impl InputSettings {
+    pub const fn new() -> Self {
+        Self {
+            repeat_delay_ms: 500,
+            repeat_interval_ms: 150,
+            accel_threshold_ms: 40,
+            accel_multiplier: 5,
+            touch_threshold_pct: 8,
+        }
+    }
+}
+
+impl Default for InputSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Audio behaviour of the radio loop.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioSettings {
+    /// Volume steps arriving within this window of the first one are merged
+    /// into one write, in ms.
+    pub volume_coalesce_ms: u16,
+    /// Hard-mute audio while tuning or seeking.
+    pub tune_mute: bool,
+    /// Keep audio muted while step tuning until no step arrived for this
+    /// long, in ms; 0 disables.
+    pub step_mute_ms: u16,
+    /// Mapping from volume percent to chip steps.
+    pub volume_curve: VolumeCurve,
+    /// Volume limit in percent, enforced for every volume change.
+    pub max_volume_pct: u8,
+    /// Left/right balance from -10 (left only) to 10 (right only).
+    pub balance: i8,
+    /// Per-station volume offsets.
+    pub leveling: Leveling,
+}
+
+impl AudioSettings {
+    pub const fn new() -> Self {
+        Self {
+            volume_coalesce_ms: 150,
+            tune_mute: false,
+            step_mute_ms: 0,
+            volume_curve: VolumeCurve::Linear,
+            max_volume_pct: 100,
+            balance: 0,
+            leveling: Leveling::new(),
+        }
+    }
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tuner reception behaviour.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReceptionSettings {
+    /// Last selected reception profile.
+    pub profile: Profile,
+}
+
+impl ReceptionSettings {
+    pub const fn new() -> Self {
+        Self {
+            profile: Profile::Normal,
+        }
+    }
+}
+
+impl Default for ReceptionSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// SNR controlled mono override, see [`stereo`](crate::stereo).
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StereoSettings {
+    pub enabled: bool,
+    /// Mono is forced once the SNR drops below this, in dB.
+    pub mono_below_snr: u8,
+    /// Stereo is allowed again once the SNR reaches this, in dB.
+    pub stereo_above_snr: u8,
+}
+
+impl StereoSettings {
+    pub const fn new() -> Self {
+        Self {
+            enabled: false,
+            mono_below_snr: 12,
+            stereo_above_snr: 18,
+        }
+    }
+}
+
+impl Default for StereoSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Seek behaviour of the FM receiver.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeekSettings {
+    pub sensitivity: Sensitivity,
+}
+
+impl SeekSettings {
+    pub const fn new() -> Self {
+        Self {
+            sensitivity: Sensitivity::Normal,
+        }
+    }
+}
+
+impl Default for SeekSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Frequency calibration per band, see [`calibration`](crate::calibration).
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationSettings {
+    /// Offset per band in kHz, in [`Band::ALL`] order.
+    offset_khz: [i16; 3],
+}
+
+impl CalibrationSettings {
+    pub const fn new() -> Self {
+        Self { offset_khz: [0; 3] }
+    }
+
+    pub fn offset_khz(&self, band: Band) -> i16 {
+        self.offset_khz[band as usize]
+    }
+
+    pub fn set_offset_khz(&mut self, band: Band, offset_khz: i16) {
+        self.offset_khz[band as usize] = offset_khz;
+    }
+}
+
+impl Default for CalibrationSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Logger behaviour.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogSettings {
+    /// Allow/deny rules for log targets.
+    pub filter: LogFilter,
+    /// Record radio events to the trace partition.
+    pub trace: bool,
+}
+
+impl LogSettings {
+    pub const fn new() -> Self {
+        Self {
+            filter: LogFilter::new(),
+            trace: false,
+        }
+    }
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Asynchronous notifications printed on the CLI terminal. Other consumers
+/// still receive every notification.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotifySettings {
+    /// Periodic signal quality readings.
+    pub rsq: bool,
+    /// RDS PI, PS and learned station names.
+    pub rds: bool,
+    /// Notifications caused by the terminal's own commands, except errors.
+    pub echo: bool,
+}
+
+impl NotifySettings {
+    pub const fn new() -> Self {
+        Self {
+            rsq: true,
+            rds: true,
+            echo: true,
+        }
+    }
+}
+
+impl Default for NotifySettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Buzzer feedback.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BeepSettings {
+    pub enabled: bool,
+    /// Loudness from 0 to [`buzzer::MAX_VOLUME`](crate::buzzer::MAX_VOLUME).
+    pub volume: u8,
+}
+
+impl BeepSettings {
+    pub const fn new() -> Self {
+        Self {
+            enabled: true,
+            volume: 5,
+        }
+    }
+}
+
+impl Default for BeepSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// User interface presentation and command aliases.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UiSettings {
+    /// Language of messages.
+    pub language: Language,
+    /// User-defined command aliases.
+    pub aliases: Aliases,
+}
+
+impl UiSettings {
+    pub const fn new() -> Self {
+        Self {
+            language: Language::En,
+            aliases: Aliases::new(),
+        }
+    }
+}
+
+impl Default for UiSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wall clock settings.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockSettings {
+    /// How RDS clock time is converted to local time.
+    pub zone: TimeZone,
+}
+
+impl ClockSettings {
+    pub const fn new() -> Self {
+        Self {
+            zone: TimeZone::Broadcast,
+        }
+    }
+}
+
+impl Default for ClockSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Emergency alert override.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertSettings {
+    /// React to RDS alarm announcements.
+    pub enabled: bool,
+    /// Volume set when an alert is raised, in percent.
+    pub volume: u8,
+}
+
+impl AlertSettings {
+    pub const fn new() -> Self {
+        Self {
+            enabled: false,
+            volume: 70,
+        }
+    }
+}
+
+impl Default for AlertSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Kiosk mode, see [`kiosk`](crate::kiosk).
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KioskSettings {
+    /// Lock the controls at boot.
+    pub enabled: bool,
+    /// Code unlocking the controls.
+    pub code: u32,
+    /// Station tuned at boot; `None` keeps the startup frequency.
+    pub station: Option<Frequency>,
+}
+
+impl KioskSettings {
+    pub const fn new() -> Self {
+        Self {
+            enabled: false,
+            code: 0,
+            station: None,
+        }
+    }
+}
+
+impl Default for KioskSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wake-up alarm.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlarmSettings {
+    pub enabled: bool,
+    /// Local time the alarm rings.
+    pub hour: u8,
+    pub minute: u8,
+    /// FM station tuned when the alarm rings; `None` keeps the current
+    /// station.
+    pub frequency: Option<Frequency>,
+    /// Final volume in percent.
+    pub volume: u8,
+    /// Time to ramp the volume up from silence, in seconds.
+    pub ramp_s: u16,
+    /// Length of a snooze, in minutes.
+    pub snooze_min: u8,
+}
+
+impl AlarmSettings {
+    pub const fn new() -> Self {
+        Self {
+            enabled: false,
+            hour: 7,
+            minute: 0,
+            frequency: None,
+            volume: 50,
+            ramp_s: 60,
+            snooze_min: 9,
+        }
+    }
+}
+
+impl Default for AlarmSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Status LED and LED strip.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedSettings {
+    /// The status LED lights when its pin is low.
+    pub active_low: bool,
+    /// LEDs on the WS2812 strip, 0 if none is fitted.
+    pub strip_len: u8,
+    /// Strip brightness in percent.
+    pub brightness: u8,
+}
+
+impl LedSettings {
+    pub const fn new() -> Self {
+        Self {
+            active_low: false,
+            strip_len: 0,
+            brightness: 20,
+        }
+    }
+}
+
+impl Default for LedSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// All runtime settings.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Settings {
+    pub input: InputSettings,
+    pub audio: AudioSettings,
+    pub reception: ReceptionSettings,
+    pub stereo: StereoSettings,
+    pub seek: SeekSettings,
+    pub calibration: CalibrationSettings,
+    pub log: LogSettings,
+    pub notify: NotifySettings,
+    pub beep: BeepSettings,
+    pub ui: UiSettings,
+    pub clock: ClockSettings,
+    pub alarm: AlarmSettings,
+    pub alert: AlertSettings,
+    pub led: LedSettings,
+    pub kiosk: KioskSettings,
+    pub tune_lock: TuneLock,
+}
+
+impl Settings {
+    pub const fn new() -> Self {
+        Self {
+            input: InputSettings::new(),
+            audio: AudioSettings::new(),
+            reception: ReceptionSettings::new(),
+            stereo: StereoSettings::new(),
+            seek: SeekSettings::new(),
+            calibration: CalibrationSettings::new(),
+            log: LogSettings::new(),
+            notify: NotifySettings::new(),
+            beep: BeepSettings::new(),
+            ui: UiSettings::new(),
+            clock: ClockSettings::new(),
+            alarm: AlarmSettings::new(),
+            alert: AlertSettings::new(),
+            led: LedSettings::new(),
+            kiosk: KioskSettings::new(),
+            tune_lock: TuneLock::new(),
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Serialize for Settings {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(SECTIONS))?;
+        INPUT.store(&mut seq, &self.input)?;
+        AUDIO.store(&mut seq, &self.audio)?;
+        RECEPTION.store(&mut seq, &self.reception)?;
+        STEREO.store(&mut seq, &self.stereo)?;
+        SEEK.store(&mut seq, &self.seek)?;
+        CALIBRATION.store(&mut seq, &self.calibration)?;
+        LOG.store(&mut seq, &self.log)?;
+        NOTIFY.store(&mut seq, &self.notify)?;
+        BEEP.store(&mut seq, &self.beep)?;
+        UI.store(&mut seq, &self.ui)?;
+        CLOCK.store(&mut seq, &self.clock)?;
+        ALARM.store(&mut seq, &self.alarm)?;
+        ALERT.store(&mut seq, &self.alert)?;
+        LED.store(&mut seq, &self.led)?;
+        KIOSK.store(&mut seq, &self.kiosk)?;
+        TUNE_LOCK.store(&mut seq, &self.tune_lock)?;
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Settings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(SectionVisitor)
+    }
+}
+
+/// Restores the stored sections over the defaults.
+struct SectionVisitor;
+
+impl<'de> Visitor<'de> for SectionVisitor {
+    type Value = Settings;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of settings sections")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Settings, A::Error> {
+        let mut s = Settings::new();
+        while let Some((id, version, body)) = seq.next_element::<(u8, u8, &[u8])>()? {
+            let known = INPUT.restore(id, version, body, &mut s.input)
+                || AUDIO.restore(id, version, body, &mut s.audio)
+                || RECEPTION.restore(id, version, body, &mut s.reception)
+                || STEREO.restore(id, version, body, &mut s.stereo)
+                || SEEK.restore(id, version, body, &mut s.seek)
+                || CALIBRATION.restore(id, version, body, &mut s.calibration)
+                || LOG.restore(id, version, body, &mut s.log)
+                || NOTIFY.restore(id, version, body, &mut s.notify)
+                || BEEP.restore(id, version, body, &mut s.beep)
+                || UI.restore(id, version, body, &mut s.ui)
+                || CLOCK.restore(id, version, body, &mut s.clock)
+                || ALARM.restore(id, version, body, &mut s.alarm)
+                || ALERT.restore(id, version, body, &mut s.alert)
+                || LED.restore(id, version, body, &mut s.led)
+                || KIOSK.restore(id, version, body, &mut s.kiosk)
+                || TUNE_LOCK.restore(id, version, body, &mut s.tune_lock);
+            if !known {
+                warn!("Skipped unknown settings section {}", id);
+            }
+        }
+        Ok(s)
+    }
+}
+
+static SETTINGS: CriticalSectionMutex<Cell<Settings>> =
+    CriticalSectionMutex::new(Cell::new(Settings::new()));
+/// Raised by [`update`] to schedule a commit.
+static CHANGED: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+/// Set by [`update`] while the stored settings are outdated.
+static DIRTY: AtomicBool = AtomicBool::new(false);
+
+/// Copy of the current settings.
+pub fn get() -> Settings {
+    SETTINGS.lock(|settings| settings.get())
+}
+
+/// Modify the current settings in place.
+pub fn update(f: impl FnOnce(&mut Settings)) {
+    SETTINGS.lock(|settings| {
+        let mut value = settings.get();
+        f(&mut value);
+        settings.set(value);
+    });
+    DIRTY.store(true, Ordering::Relaxed);
+    CHANGED.signal(());
+}
+
+/// Replace the current settings with the stored ones. Returns `false` and
+/// keeps the defaults if nothing valid is stored. Call once during startup.
+pub fn load() -> Result<bool, Error> {
+    let mut buf = [0u8; RECORD_SIZE];
+    match record::load::<Settings>(&PARTITION, MAGIC, VERSION, &mut buf)? {
+        Some(stored) => {
+            SETTINGS.lock(|settings| settings.set(stored));
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Write the current settings to flash.
+pub fn save() -> Result<(), Error> {
+    DIRTY.store(false, Ordering::Relaxed);
+    let mut buf = [0u8; RECORD_SIZE];
+    record::save(&PARTITION, MAGIC, VERSION, &get(), &mut buf)
+}
+
+/// Write the settings now if a commit is pending. Returns whether anything
+/// was written.
+pub fn flush() -> Result<bool, Error> {
+    if !DIRTY.load(Ordering::Relaxed) {
+        return Ok(false);
+    }
+    save()?;
+    Ok(true)
+}
+
+/// Like [`flush`], but leaves the settings pending if writing them would
+/// need a page erase. Used on a power failure warning.
+pub fn flush_without_erase() -> Result<bool, Error> {
+    if !DIRTY.swap(false, Ordering::Relaxed) {
+        return Ok(false);
+    }
+    let mut buf = [0u8; RECORD_SIZE];
+    let written = record::save_without_erase(&PARTITION, MAGIC, VERSION, &get(), &mut buf)
+        .inspect_err(|_| DIRTY.store(true, Ordering::Relaxed))?;
+    if !written {
+        DIRTY.store(true, Ordering::Relaxed);
+    }
+    Ok(written)
+}
+
+/// Persist settings after they changed.
+#[embassy_executor::task]
+pub async fn commit_task() {
+    let heartbeat = monitor::register("settings");
+    loop {
+        CHANGED.wait().await;
+        // Restart the delay on every further change.
+        while with_timeout(COMMIT_DELAY, CHANGED.wait()).await.is_ok() {}
+        heartbeat.beat();
+        if let Err(err) = flush() {
+            warn!("Failed to save settings: {}", err);
+        }
+    }
+}
+
+/// Settings file being received, applied once it is complete.
+pub struct Import {
+    buf: [u8; RECORD_SIZE],
+    received: usize,
+}
+
+impl Import {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0xFF; RECORD_SIZE],
+            received: 0,
+        }
+    }
+}
+
+impl Default for Import {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sink for Import {
+    fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        // Anything past the largest record is block padding.
+        let len = data.len().min(RECORD_SIZE - self.received);
+        self.buf[self.received..self.received + len].copy_from_slice(&data[..len]);
+        self.received += len;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        if !get().tune_lock.is_modifiable() {
+            return Err(Error::Locked);
+        }
+        let imported: Settings = record::from_bytes(MAGIC, VERSION, &self.buf[..self.received])?;
+        update(|settings| {
+            *settings = Settings {
+                tune_lock: settings.tune_lock,
+                kiosk: settings.kiosk,
+                ..imported
+            }
+        });
+        Ok(())
+    }
+}
+
+/// The current settings as a file.
+pub struct Export {
+    buf: [u8; RECORD_SIZE],
+    len: usize,
+    position: usize,
+}
+
+impl Export {
+    pub fn new() -> Result<Self, Error> {
+        let mut settings = get();
+        settings.tune_lock.set_pin(None);
+        settings.kiosk.code = 0;
+        let mut buf = [0u8; RECORD_SIZE];
+        let len = record::to_bytes(MAGIC, VERSION, &settings, &mut buf)?;
+        Ok(Self {
+            buf,
+            len,
+            position: 0,
+        })
+    }
+}
+
+impl Source for Export {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let len = buf.len().min(self.len - self.position);
+        buf[..len].copy_from_slice(&self.buf[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body<'a, T: Serialize>(value: &T, buf: &'a mut [u8]) -> Bytes<'a> {
+        Bytes(postcard::to_slice(value, buf).unwrap())
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let mut settings = Settings::new();
+        settings.input.repeat_delay_ms = 300;
+        settings.seek.sensitivity = Sensitivity::High;
+        settings.alarm.frequency = Some(Frequency::from_khz(101_100));
+        let mut buf = [0u8; RECORD_SIZE];
+        let len = record::to_bytes(MAGIC, VERSION, &settings, &mut buf).unwrap();
+        let restored: Settings = record::from_bytes(MAGIC, VERSION, &buf[..len]).unwrap();
+        assert_eq!(restored, settings);
+    }
+
+    #[test]
+    fn changed_section_keeps_defaults() {
+        let mut input = InputSettings::new();
+        input.repeat_delay_ms = 300;
+        let seek = SeekSettings {
+            sensitivity: Sensitivity::High,
+        };
+        let (mut input_buf, mut seek_buf) = ([0u8; SECTION_SIZE], [0u8; SECTION_SIZE]);
+        let sections = [
+            (INPUT.id, INPUT.version, body(&input, &mut input_buf)),
+            (SEEK.id, SEEK.version + 1, body(&seek, &mut seek_buf)),
+            (SECTIONS as u8, 1, Bytes(&[1, 2, 3])),
+        ];
+        let mut buf = [0u8; RECORD_SIZE];
+        let stored = postcard::to_slice(&sections[..], &mut buf).unwrap();
+        let restored: Settings = postcard::from_bytes(stored).unwrap();
+        assert_eq!(
+            restored,
+            Settings {
+                input,
+                ..Settings::new()
+            }
+        );
+    }
+
+    #[test]
+    fn older_record_keeps_defaults_of_new_sections() {
+        let mut beep = BeepSettings::new();
+        beep.volume = 2;
+        let mut beep_buf = [0u8; SECTION_SIZE];
+        let sections = [(BEEP.id, BEEP.version, body(&beep, &mut beep_buf))];
+        let mut buf = [0u8; RECORD_SIZE];
+        let stored = postcard::to_slice(&sections[..], &mut buf).unwrap();
+        let restored: Settings = postcard::from_bytes(stored).unwrap();
+        assert_eq!(restored.beep, beep);
+        assert_eq!(restored.alarm, AlarmSettings::new());
+    }
+}
@@ -0,0 +1,78 @@
+//! Sleep timer.
+//!
+//! `sleep set <minutes>` arms [`sleep_task`]; [`FADE`] before the timer runs
+//! out it asks the radio loop to ramp the volume down to silence, so audio
+//! fades instead of stopping abruptly, and then sends
+//! [`SystemEvent::RadioOff`]. Cancelling the timer during the fade leaves the
+//! volume where the fade stopped.
+
+use core::cell::Cell;
+
+use embassy_futures::select::{Either, select};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Timer};
+use log::info;
+
+use crate::events::SystemEvent;
+use crate::monitor;
+use crate::service;
+
+/// Time the volume takes to fade out before the radio is switched off.
+pub const FADE: Duration = Duration::from_secs(60);
+
+/// When the radio is switched off, if the timer is armed.
+static END: Mutex<ThreadModeRawMutex, Cell<Option<Instant>>> = Mutex::new(Cell::new(None));
+/// Wakes the task after [`END`] changed.
+static CHANGED: Signal<ThreadModeRawMutex, ()> = Signal::new();
+
+/// When the radio is switched off, if the timer is armed.
+pub fn end() -> Option<Instant> {
+    END.lock(|end| end.get())
+}
+
+/// Switch the radio off after `duration`, or disarm the timer with `None`.
+pub fn set(duration: Option<Duration>) {
+    END.lock(|end| end.set(duration.map(|duration| Instant::now() + duration)));
+    CHANGED.signal(());
+}
+
+/// Wait until `at`. Returns `false` if the timer was changed meanwhile.
+async fn wait_until(at: Instant) -> bool {
+    matches!(
+        select(Timer::at(at), CHANGED.wait()).await,
+        Either::First(_)
+    )
+}
+
+/// Fade out and switch the radio off when the sleep timer runs out.
+#[embassy_executor::task]
+pub async fn sleep_task() {
+    let heartbeat = monitor::register("sleep");
+    loop {
+        heartbeat.beat();
+        let Some(end) = end() else {
+            CHANGED.wait().await;
+            continue;
+        };
+        let fade_at = end.checked_sub(FADE).unwrap_or(Instant::MIN);
+        if !wait_until(fade_at).await {
+            continue;
+        }
+        let fade = end.saturating_duration_since(Instant::now());
+        info!("Sleep timer fading out over {} s", fade.as_secs());
+        let _ = service::request(SystemEvent::RadioVolumeRamp {
+            level: 0,
+            duration_ms: fade.as_millis() as u32,
+        })
+        .await;
+        if !wait_until(end).await {
+            let _ = service::request(SystemEvent::RadioVolumeRampStop).await;
+            continue;
+        }
+        info!("Sleep timer expired");
+        END.lock(|end| end.set(None));
+        let _ = service::request(SystemEvent::RadioOff).await;
+    }
+}
@@ -0,0 +1,73 @@
+//! Stack usage.
+//!
+//! Embassy tasks have no stacks of their own: each task is a state machine
+//! stored in a static, and all of them, together with interrupt handlers,
+//! run on the one main stack. Task state therefore shows up in the `.bss`
+//! size reported by the linker, while the main stack takes whatever RAM is
+//! left above the statics.
+//!
+//! [`paint`] fills the unused part of the main stack with a pattern at boot.
+//! The deepest call since then is found by looking for the lowest word that
+//! no longer holds the pattern, which `stacks` reports as the high-water
+//! mark, to right-size the statically allocated buffers as subsystems grow.
+
+use core::ptr::addr_of;
+
+/// Pattern painted over the unused stack.
+const PAINT: u32 = 0xCDCD_CDCD;
+/// Bytes below the stack pointer left alone while painting, for the frame of
+/// [`paint`] itself.
+const MARGIN: usize = 256;
+
+unsafe extern "C" {
+    /// End of the statics, the lowest address the stack can grow to.
+    static __sheap: u32;
+    /// Initial stack pointer, the top of the stack.
+    static _stack_start: u32;
+}
+
+fn bottom() -> usize {
+    addr_of!(__sheap) as usize
+}
+
+fn top() -> usize {
+    addr_of!(_stack_start) as usize
+}
+
+/// Paint the stack below the current stack pointer. Call first thing in
+/// `main`.
+#[inline(never)]
+pub fn paint() {
+    let limit = cortex_m::register::msp::read() as usize - MARGIN;
+    let mut word = bottom() as *mut u32;
+    while (word as usize) < limit {
+        // SAFETY: the words between the statics and the stack pointer are
+        // not in use; interrupt frames pushed meanwhile are dead once the
+        // handler returns.
+        unsafe {
+            word.write_volatile(PAINT);
+            word = word.add(1);
+        }
+    }
+}
+
+/// Size of the main stack and the most of it used since boot, in bytes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Usage {
+    pub size: u32,
+    pub peak: u32,
+}
+
+/// Main stack usage since [`paint`].
+pub fn usage() -> Usage {
+    let mut word = bottom() as *const u32;
+    // SAFETY: every word between the statics and the top of the stack is
+    // RAM; reading the unused ones has no effect.
+    while (word as usize) < top() && unsafe { word.read_volatile() } == PAINT {
+        word = unsafe { word.add(1) };
+    }
+    Usage {
+        size: (top() - bottom()) as u32,
+        peak: (top() - word as usize) as u32,
+    }
+}
@@ -0,0 +1,50 @@
+//! Startup sequencing.
+//!
+//! `main` spawns the tasks and then powers up the tuner, whose first
+//! notifications only reach subscribers that already exist. Instead of
+//! relying on the executor to run the new tasks in time, startup is ordered
+//! explicitly:
+//!
+//! 1. Tasks listening to notifications call [`subscribed`] once their
+//!    subscriber exists. `main` counts the ones it spawned and waits for them
+//!    with [`wait_subscribed`] before the radio publishes anything.
+//! 2. `main` calls [`set_radio_ready`] when the radio loop starts taking
+//!    events. Tasks whose commands would otherwise be dropped, like the CLI,
+//!    wait for it with [`radio_ready`].
+
+use core::cell::Cell;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::once_lock::OnceLock;
+use embassy_sync::signal::Signal;
+
+/// Number of tasks that have subscribed to notifications.
+static SUBSCRIBED: Mutex<ThreadModeRawMutex, Cell<u8>> = Mutex::new(Cell::new(0));
+/// Wakes [`wait_subscribed`] after [`SUBSCRIBED`] changed.
+static ARRIVED: Signal<ThreadModeRawMutex, ()> = Signal::new();
+/// Set once the radio loop takes events.
+static RADIO_READY: OnceLock<()> = OnceLock::new();
+
+/// Report that the calling task has subscribed to notifications.
+pub fn subscribed() {
+    SUBSCRIBED.lock(|subscribed| subscribed.set(subscribed.get() + 1));
+    ARRIVED.signal(());
+}
+
+/// Wait until `count` tasks have subscribed to notifications.
+pub async fn wait_subscribed(count: u8) {
+    while SUBSCRIBED.lock(|subscribed| subscribed.get()) < count {
+        ARRIVED.wait().await;
+    }
+}
+
+/// Report that the radio loop takes events.
+pub fn set_radio_ready() {
+    let _ = RADIO_READY.init(());
+}
+
+/// Wait until the radio loop takes events.
+pub async fn radio_ready() {
+    RADIO_READY.get().await;
+}
@@ -0,0 +1,135 @@
+//! Current radio state.
+//!
+//! Notifications only report changes, so a display that was just initialized
+//! or a host that just connected would show nothing until the next one. The
+//! latest values are therefore also kept as a [`RadioState`] snapshot in a
+//! [`Watch`]: every notification published through [`events`](crate::events)
+//! is folded in with [`apply`], [`get`] returns the snapshot and a
+//! [`receiver`] yields it at once and then on every change.
+//!
+//! Stereo is only known from signal quality readings, so it stays `false`
+//! until one is taken (`poll rsq on` or `diag fm`).
+
+use core::fmt;
+
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::watch::{Receiver, Watch};
+
+use crate::events::SystemNotify;
+use crate::frequency::Frequency;
+
+/// Maximum number of receivers watching the state.
+pub const MAX_RECEIVERS: usize = 4;
+
+/// Operating mode of the tuner.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Off,
+    Fm,
+    Am,
+}
+
+impl Mode {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Mode::Off => "off",
+            Mode::Fm => "FM",
+            Mode::Am => "AM",
+        }
+    }
+}
+
+/// Snapshot of the radio state.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RadioState {
+    pub mode: Mode,
+    /// Tuned frequency.
+    pub frequency: Frequency,
+    /// Volume in percent.
+    pub volume: u8,
+    pub muted: bool,
+    /// The last signal quality reading showed a stereo blend.
+    pub stereo: bool,
+    /// Received signal strength in dBµV.
+    pub rssi: u8,
+}
+
+impl RadioState {
+    pub const fn new() -> Self {
+        Self {
+            mode: Mode::Off,
+            frequency: Frequency::from_khz(0),
+            volume: 0,
+            muted: false,
+            stereo: false,
+            rssi: 0,
+        }
+    }
+}
+
+impl Default for RadioState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for RadioState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:.2}, volume {}{}, {}, RSSI {} dBuV",
+            self.mode.name(),
+            self.frequency,
+            self.volume,
+            if self.muted { " (muted)" } else { "" },
+            if self.stereo { "stereo" } else { "mono" },
+            self.rssi
+        )
+    }
+}
+
+static STATE: Watch<ThreadModeRawMutex, RadioState, MAX_RECEIVERS> =
+    Watch::new_with(RadioState::new());
+
+/// The latest radio state.
+pub fn get() -> RadioState {
+    STATE.try_get().unwrap_or_default()
+}
+
+/// Watch the radio state. Returns `None` once [`MAX_RECEIVERS`] are taken.
+pub fn receiver() -> Option<Receiver<'static, ThreadModeRawMutex, RadioState, MAX_RECEIVERS>> {
+    STATE.receiver()
+}
+
+/// Change the snapshot, waking receivers if anything changed.
+pub fn update(f: impl Fn(&mut RadioState)) {
+    STATE.sender().send_if_modified(|state| {
+        let state = state.get_or_insert_default();
+        let before = *state;
+        f(state);
+        *state != before
+    });
+}
+
+/// Fold `notification` into the snapshot.
+pub fn apply(notification: &SystemNotify) {
+    match *notification {
+        SystemNotify::RadioFmOn => update(|state| state.mode = Mode::Fm),
+        SystemNotify::RadioAmOn => update(|state| state.mode = Mode::Am),
+        SystemNotify::RadioOff => update(|state| state.mode = Mode::Off),
+        SystemNotify::RadioMute => update(|state| state.muted = true),
+        SystemNotify::RadioUnmute => update(|state| state.muted = false),
+        SystemNotify::VolumeChanged(volume) => update(|state| state.volume = volume),
+        SystemNotify::TuneStatus(status) => update(|state| {
+            state.frequency = status.frequency;
+            state.rssi = status.rssi;
+        }),
+        SystemNotify::SignalQuality(quality) | SystemNotify::FmDiagnostics(quality) => {
+            update(|state| {
+                state.rssi = quality.rssi;
+                state.stereo = quality.stereo_blend > 0;
+            })
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,313 @@
+//! Persisted usage statistics.
+//!
+//! Counts power cycles and accumulates listening time per band and per
+//! station, for users curious about their habits and as evidence from soak
+//! tests. [`stats_task`] follows the radio notifications: time counts while
+//! the receiver is on, unmuted and at a volume above zero, and is credited to
+//! the band and channel last reported by a tune status. The totals are kept in
+//! [`storage::STATS`](crate::storage::STATS) and written every
+//! [`COMMIT_INTERVAL`] while they change, so at most that much listening time
+//! is lost on a power cut.
+//!
+//! The record also carries the flash erase counts of
+//! [`storage`](crate::storage), which has nowhere else to keep them.
+//!
+//! Presets do not exist yet, so stations are identified by their frequency.
+//! Up to [`MAX_STATIONS`] are tracked; a new station replaces the one with
+//! the least listening time.
+
+use core::cell::Cell;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embassy_futures::select::{Either, select};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_time::{Duration, Instant, Timer};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::events::{self, Received, SystemNotify};
+use crate::frequency::Frequency;
+use crate::monitor;
+use crate::record;
+use crate::scan::Band;
+use crate::startup;
+use crate::state::{Mode, RadioState};
+use crate::storage::{self, PARTITIONS, STATS as PARTITION};
+
+/// Stations whose listening time is tracked.
+pub const MAX_STATIONS: usize = 16;
+/// Time between flash commits while the statistics change.
+pub const COMMIT_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Marks a valid record ("S47T").
+const MAGIC: u32 = 0x5437_3453;
+/// Bump whenever the layout of [`Stats`] changes.
+const VERSION: u16 = 4;
+/// Largest serialized record, header included.
+const RECORD_SIZE: usize = 512;
+/// Listening time is accounted at least this often.
+const TICK: Duration = Duration::from_secs(60);
+
+/// Listening time on one station.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StationTime {
+    pub band: Band,
+    /// Frequency in 10 kHz units for FM, in kHz for AM and SW.
+    channel: u16,
+    pub seconds: u32,
+}
+
+impl StationTime {
+    pub fn frequency(&self) -> Frequency {
+        match self.band {
+            Band::Fm => Frequency::from_khz(self.channel as u32 * 10),
+            Band::Am | Band::Sw => Frequency::from_khz(self.channel as u32),
+        }
+    }
+}
+
+/// Cumulative statistics.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stats {
+    /// Boots since the statistics were cleared.
+    pub power_cycles: u32,
+    /// Listening seconds per band, in [`Band::ALL`] order.
+    band_seconds: [u32; 3],
+    stations: [Option<StationTime>; MAX_STATIONS],
+    /// Flash page erases per partition, kept across `stats clear`.
+    erases: [u32; PARTITIONS.len()],
+}
+
+impl Stats {
+    pub const fn new() -> Self {
+        Self {
+            power_cycles: 0,
+            band_seconds: [0; 3],
+            stations: [None; MAX_STATIONS],
+            erases: [0; PARTITIONS.len()],
+        }
+    }
+
+    /// Listening seconds on `band`.
+    pub fn band_seconds(&self, band: Band) -> u32 {
+        self.band_seconds[band as usize]
+    }
+
+    /// Tracked stations, most listened first.
+    pub fn stations(&self) -> impl Iterator<Item = StationTime> {
+        let mut stations = self.stations;
+        stations.sort_unstable_by_key(|station| {
+            core::cmp::Reverse(station.map_or(0, |station| station.seconds))
+        });
+        stations.into_iter().flatten()
+    }
+
+    fn add(&mut self, band: Band, channel: u16, seconds: u32) {
+        let total = &mut self.band_seconds[band as usize];
+        *total = total.saturating_add(seconds);
+        let existing = self
+            .stations
+            .iter()
+            .position(|slot| slot.is_some_and(|s| s.band == band && s.channel == channel));
+        let least = self.stations.iter().flatten().map(|s| s.seconds).min();
+        let Some(index) = existing
+            .or_else(|| self.stations.iter().position(|slot| slot.is_none()))
+            .or_else(|| {
+                self.stations
+                    .iter()
+                    .position(|slot| slot.map(|s| s.seconds) == least)
+            })
+        else {
+            return;
+        };
+        if existing.is_none() {
+            self.stations[index] = Some(StationTime {
+                band,
+                channel,
+                seconds: 0,
+            });
+        }
+        if let Some(station) = &mut self.stations[index] {
+            station.seconds = station.seconds.saturating_add(seconds);
+        }
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+static STATS: Mutex<ThreadModeRawMutex, Cell<Stats>> = Mutex::new(Cell::new(Stats::new()));
+/// Set while listening time was credited since the last write.
+static DIRTY: AtomicBool = AtomicBool::new(false);
+
+/// Copy of the current statistics.
+pub fn get() -> Stats {
+    STATS.lock(|stats| stats.get())
+}
+
+/// Restore the stored statistics and count this boot. Call once during
+/// startup; [`stats_task`] writes the new count.
+pub fn load() -> Result<(), Error> {
+    let mut buf = [0u8; RECORD_SIZE];
+    let stored = record::load::<Stats>(&PARTITION, MAGIC, VERSION, &mut buf)?;
+    let mut stats = stored.unwrap_or_default();
+    stats.power_cycles = stats.power_cycles.saturating_add(1);
+    storage::restore_erase_counts(stats.erases);
+    STATS.lock(|cell| cell.set(stats));
+    Ok(())
+}
+
+/// Write the current statistics to flash.
+pub fn save() -> Result<(), Error> {
+    DIRTY.store(false, Ordering::Relaxed);
+    let mut stats = get();
+    stats.erases = storage::erase_counts();
+    let mut buf = [0u8; RECORD_SIZE];
+    record::save(&PARTITION, MAGIC, VERSION, &stats, &mut buf)
+}
+
+/// Write the statistics if listening time was credited since the last write
+/// and no page erase is needed for it. Used on a power failure warning.
+/// Returns whether anything was written.
+pub fn flush_without_erase() -> Result<bool, Error> {
+    if !DIRTY.swap(false, Ordering::Relaxed) {
+        return Ok(false);
+    }
+    let mut stats = get();
+    stats.erases = storage::erase_counts();
+    let mut buf = [0u8; RECORD_SIZE];
+    let written = record::save_without_erase(&PARTITION, MAGIC, VERSION, &stats, &mut buf)
+        .inspect_err(|_| DIRTY.store(true, Ordering::Relaxed))?;
+    if !written {
+        DIRTY.store(true, Ordering::Relaxed);
+    }
+    Ok(written)
+}
+
+/// Reset all statistics and write them.
+pub fn clear() -> Result<(), Error> {
+    STATS.lock(|stats| stats.set(Stats::new()));
+    save()
+}
+
+/// What the receiver is playing, as far as the notifications tell.
+struct Listening {
+    band: Option<Band>,
+    /// Channel of the last tune status, in [`StationTime`] units.
+    channel: Option<u16>,
+    muted: bool,
+    silent: bool,
+    /// Time up to which listening has been accounted.
+    since: Instant,
+}
+
+impl Listening {
+    /// Credit the time since the last call, returning whether any was.
+    fn account(&mut self) -> bool {
+        let now = Instant::now();
+        let seconds = (now - self.since).as_secs() as u32;
+        if seconds == 0 {
+            return false;
+        }
+        // Keep the sub-second remainder for the next call.
+        self.since += Duration::from_secs(seconds as u64);
+        match (self.band, self.channel) {
+            (Some(band), Some(channel)) if !self.muted && !self.silent => {
+                STATS.lock(|stats| {
+                    let mut value = stats.get();
+                    value.add(band, channel, seconds);
+                    stats.set(value);
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn update(&mut self, notification: &SystemNotify) {
+        match notification {
+            SystemNotify::RadioFmOn => self.band = Some(Band::Fm),
+            SystemNotify::RadioAmOn => self.band = Some(Band::Am),
+            SystemNotify::RadioOff => self.band = None,
+            SystemNotify::RadioMute => self.muted = true,
+            SystemNotify::RadioUnmute => self.muted = false,
+            SystemNotify::VolumeChanged(volume) => self.silent = *volume == 0,
+            SystemNotify::TuneStatus(status) => self.tuned(status.frequency),
+            _ => {}
+        }
+    }
+
+    /// Note the frequency tuned.
+    fn tuned(&mut self, frequency: Frequency) {
+        match self.band {
+            Some(Band::Fm) => self.channel = Some(((frequency.khz() + 5) / 10) as u16),
+            Some(Band::Am | Band::Sw) => {
+                let khz = frequency.khz() as u16;
+                // Frequencies above the MW band are shortwave.
+                self.band = Some(if khz > 1710 { Band::Sw } else { Band::Am });
+                self.channel = Some(khz);
+            }
+            None => {}
+        }
+    }
+
+    /// Catch up with `state` after missed notifications.
+    fn sync(&mut self, state: &RadioState) {
+        self.band = match state.mode {
+            Mode::Off => None,
+            Mode::Fm => Some(Band::Fm),
+            Mode::Am => Some(Band::Am),
+        };
+        self.channel = None;
+        self.tuned(state.frequency);
+        self.muted = state.muted;
+        self.silent = state.volume == 0;
+    }
+}
+
+/// Account listening time and commit the statistics periodically.
+#[embassy_executor::task]
+pub async fn stats_task() {
+    let heartbeat = monitor::register("stats");
+    let mut subscriber = events::notify_subscriber().unwrap();
+    startup::subscribed();
+    if let Err(err) = save() {
+        warn!("Failed to save statistics: {}", err);
+    }
+    let mut listening = Listening {
+        band: None,
+        channel: None,
+        muted: false,
+        silent: false,
+        since: Instant::now(),
+    };
+    let mut commit_at = Instant::now() + COMMIT_INTERVAL;
+    loop {
+        let next = select(subscriber.next(), Timer::after(TICK)).await;
+        heartbeat.beat();
+        if listening.account() {
+            DIRTY.store(true, Ordering::Relaxed);
+        }
+        match next {
+            Either::First(Received::Notification(delivery)) => {
+                listening.update(&delivery.notification)
+            }
+            Either::First(Received::Lagged { missed, state }) => {
+                warn!("Statistics missed {} notifications", missed);
+                listening.sync(&state);
+            }
+            Either::Second(()) => {}
+        }
+        if DIRTY.load(Ordering::Relaxed) && Instant::now() >= commit_at {
+            if let Err(err) = save() {
+                warn!("Failed to save statistics: {}", err);
+            }
+            commit_at = Instant::now() + COMMIT_INTERVAL;
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! SNR controlled mono override.
+//!
+//! The tuner blends between stereo and mono on its own, but on some parts the
+//! blend lets too much stereo noise through on weak stations. While the
+//! override is enabled, the radio loop reads the SNR every second through
+//! [`poller::Source::Stereo`] and [`decide`] forces mono once it drops below
+//! [`StereoSettings::mono_below_snr`], returning to the tuner's own blend
+//! once it reaches [`StereoSettings::stereo_above_snr`]. The gap between the
+//! two keeps a station near the threshold from flapping between both.
+//!
+//! Mono is forced by raising the RSSI blend thresholds to their maximum and
+//! undone by writing back the values of the current reception profile.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::events::{self, Origin, SystemEvent};
+use crate::poller::{self, Source};
+use crate::properties::{self, Property};
+use crate::settings::{self, StereoSettings};
+
+/// RSSI blend threshold forcing mono.
+const FORCE_MONO: u16 = 127;
+/// Properties written to force mono.
+const BLEND: [Property; 2] = [
+    properties::FM_BLEND_RSSI_STEREO_THRESHOLD,
+    properties::FM_BLEND_RSSI_MONO_THRESHOLD,
+];
+
+static FORCED: AtomicBool = AtomicBool::new(false);
+
+/// Whether mono is currently forced.
+pub fn is_forced() -> bool {
+    FORCED.load(Ordering::Relaxed)
+}
+
+/// Record that mono was forced or released.
+pub fn set_forced(mono: bool) {
+    FORCED.store(mono, Ordering::Relaxed);
+}
+
+/// Enable or disable the override. Disabling releases a forced mono.
+pub fn set_enabled(enabled: bool) {
+    settings::update(|s| s.stereo.enabled = enabled);
+    poller::set_enabled(Source::Stereo, enabled);
+    events::event_try_send(Origin::System, SystemEvent::RadioStereo);
+}
+
+/// Whether mono should be forced at `snr`, if that differs from now.
+pub fn decide(snr: u8) -> Option<bool> {
+    let stereo = settings::get().stereo;
+    let mono = if !stereo.enabled {
+        false
+    } else if is_forced() {
+        snr < stereo.stereo_above_snr
+    } else {
+        snr < stereo.mono_below_snr
+    };
+    (mono != is_forced()).then_some(mono)
+}
+
+/// Property values forcing mono, or restoring the profile's blend.
+pub fn properties(mono: bool) -> [(Property, u16); 2] {
+    let profile = settings::get().reception.profile;
+    BLEND.map(|property| {
+        let value = if mono {
+            FORCE_MONO
+        } else {
+            profile
+                .values()
+                .iter()
+                .find(|(p, _)| *p == property)
+                .map_or(property.default, |&(_, value)| value)
+        };
+        (property, value)
+    })
+}
+
+/// Whether `mono_below_snr` and `stereo_above_snr` form a valid pair.
+pub fn valid(settings: &StereoSettings) -> bool {
+    settings.mono_below_snr < settings.stereo_above_snr && settings.stereo_above_snr <= 127
+}
@@ -0,0 +1,200 @@
+//! Raw access to the data partitions at the top of the internal flash.
+//!
+//! The application image is linked below [`DATA_START`] (see `memory.x`); the
+//! remaining pages are split into fixed [`Partition`]s used by the persistence
+//! features. All partitions share one NVMC driver installed with [`init`].
+//! Accesses are blocking and page erases stall the CPU for tens of
+//! milliseconds, so callers should keep them out of latency-sensitive paths.
+
+use core::cell::{Cell, RefCell};
+
+use embassy_nrf::nvmc::{self, Nvmc};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+use log::warn;
+
+/// Erase granularity of the internal flash.
+pub const PAGE_SIZE: u32 = nvmc::PAGE_SIZE as u32;
+
+/// First address not used by the application image.
+pub const DATA_START: u32 = 0x000E_0000;
+
+/// Drive-test survey ring log.
+pub const SURVEY: Partition = Partition::new("survey", DATA_START, 16 * PAGE_SIZE);
+/// Console session capture.
+pub const CAPTURE: Partition =
+    Partition::new("capture", SURVEY.offset + SURVEY.size, 6 * PAGE_SIZE);
+/// Radio event trace.
+pub const TRACE: Partition = Partition::new("trace", CAPTURE.offset + CAPTURE.size, 2 * PAGE_SIZE);
+/// Si47xx firmware patch applied at power-up.
+pub const PATCH: Partition = Partition::new("patch", TRACE.offset + TRACE.size, 4 * PAGE_SIZE);
+/// Learned RDS stations.
+pub const RDS_DB: Partition = Partition::new("rds", PATCH.offset + PATCH.size, PAGE_SIZE);
+/// Persisted runtime settings, two pages so the previous copy survives
+/// while a page is erased.
+pub const SETTINGS: Partition =
+    Partition::new("settings", RDS_DB.offset + RDS_DB.size, 2 * PAGE_SIZE);
+/// Power cycle and listening time statistics.
+pub const STATS: Partition = Partition::new("stats", SETTINGS.offset + SETTINGS.size, PAGE_SIZE);
+
+/// All partitions, in address order.
+pub const PARTITIONS: [Partition; 7] = [SURVEY, CAPTURE, TRACE, PATCH, RDS_DB, SETTINGS, STATS];
+
+/// Rated erase cycles of a flash page.
+pub const ENDURANCE: u32 = 10_000;
+/// Share of the rated endurance, in percent, at which a partition is
+/// reported as worn.
+pub const WEAR_WARNING_PCT: u32 = 80;
+
+/// Errors reported by partition accesses.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StorageError {
+    /// [`init`] has not been called yet.
+    NotInitialized,
+    /// The access does not fit into the partition.
+    OutOfBounds,
+    /// The flash controller rejected the access.
+    Flash(nvmc::Error),
+}
+
+static FLASH: Mutex<ThreadModeRawMutex, RefCell<Option<Nvmc<'static>>>> =
+    Mutex::new(RefCell::new(None));
+/// Page erases per partition, in [`PARTITIONS`] order. Persisted with the
+/// statistics, see [`erase_counts`].
+static ERASES: Mutex<ThreadModeRawMutex, Cell<[u32; PARTITIONS.len()]>> =
+    Mutex::new(Cell::new([0; PARTITIONS.len()]));
+
+/// Install the NVMC driver used by all partitions.
+///
+/// Call this once during startup.
+pub fn init(nvmc: Nvmc<'static>) {
+    FLASH.lock(|flash| flash.borrow_mut().replace(nvmc));
+}
+
+fn with_flash<R>(
+    f: impl FnOnce(&mut Nvmc<'static>) -> Result<R, nvmc::Error>,
+) -> Result<R, StorageError> {
+    FLASH.lock(|flash| match flash.borrow_mut().as_mut() {
+        Some(nvmc) => f(nvmc).map_err(StorageError::Flash),
+        None => Err(StorageError::NotInitialized),
+    })
+}
+
+/// The partition called `name`.
+pub fn find(name: &str) -> Option<Partition> {
+    PARTITIONS
+        .into_iter()
+        .find(|partition| partition.name == name)
+}
+
+/// Page erases of every partition, in [`PARTITIONS`] order.
+///
+/// The counts only live in RAM here; [`stats`](crate::stats) stores them
+/// with its record and hands them back with [`restore_erase_counts`] at
+/// startup, so they cover the device's lifetime up to the last stats commit.
+pub fn erase_counts() -> [u32; PARTITIONS.len()] {
+    ERASES.lock(|erases| erases.get())
+}
+
+/// Replace the erase counts with stored ones.
+pub fn restore_erase_counts(counts: [u32; PARTITIONS.len()]) {
+    ERASES.lock(|erases| erases.set(counts));
+}
+
+/// A fixed, page-aligned region of the internal flash.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Partition {
+    /// Human-readable name used by CLI commands.
+    pub name: &'static str,
+    /// Absolute start address.
+    pub offset: u32,
+    /// Size in bytes, a multiple of [`PAGE_SIZE`].
+    pub size: u32,
+}
+
+impl Partition {
+    pub const fn new(name: &'static str, offset: u32, size: u32) -> Self {
+        Self { name, offset, size }
+    }
+
+    /// Number of erase pages in the partition.
+    pub const fn pages(&self) -> u32 {
+        self.size / PAGE_SIZE
+    }
+
+    fn index(&self) -> Option<usize> {
+        PARTITIONS.iter().position(|p| p.offset == self.offset)
+    }
+
+    /// Page erases counted for the partition.
+    pub fn erases(&self) -> u32 {
+        self.index().map_or(0, |index| erase_counts()[index])
+    }
+
+    /// Average erases per page as a percentage of [`ENDURANCE`].
+    pub fn wear_pct(&self) -> u32 {
+        (self.erases() as u64 * 100 / (self.pages() as u64 * ENDURANCE as u64)) as u32
+    }
+
+    /// Number of erased (`0xFF`) bytes, free for programming without an
+    /// erase.
+    pub fn free(&self) -> Result<u32, StorageError> {
+        let mut chunk = [0u8; 256];
+        let mut free = 0;
+        for offset in (0..self.size).step_by(chunk.len()) {
+            self.read(offset, &mut chunk)?;
+            free += chunk.iter().filter(|&&b| b == 0xFF).count() as u32;
+        }
+        Ok(free)
+    }
+
+    fn absolute(&self, offset: u32, len: usize) -> Result<u32, StorageError> {
+        match offset.checked_add(len as u32) {
+            Some(end) if end <= self.size => Ok(self.offset + offset),
+            _ => Err(StorageError::OutOfBounds),
+        }
+    }
+
+    /// Read `buf.len()` bytes starting at `offset` within the partition.
+    pub fn read(&self, offset: u32, buf: &mut [u8]) -> Result<(), StorageError> {
+        let address = self.absolute(offset, buf.len())?;
+        with_flash(|nvmc| nvmc.read(address, buf))
+    }
+
+    /// Program `data` at `offset` within the partition.
+    ///
+    /// The target area must be erased and both `offset` and `data.len()`
+    /// must be word-aligned.
+    pub fn write(&self, offset: u32, data: &[u8]) -> Result<(), StorageError> {
+        let address = self.absolute(offset, data.len())?;
+        with_flash(|nvmc| nvmc.write(address, data))
+    }
+
+    /// Erase the page with index `page` within the partition.
+    pub fn erase_page(&self, page: u32) -> Result<(), StorageError> {
+        let address = self.absolute(page * PAGE_SIZE, PAGE_SIZE as usize)?;
+        with_flash(|nvmc| nvmc.erase(address, address + PAGE_SIZE))?;
+        if let Some(index) = self.index() {
+            let before = self.wear_pct();
+            ERASES.lock(|erases| {
+                let mut counts = erases.get();
+                counts[index] = counts[index].saturating_add(1);
+                erases.set(counts);
+            });
+            if before < WEAR_WARNING_PCT && self.wear_pct() >= WEAR_WARNING_PCT {
+                warn!(
+                    "Flash partition {} reached {} % of its rated erase cycles",
+                    self.name, WEAR_WARNING_PCT
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Erase the whole partition.
+    pub fn erase_all(&self) -> Result<(), StorageError> {
+        (0..self.pages()).try_for_each(|page| self.erase_page(page))
+    }
+}
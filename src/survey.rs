@@ -0,0 +1,193 @@
+//! Time-based signal quality logging for drive tests.
+//!
+//! While a survey runs, the radio loop takes a sample every interval as the
+//! [`poller::Source::Survey`] poll. It reads the current tune status and
+//! hands it to [`record`], which appends a fixed-size
+//! [`SurveyRecord`] to the [`storage::SURVEY`] ring. When the ring wraps, the
+//! oldest page is erased. Records are stamped with the current [`gps::fix`]
+//! when a receiver is connected; [`dump`] replays the log oldest first.
+
+use core::cell::Cell;
+
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::frequency::{Frequency, TuneStatus};
+use crate::gps::{self, Degrees};
+use crate::poller::{self, Source};
+use crate::storage::{self, PAGE_SIZE};
+
+/// Sampling interval used when none is given.
+pub const DEFAULT_INTERVAL_S: u16 = 10;
+
+const RECORD_SIZE: u32 = 32;
+const SLOTS: u32 = storage::SURVEY.size / RECORD_SIZE;
+const ERASED: u32 = 0xFFFF_FFFF;
+const NO_POSITION: i32 = i32::MIN;
+
+/// One signal quality sample.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SurveyRecord {
+    /// Monotonic record number, survives reboots.
+    pub sequence: u32,
+    /// Milliseconds since boot when the sample was taken.
+    pub timestamp_ms: u32,
+    /// Tuned frequency.
+    pub frequency: Frequency,
+    /// Received signal strength in dBµV.
+    pub rssi: u8,
+    /// Signal-to-noise ratio in dB.
+    pub snr: u8,
+    /// Multipath indicator.
+    pub multipath: u8,
+    /// Latitude and longitude, if a GPS fix was available.
+    pub position: Option<(Degrees, Degrees)>,
+}
+
+impl SurveyRecord {
+    fn to_bytes(self) -> [u8; RECORD_SIZE as usize] {
+        let mut bytes = [0xFF; RECORD_SIZE as usize];
+        bytes[0..4].copy_from_slice(&self.sequence.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.timestamp_ms.to_le_bytes());
+        // Stored as `f32` MHz, as logs written before kHz frequencies were.
+        bytes[8..12].copy_from_slice(&self.frequency.mhz_f32().to_le_bytes());
+        bytes[12] = self.rssi;
+        bytes[13] = self.snr;
+        bytes[14] = self.multipath;
+        let (latitude, longitude) = self
+            .position
+            .map_or((NO_POSITION, NO_POSITION), |(lat, lon)| (lat.0, lon.0));
+        bytes[16..20].copy_from_slice(&latitude.to_le_bytes());
+        bytes[20..24].copy_from_slice(&longitude.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8; RECORD_SIZE as usize]) -> Option<Self> {
+        let word = |at: usize| {
+            u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+        };
+        let sequence = word(0);
+        if sequence == ERASED {
+            return None;
+        }
+        Some(Self {
+            sequence,
+            timestamp_ms: word(4),
+            frequency: Frequency::from_mhz_f32(f32::from_bits(word(8))),
+            rssi: bytes[12],
+            snr: bytes[13],
+            multipath: bytes[14],
+            position: match (word(16) as i32, word(20) as i32) {
+                (NO_POSITION, _) | (_, NO_POSITION) => None,
+                (lat, lon) => Some((Degrees(lat), Degrees(lon))),
+            },
+        })
+    }
+}
+
+/// Position and sequence number of the next record to write.
+#[derive(Debug, Copy, Clone)]
+struct Cursor {
+    slot: u32,
+    sequence: u32,
+}
+
+static CURSOR: Mutex<ThreadModeRawMutex, Cell<Option<Cursor>>> = Mutex::new(Cell::new(None));
+
+fn read_slot(slot: u32) -> Result<Option<SurveyRecord>, Error> {
+    let mut bytes = [0; RECORD_SIZE as usize];
+    storage::SURVEY.read(slot * RECORD_SIZE, &mut bytes)?;
+    Ok(SurveyRecord::from_bytes(&bytes))
+}
+
+/// Return the write cursor, scanning the ring for the newest record on first use.
+fn cursor() -> Result<Cursor, Error> {
+    if let Some(cursor) = CURSOR.lock(|c| c.get()) {
+        return Ok(cursor);
+    }
+    let mut newest: Option<(u32, u32)> = None;
+    for slot in 0..SLOTS {
+        if let Some(record) = read_slot(slot)?
+            && newest.is_none_or(|(_, sequence)| record.sequence > sequence)
+        {
+            newest = Some((slot, record.sequence));
+        }
+    }
+    let cursor = match newest {
+        Some((slot, sequence)) => Cursor {
+            slot: (slot + 1) % SLOTS,
+            sequence: sequence + 1,
+        },
+        None => Cursor {
+            slot: 0,
+            sequence: 0,
+        },
+    };
+    CURSOR.lock(|c| c.set(Some(cursor)));
+    Ok(cursor)
+}
+
+/// Append a sample built from `status` to the survey log.
+pub fn record(status: &TuneStatus) -> Result<SurveyRecord, Error> {
+    let cursor = cursor()?;
+    let offset = cursor.slot * RECORD_SIZE;
+    if offset.is_multiple_of(PAGE_SIZE) {
+        storage::SURVEY.erase_page(offset / PAGE_SIZE)?;
+    }
+    let record = SurveyRecord {
+        sequence: cursor.sequence,
+        timestamp_ms: Instant::now().as_millis() as u32,
+        frequency: status.frequency,
+        rssi: status.rssi,
+        snr: status.snr,
+        multipath: status.multipath,
+        position: gps::fix().map(|fix| (fix.latitude, fix.longitude)),
+    };
+    storage::SURVEY.write(offset, &record.to_bytes())?;
+    CURSOR.lock(|c| {
+        c.set(Some(Cursor {
+            slot: (cursor.slot + 1) % SLOTS,
+            sequence: cursor.sequence + 1,
+        }))
+    });
+    Ok(record)
+}
+
+/// Call `f` for every stored record, oldest first.
+pub fn dump(mut f: impl FnMut(&SurveyRecord)) -> Result<(), Error> {
+    let start = cursor()?.slot;
+    for i in 0..SLOTS {
+        if let Some(record) = read_slot((start + i) % SLOTS)? {
+            f(&record);
+        }
+    }
+    Ok(())
+}
+
+/// Erase the whole survey log.
+pub fn clear() -> Result<(), Error> {
+    storage::SURVEY.erase_all()?;
+    CURSOR.lock(|c| {
+        c.set(Some(Cursor {
+            slot: 0,
+            sequence: 0,
+        }))
+    });
+    Ok(())
+}
+
+/// Start sampling every `interval_s` seconds.
+pub fn start(interval_s: u16) {
+    poller::set_interval(
+        Source::Survey,
+        Duration::from_secs(interval_s.max(1) as u64),
+    );
+    poller::set_enabled(Source::Survey, true);
+}
+
+/// Stop sampling.
+pub fn stop() {
+    poller::set_enabled(Source::Survey, false);
+}
@@ -0,0 +1,51 @@
+//! Telemetry stream for plotting tools.
+//!
+//! While `telemetry on <hz>` is set, the radio loop reads the signal quality
+//! through [`poller::Source::Telemetry`] at the requested rate and [`emit`]
+//! writes one fixed-format CSV line per reading to the console:
+//!
+//! ```text
+//! TLM,<uptime ms>,<frequency>,<RSSI dBuV>,<SNR dB>,<stereo 0|1>,<volume %>
+//! ```
+//!
+//! Lines start with `TLM,` so a host can pick them out of the other console
+//! output. They bypass the logger, so log levels and filters do not affect
+//! them, and `telemetry off` stops them without touching the log. The stream
+//! is not persisted.
+
+use core::fmt::Write;
+
+use embassy_time::{Duration, Instant};
+
+use crate::console;
+use crate::poller::{self, Source};
+use crate::state;
+
+/// Highest accepted rate, in lines per second.
+pub const MAX_RATE_HZ: u8 = 20;
+
+/// Start emitting `hz` lines per second.
+pub fn start(hz: u8) {
+    poller::set_interval(Source::Telemetry, Duration::from_hz(hz.max(1) as u64));
+    poller::set_enabled(Source::Telemetry, true);
+}
+
+/// Stop the stream.
+pub fn stop() {
+    poller::set_enabled(Source::Telemetry, false);
+}
+
+/// Write one line for a signal quality reading.
+pub fn emit(rssi: u8, snr: u8, stereo: bool) {
+    let state = state::get();
+    let _ = write!(
+        console::stdout_get(),
+        "TLM,{},{:.2},{},{},{},{}\r\n",
+        Instant::now().as_millis(),
+        state.frequency,
+        rssi,
+        snr,
+        stereo as u8,
+        state.volume
+    );
+}
@@ -0,0 +1,221 @@
+//! Capacitive touch pads.
+//!
+//! Front panels without mechanical buttons use copper pads on the analog
+//! inputs instead. The COMP peripheral runs as a relaxation oscillator on one
+//! pad at a time: its current source charges and discharges the pad between
+//! the hysteresis thresholds, and every crossing is counted by a TIMER in
+//! counter mode through a (D)PPI channel. A finger adds capacitance and
+//! lowers the count, so a pad is touched once its count drops
+//! [`InputSettings::touch_threshold_pct`](crate::settings::InputSettings)
+//! below the pad's baseline.
+//!
+//! Baselines are measured at startup and with `touch calibrate` (keep hands
+//! off the panel meanwhile), and follow slow drift while a pad is released.
+//! Touched pads send their event like a button, with the same auto-repeat
+//! timing, and likewise acknowledge an alert or snooze a ringing alarm.
+
+use core::cell::Cell;
+use core::ptr::NonNull;
+
+use embassy_nrf::pac;
+use embassy_nrf::peripherals::{PPI_CH0, TIMER1};
+use embassy_nrf::ppi::{Event, Ppi};
+use embassy_nrf::{Peri, timer};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Timer};
+
+use crate::alarm;
+use crate::alert;
+use crate::events::{self, Origin, SystemEvent};
+use crate::kiosk;
+use crate::monitor;
+use crate::settings;
+
+/// Number of pads, on AIN0 to AIN3 (P0.04 to P0.07).
+pub const PADS: usize = 4;
+
+/// Time oscillations are counted on each pad.
+const WINDOW: Duration = Duration::from_millis(1);
+/// Pause between scans of all pads.
+const SCAN_INTERVAL: Duration = Duration::from_millis(20);
+/// Scans averaged into a fresh baseline.
+const CALIBRATION_SCANS: u32 = 16;
+/// Released pads move their baseline by 1/2^n of the difference per scan.
+const DRIFT_SHIFT: u32 = 6;
+
+/// COMP register values; see the nRF5340 COMP chapter.
+const COMP_MODE_SE_LOW_POWER: u32 = 0;
+const COMP_REFSEL_VDD: u32 = 4;
+/// Lower threshold at 20/64 and upper threshold at 40/64 of VDD.
+const COMP_TH: u32 = (40 << 8) | 20;
+const COMP_ISOURCE_10UA: u32 = 3;
+const COMP_ENABLED: u32 = 2;
+
+/// Measurement state of one pad.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PadState {
+    /// Oscillations counted in the last window.
+    pub count: u32,
+    /// Count of the released pad.
+    pub baseline: u32,
+    pub touched: bool,
+}
+
+impl PadState {
+    const fn new() -> Self {
+        Self {
+            count: 0,
+            baseline: 0,
+            touched: false,
+        }
+    }
+
+    /// Update the touch state from the latest count.
+    fn evaluate(&mut self, threshold_pct: u8) {
+        let press = self.baseline * (100 - threshold_pct.min(99) as u32) / 100;
+        // Release at half the threshold so a hovering finger does not chatter.
+        let release = self.baseline * (200 - threshold_pct.min(99) as u32) / 200;
+        if self.touched {
+            self.touched = self.count < release;
+        } else {
+            self.touched = self.count < press;
+        }
+        if !self.touched {
+            let baseline = self.baseline as i64;
+            let drift = (self.count as i64 - baseline) >> DRIFT_SHIFT;
+            self.baseline = (baseline + drift) as u32;
+        }
+    }
+}
+
+static STATE: Mutex<ThreadModeRawMutex, Cell<[PadState; PADS]>> =
+    Mutex::new(Cell::new([PadState::new(); PADS]));
+static CALIBRATE: Signal<ThreadModeRawMutex, ()> = Signal::new();
+
+/// Latest state of all pads.
+pub fn state() -> [PadState; PADS] {
+    STATE.lock(|state| state.get())
+}
+
+/// Measure new baselines. Pads must not be touched meanwhile.
+pub fn calibrate() {
+    CALIBRATE.signal(());
+}
+
+/// Start the COMP oscillating on analog input `pad`.
+fn comp_start(pad: usize) {
+    let comp = pac::COMP;
+    comp.psel().write_value(pac::comp::regs::Psel(pad as u32));
+    comp.mode()
+        .write_value(pac::comp::regs::Mode(COMP_MODE_SE_LOW_POWER));
+    comp.refsel()
+        .write_value(pac::comp::regs::Refsel(COMP_REFSEL_VDD));
+    comp.th().write_value(pac::comp::regs::Th(COMP_TH));
+    comp.isource()
+        .write_value(pac::comp::regs::Isource(COMP_ISOURCE_10UA));
+    comp.enable()
+        .write_value(pac::comp::regs::Enable(COMP_ENABLED));
+    comp.tasks_start().write_value(1);
+}
+
+fn comp_stop() {
+    let comp = pac::COMP;
+    comp.tasks_stop().write_value(1);
+    comp.enable().write_value(pac::comp::regs::Enable(0));
+}
+
+/// Scan the pads and send `pad_events[n]` while pad `n` is touched.
+///
+/// Only spawned in builds with the `touch` feature.
+#[embassy_executor::task]
+pub async fn touch_task(
+    timer: Peri<'static, TIMER1>,
+    channel: Peri<'static, PPI_CH0>,
+    pad_events: [SystemEvent; PADS],
+) {
+    let heartbeat = monitor::register("touch");
+    let counter = timer::Timer::new_counter(timer);
+    // SAFETY: EVENTS_CROSS is a valid event register and COMP is only
+    // accessed by this task.
+    let cross =
+        unsafe { Event::new_unchecked(NonNull::new_unchecked(pac::COMP.events_cross().as_ptr())) };
+    let mut ppi = Ppi::new_one_to_one(channel, cross, counter.task_count());
+    ppi.enable();
+    counter.start();
+
+    let scan = async || {
+        let mut counts = [0u32; PADS];
+        for (pad, count) in counts.iter_mut().enumerate() {
+            counter.clear();
+            comp_start(pad);
+            Timer::after(WINDOW).await;
+            comp_stop();
+            *count = counter.cc(0).capture();
+        }
+        counts
+    };
+
+    // Next time each pad repeats its event while held.
+    let mut repeat_at: [Option<Instant>; PADS] = [None; PADS];
+    CALIBRATE.signal(());
+    loop {
+        if CALIBRATE.try_take().is_some() {
+            let mut sums = [0u32; PADS];
+            for _ in 0..CALIBRATION_SCANS {
+                for (sum, count) in sums.iter_mut().zip(scan().await) {
+                    *sum += count;
+                }
+                Timer::after(SCAN_INTERVAL).await;
+            }
+            STATE.lock(|state| {
+                let mut pads = state.get();
+                for (pad, sum) in pads.iter_mut().zip(sums) {
+                    pad.baseline = sum / CALIBRATION_SCANS;
+                    pad.touched = false;
+                }
+                state.set(pads);
+            });
+            repeat_at = [None; PADS];
+        }
+
+        let counts = scan().await;
+        heartbeat.beat();
+        let config = settings::get().input;
+        let pads = STATE.lock(|state| {
+            let mut pads = state.get();
+            for (pad, count) in pads.iter_mut().zip(counts) {
+                pad.count = count;
+                pad.evaluate(config.touch_threshold_pct);
+            }
+            state.set(pads);
+            pads
+        });
+        let now = Instant::now();
+        for (index, pad) in pads.iter().enumerate() {
+            match (pad.touched, repeat_at[index]) {
+                (false, _) => repeat_at[index] = None,
+                (true, None) if alert::acknowledge() || alarm::snooze() => {
+                    // Held pads do not repeat after acknowledging or snoozing.
+                    repeat_at[index] = Some(Instant::MAX);
+                }
+                (true, None) if kiosk::blocks(&pad_events[index]) => {
+                    repeat_at[index] = Some(Instant::MAX);
+                }
+                (true, None) => {
+                    events::event_try_send(Origin::Input, pad_events[index]);
+                    let delay = Duration::from_millis(config.repeat_delay_ms as u64);
+                    repeat_at[index] = Some(now + delay);
+                }
+                (true, Some(at)) if at <= now => {
+                    events::event_try_send(Origin::Input, pad_events[index]);
+                    let interval = Duration::from_millis(config.repeat_interval_ms as u64);
+                    repeat_at[index] = Some(now + interval);
+                }
+                (true, Some(_)) => {}
+            }
+        }
+        Timer::after(SCAN_INTERVAL).await;
+    }
+}
@@ -0,0 +1,288 @@
+//! Event trace for reproducing field bugs.
+//!
+//! While tracing is enabled in the [`LogSettings`](crate::settings::LogSettings)
+//! the radio loop records every [`SystemEvent`] it handles to the `trace`
+//! flash partition, together with the time since boot. Each boot (and
+//! `trace on`) starts a new session with a marker, so a trace taken in the
+//! field shows what led up to a reset. Recording stops when the partition is
+//! full; the next session then erases it and starts over.
+//!
+//! `replay last` stores a request in `GPREGRET[1]`, which survives the
+//! system reset, and restarts the firmware. At the next boot [`init`] picks
+//! the request up and [`replay_task`] sends the events of the last session
+//! before the request again, with their original spacing, as soon as the
+//! radio loop runs. Nothing is recorded during a replay boot.
+//!
+//! Replay repeats the commands, not the conditions: reception, RDS and chip
+//! timing differ, so a bug depending on those may not show again.
+
+use core::cell::Cell;
+
+use embassy_nrf::pac;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_time::{Duration, Instant, Timer};
+use log::{info, warn};
+
+use crate::error::Error;
+use crate::events::{self, Origin, SystemEvent};
+use crate::monitor;
+use crate::settings;
+use crate::startup;
+use crate::storage::TRACE;
+
+/// Size of one trace entry.
+const SLOT_SIZE: u32 = 16;
+/// Number of entries the partition holds.
+pub const CAPACITY: u32 = TRACE.size / SLOT_SIZE;
+/// Offset of the time since boot, in milliseconds, within a slot.
+const TIME: usize = 4;
+/// Offset of the serialized event within a slot.
+const PAYLOAD: usize = 8;
+/// First byte of a slot starting a session; otherwise it holds the payload
+/// length.
+const SESSION: u8 = 0;
+/// First byte of a slot that has not been written since the last erase.
+const EMPTY: u8 = 0xFF;
+/// `GPREGRET[1]` value requesting a replay, or-ed with the number of
+/// sessions to go back.
+const REPLAY_MAGIC: u8 = 0xA0;
+
+/// Slot the next event is recorded to, while recording.
+static NEXT: Mutex<ThreadModeRawMutex, Cell<Option<u32>>> = Mutex::new(Cell::new(None));
+/// Sessions started since boot.
+static STARTED: Mutex<ThreadModeRawMutex, Cell<u8>> = Mutex::new(Cell::new(0));
+/// Set for the whole boot when it replays a trace.
+static REPLAYING: Mutex<ThreadModeRawMutex, Cell<bool>> = Mutex::new(Cell::new(false));
+
+/// A recorded event.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Entry {
+    /// Time since the boot of its session.
+    pub time: Duration,
+    pub event: SystemEvent,
+}
+
+/// Slots in use and sessions stored.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Usage {
+    pub used: u32,
+    pub sessions: u32,
+}
+
+fn read_slot(index: u32) -> Result<[u8; SLOT_SIZE as usize], Error> {
+    let mut slot = [0; SLOT_SIZE as usize];
+    TRACE.read(index * SLOT_SIZE, &mut slot)?;
+    Ok(slot)
+}
+
+fn write_slot(index: u32, event: Option<&SystemEvent>) -> Result<(), Error> {
+    let mut slot = [EMPTY; SLOT_SIZE as usize];
+    slot[0] = match event {
+        Some(event) => postcard::to_slice(event, &mut slot[PAYLOAD..])
+            .map_err(|_| Error::InvalidArgument)?
+            .len() as u8,
+        None => SESSION,
+    };
+    let time = Instant::now().as_millis() as u32;
+    slot[TIME..PAYLOAD].copy_from_slice(&time.to_le_bytes());
+    TRACE.write(index * SLOT_SIZE, &slot)?;
+    Ok(())
+}
+
+/// Slots in use and sessions stored.
+pub fn usage() -> Result<Usage, Error> {
+    let mut usage = Usage {
+        used: 0,
+        sessions: 0,
+    };
+    while usage.used < CAPACITY {
+        match read_slot(usage.used)?[0] {
+            EMPTY => break,
+            SESSION => usage.sessions += 1,
+            _ => {}
+        }
+        usage.used += 1;
+    }
+    Ok(usage)
+}
+
+/// Whether events are being recorded.
+pub fn is_recording() -> bool {
+    NEXT.lock(|next| next.get()).is_some()
+}
+
+/// Whether this boot replays a trace.
+pub fn is_replaying() -> bool {
+    REPLAYING.lock(|replaying| replaying.get())
+}
+
+/// Start a new session after the stored ones.
+fn start() -> Result<(), Error> {
+    if is_replaying() || is_recording() {
+        return Ok(());
+    }
+    let mut index = usage()?.used;
+    if index >= CAPACITY {
+        TRACE.erase_all()?;
+        index = 0;
+    }
+    write_slot(index, None)?;
+    NEXT.lock(|next| next.set(Some(index + 1)));
+    STARTED.lock(|started| started.set(started.get().saturating_add(1)));
+    Ok(())
+}
+
+/// Stop recording.
+pub fn stop() {
+    NEXT.lock(|next| next.set(None));
+}
+
+/// Take a replay request left by [`request_replay`] and start recording if
+/// enabled. Returns the number of sessions to go back when replaying.
+///
+/// Call once during startup, after the settings are loaded.
+pub fn init() -> Result<Option<u8>, Error> {
+    let request = pac::POWER.gpregret(1).read().gpregret();
+    pac::POWER.gpregret(1).write(|w| w.set_gpregret(0));
+    if request & 0xF0 == REPLAY_MAGIC {
+        REPLAYING.lock(|replaying| replaying.set(true));
+        return Ok(Some(request & 0x0F));
+    }
+    if settings::get().log.trace {
+        start()?;
+    }
+    Ok(None)
+}
+
+/// Enable or disable recording, starting a new session when enabled.
+pub fn set_enabled(enabled: bool) -> Result<(), Error> {
+    settings::update(|s| s.log.trace = enabled);
+    if enabled {
+        return start();
+    }
+    stop();
+    Ok(())
+}
+
+/// Erase the stored trace, starting a new session if recording.
+pub fn clear() -> Result<(), Error> {
+    let recording = is_recording();
+    stop();
+    TRACE.erase_all()?;
+    if recording { start() } else { Ok(()) }
+}
+
+/// Record `event`. Called by the radio loop for every event it handles.
+pub fn record(event: &SystemEvent) {
+    let Some(index) = NEXT.lock(|next| next.get()) else {
+        return;
+    };
+    if index >= CAPACITY {
+        warn!("Trace partition full, recording stopped");
+        stop();
+        return;
+    }
+    if let Err(err) = write_slot(index, Some(event)) {
+        warn!("Failed to record event: {}", err);
+        stop();
+        return;
+    }
+    NEXT.lock(|next| next.set(Some(index + 1)));
+}
+
+/// Slots of the session `back` sessions before the end, without its marker.
+fn session(back: u8) -> Result<Option<(u32, u32)>, Error> {
+    if back == 0 {
+        return Ok(None);
+    }
+    let usage = usage()?;
+    let mut end = usage.used;
+    let mut start = None;
+    let mut remaining = back;
+    for index in (0..usage.used).rev() {
+        if read_slot(index)?[0] != SESSION {
+            continue;
+        }
+        remaining -= 1;
+        if remaining == 0 {
+            start = Some(index + 1);
+            break;
+        }
+        end = index;
+    }
+    Ok(start.map(|start| (start, end)))
+}
+
+/// Sessions to go back to reach the last one recorded before this boot.
+fn last_complete() -> u8 {
+    STARTED.lock(|started| started.get()).saturating_add(1)
+}
+
+/// Call `f` for every event of the last completed session.
+pub fn for_each_last(mut f: impl FnMut(Entry)) -> Result<bool, Error> {
+    let Some((start, end)) = session(last_complete())? else {
+        return Ok(false);
+    };
+    for index in start..end {
+        if let Some(entry) = decode(&read_slot(index)?) {
+            f(entry);
+        }
+    }
+    Ok(true)
+}
+
+fn decode(slot: &[u8; SLOT_SIZE as usize]) -> Option<Entry> {
+    let len = slot[0] as usize;
+    let payload = slot.get(PAYLOAD..PAYLOAD + len)?;
+    let event = postcard::from_bytes(payload).ok()?;
+    let time = u32::from_le_bytes(slot[TIME..PAYLOAD].try_into().ok()?);
+    Some(Entry {
+        time: Duration::from_millis(time as u64),
+        event,
+    })
+}
+
+/// Ask for the last completed session to be replayed after the next reset.
+pub fn request_replay() -> Result<(), Error> {
+    let back = last_complete();
+    if session(back)?.is_none() {
+        return Err(Error::InvalidArgument);
+    }
+    pac::POWER
+        .gpregret(1)
+        .write(|w| w.set_gpregret(REPLAY_MAGIC | back));
+    Ok(())
+}
+
+/// Send the events of the session `back` sessions before the end again.
+#[embassy_executor::task]
+pub async fn replay_task(back: u8) {
+    let heartbeat = monitor::register("replay");
+    startup::radio_ready().await;
+    let (start, end) = match session(back) {
+        Ok(Some(slots)) => slots,
+        Ok(None) => {
+            warn!("No trace session to replay");
+            return;
+        }
+        Err(err) => {
+            warn!("Failed to read trace: {}", err);
+            return;
+        }
+    };
+    info!("Replaying {} trace entries", end - start);
+    let began = Instant::now();
+    let mut first = None;
+    for index in start..end {
+        let Some(entry) = read_slot(index).ok().as_ref().and_then(decode) else {
+            continue;
+        };
+        let first = *first.get_or_insert(entry.time);
+        Timer::at(began + (entry.time - first)).await;
+        heartbeat.beat();
+        info!("Replay: {:?}", entry.event);
+        events::event_send(Origin::Replay, entry.event).await;
+    }
+    info!("Replay finished");
+}
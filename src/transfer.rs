@@ -0,0 +1,285 @@
+//! XMODEM/YMODEM file transfer over the console UART.
+//!
+//! [`receive`] accepts XMODEM-CRC, XMODEM-1K and single-file YMODEM uploads
+//! (`sx`, `sb` or any terminal program) into a [`Sink`]; the YMODEM header
+//! supplies the exact file size so block padding is stripped. [`send`] serves
+//! a [`Source`] as XMODEM-1K with CRC (`rx` on the host). Text output is
+//! suspended for the duration of a transfer, which is announced with
+//! [`SystemNotify::Progress`], and progress is published as
+//! [`SystemNotify::TransferProgress`] every [`PROGRESS_INTERVAL`] bytes.
+
+use embassy_nrf::uarte::UarteRx;
+use embassy_time::{Duration, with_timeout};
+
+use crate::checksum::crc16_xmodem;
+use crate::console;
+use crate::error::Error;
+use crate::events::{self, Operation, Origin, SystemNotify};
+
+const SOH: u8 = 0x01;
+const STX: u8 = 0x02;
+const EOT: u8 = 0x04;
+const ACK: u8 = 0x06;
+const NAK: u8 = 0x15;
+const CAN: u8 = 0x18;
+const SUB: u8 = 0x1A;
+/// Sent by the receiver to request CRC mode.
+const CRC_REQUEST: u8 = b'C';
+
+const BLOCK_SIZE: usize = 128;
+const BLOCK_SIZE_1K: usize = 1024;
+
+/// Time between start requests while waiting for the other side.
+const START_INTERVAL: Duration = Duration::from_secs(3);
+/// Start requests before giving up, about one minute in total.
+const START_ATTEMPTS: u8 = 20;
+/// Time allowed for the remainder of a block or for an acknowledgement.
+const BLOCK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Silence that ends discarding of a corrupted block.
+const PURGE_TIMEOUT: Duration = Duration::from_millis(100);
+/// Consecutive errors before a transfer is abandoned.
+const MAX_ERRORS: u8 = 10;
+
+/// Bytes between progress notifications.
+pub const PROGRESS_INTERVAL: u32 = 4096;
+
+/// Destination of a received file.
+pub trait Sink {
+    /// Store the next part of the file.
+    fn write(&mut self, data: &[u8]) -> Result<(), Error>;
+    /// The whole file has been received.
+    fn finish(&mut self) -> Result<(), Error>;
+}
+
+/// Origin of a sent file.
+pub trait Source {
+    /// Fill `buf` with the next part of the file, returning 0 at the end.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error>;
+}
+
+async fn read_exact(rx: &mut UarteRx<'_>, buf: &mut [u8], timeout: Duration) -> Result<(), Error> {
+    match with_timeout(timeout, rx.read(buf)).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(_)) => Err(Error::Transfer),
+        Err(_) => Err(Error::Timeout),
+    }
+}
+
+async fn read_byte(rx: &mut UarteRx<'_>, timeout: Duration) -> Result<u8, Error> {
+    let mut byte = [0u8];
+    read_exact(rx, &mut byte, timeout).await?;
+    Ok(byte[0])
+}
+
+/// Discard input until the line has been quiet for [`PURGE_TIMEOUT`].
+async fn purge(rx: &mut UarteRx<'_>) {
+    while read_byte(rx, PURGE_TIMEOUT).await.is_ok() {}
+}
+
+fn cancel() {
+    console::write_raw(&[CAN, CAN]);
+}
+
+fn report_progress(before: u32, after: u32) {
+    if before / PROGRESS_INTERVAL != after / PROGRESS_INTERVAL {
+        events::notify_immediate(Origin::Console, SystemNotify::TransferProgress(after));
+    }
+}
+
+/// Parse the file size from a YMODEM header block (`name\0size ...`).
+///
+/// Returns `None` for the empty header that ends a batch.
+fn ymodem_size(header: &[u8]) -> Option<Option<u32>> {
+    let mut fields = header.split(|&b| b == 0);
+    let name = fields.next().unwrap_or_default();
+    if name.is_empty() {
+        return None;
+    }
+    let size = fields
+        .next()
+        .and_then(|info| info.split(|&b| b == b' ').next())
+        .and_then(|size| core::str::from_utf8(size).ok())
+        .and_then(|size| size.parse().ok());
+    Some(size)
+}
+
+/// Receive a file from the host into `sink`.
+///
+/// Returns the number of bytes stored.
+pub async fn receive(rx: &mut UarteRx<'_>, sink: &mut impl Sink) -> Result<u32, Error> {
+    events::notify_immediate(
+        Origin::Console,
+        SystemNotify::Progress(Some(Operation::Transfer)),
+    );
+    console::suspend();
+    let result = receive_blocks(rx, sink).await;
+    if result.is_err() {
+        cancel();
+    }
+    console::resume();
+    events::notify_immediate(Origin::Console, SystemNotify::Progress(None));
+    result
+}
+
+async fn receive_blocks(rx: &mut UarteRx<'_>, sink: &mut impl Sink) -> Result<u32, Error> {
+    let mut frame = [0u8; BLOCK_SIZE_1K + 4];
+    let mut expected: u8 = 1;
+    let mut received: u32 = 0;
+    // Bytes still to come as announced by a YMODEM header.
+    let mut remaining: Option<u32> = None;
+    let mut ymodem = false;
+    let mut started = false;
+    let mut errors = 0;
+    let mut reply = CRC_REQUEST;
+    loop {
+        console::write_raw(&[reply]);
+        let timeout = if started {
+            BLOCK_TIMEOUT
+        } else {
+            START_INTERVAL
+        };
+        let size = match read_byte(rx, timeout).await {
+            Ok(SOH) => BLOCK_SIZE,
+            Ok(STX) => BLOCK_SIZE_1K,
+            Ok(EOT) => break,
+            Ok(CAN) => return Err(Error::Transfer),
+            result => {
+                errors += 1;
+                let limit = if started { MAX_ERRORS } else { START_ATTEMPTS };
+                if errors >= limit {
+                    return Err(result.err().unwrap_or(Error::Transfer));
+                }
+                if result.is_ok() {
+                    purge(rx).await;
+                }
+                reply = if started { NAK } else { CRC_REQUEST };
+                continue;
+            }
+        };
+        let frame = &mut frame[..size + 4];
+        let valid = read_exact(rx, frame, BLOCK_TIMEOUT).await.is_ok()
+            && frame[0] == !frame[1]
+            && crc16_xmodem(&frame[2..size + 2])
+                == u16::from_be_bytes([frame[size + 2], frame[size + 3]]);
+        if !valid {
+            errors += 1;
+            if errors >= MAX_ERRORS {
+                return Err(Error::Checksum);
+            }
+            purge(rx).await;
+            reply = NAK;
+            continue;
+        }
+        errors = 0;
+        let (number, data) = (frame[0], &frame[2..size + 2]);
+        if !started && number == 0 {
+            // YMODEM header; the data blocks follow after another start request.
+            remaining = ymodem_size(data).ok_or(Error::Transfer)?;
+            ymodem = true;
+            console::write_raw(&[ACK]);
+            reply = CRC_REQUEST;
+            continue;
+        }
+        started = true;
+        if number == expected {
+            let len = remaining.map_or(size, |left| left.min(size as u32) as usize);
+            sink.write(&data[..len])?;
+            remaining = remaining.map(|left| left - len as u32);
+            report_progress(received, received + len as u32);
+            received += len as u32;
+            expected = expected.wrapping_add(1);
+        } else if number != expected.wrapping_sub(1) {
+            // Anything but a repeat of the last block means the stream is lost.
+            return Err(Error::Transfer);
+        }
+        reply = ACK;
+    }
+    console::write_raw(&[ACK]);
+    if ymodem {
+        // Acknowledge the empty header that closes the batch.
+        console::write_raw(&[CRC_REQUEST]);
+        if read_byte(rx, BLOCK_TIMEOUT).await == Ok(SOH) {
+            let mut end = [0u8; BLOCK_SIZE + 4];
+            if read_exact(rx, &mut end, BLOCK_TIMEOUT).await.is_ok() {
+                console::write_raw(&[ACK]);
+            }
+        }
+    }
+    sink.finish()?;
+    Ok(received)
+}
+
+/// Send the contents of `source` to the host.
+///
+/// Returns the number of bytes sent, excluding block padding.
+pub async fn send(rx: &mut UarteRx<'_>, source: &mut impl Source) -> Result<u32, Error> {
+    events::notify_immediate(
+        Origin::Console,
+        SystemNotify::Progress(Some(Operation::Transfer)),
+    );
+    console::suspend();
+    let result = send_blocks(rx, source).await;
+    if result.is_err() {
+        cancel();
+    }
+    console::resume();
+    events::notify_immediate(Origin::Console, SystemNotify::Progress(None));
+    result
+}
+
+async fn send_blocks(rx: &mut UarteRx<'_>, source: &mut impl Source) -> Result<u32, Error> {
+    let mut attempts = 0;
+    loop {
+        match read_byte(rx, START_INTERVAL).await {
+            Ok(CRC_REQUEST) => break,
+            Ok(CAN) => return Err(Error::Transfer),
+            _ => {
+                attempts += 1;
+                if attempts >= START_ATTEMPTS {
+                    return Err(Error::Timeout);
+                }
+            }
+        }
+    }
+    let mut block = [0u8; BLOCK_SIZE_1K + 5];
+    let mut number: u8 = 1;
+    let mut sent: u32 = 0;
+    loop {
+        let data = &mut block[3..BLOCK_SIZE_1K + 3];
+        let mut len = 0;
+        while len < data.len() {
+            match source.read(&mut data[len..])? {
+                0 => break,
+                read => len += read,
+            }
+        }
+        if len == 0 {
+            break;
+        }
+        data[len..].fill(SUB);
+        let crc = crc16_xmodem(data);
+        block[0] = STX;
+        block[1] = number;
+        block[2] = !number;
+        block[BLOCK_SIZE_1K + 3..].copy_from_slice(&crc.to_be_bytes());
+        send_acknowledged(rx, &block).await?;
+        report_progress(sent, sent + len as u32);
+        sent += len as u32;
+        number = number.wrapping_add(1);
+    }
+    send_acknowledged(rx, &[EOT]).await?;
+    Ok(sent)
+}
+
+/// Send `frame` until the host acknowledges it.
+async fn send_acknowledged(rx: &mut UarteRx<'_>, frame: &[u8]) -> Result<(), Error> {
+    for _ in 0..MAX_ERRORS {
+        console::write_raw(frame);
+        match read_byte(rx, BLOCK_TIMEOUT).await {
+            Ok(ACK) => return Ok(()),
+            Ok(CAN) => return Err(Error::Transfer),
+            _ => {}
+        }
+    }
+    Err(Error::Timeout)
+}
@@ -0,0 +1,231 @@
+//! Parental and installer lock on the tuning range.
+//!
+//! A [`TuneLock`] restricts tuning to a sub-band, to an allow-list of
+//! stations, or to either. The radio loop refuses to tune a direct frequency
+//! outside it with [`Error::Locked`](crate::error::Error::Locked), holds step
+//! tuning to the permitted frequencies and keeps seeking past stations that
+//! are not permitted. Without a sub-band or stations every frequency is
+//! permitted.
+//!
+//! Once a PIN is set, the lock can only be changed after `tune-lock unlock
+//! <pin>`, which lasts until `tune-lock lock` or the next reset. The lock is
+//! kept in the persisted settings.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::frequency::Frequency;
+
+/// Number of stations the allow-list holds.
+pub const MAX_ALLOWED: usize = 8;
+
+/// Unlocked with the PIN since boot.
+static UNLOCKED: AtomicBool = AtomicBool::new(false);
+
+/// Permitted frequencies and the PIN guarding them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TuneLock {
+    /// PIN needed to change the lock.
+    pin: Option<u32>,
+    /// Permitted sub-band, both ends included.
+    pub range: Option<(Frequency, Frequency)>,
+    allowed: [Option<Frequency>; MAX_ALLOWED],
+}
+
+impl TuneLock {
+    pub const fn new() -> Self {
+        Self {
+            pin: None,
+            range: None,
+            allowed: [None; MAX_ALLOWED],
+        }
+    }
+
+    pub fn allowed(&self) -> impl Iterator<Item = &Frequency> {
+        self.allowed.iter().flatten()
+    }
+
+    /// Whether tuning is restricted at all.
+    pub fn is_active(&self) -> bool {
+        self.range.is_some() || self.allowed().next().is_some()
+    }
+
+    /// Whether `frequency` may be tuned: inside the sub-band or on the
+    /// allow-list.
+    pub fn permits(&self, frequency: Frequency) -> bool {
+        !self.is_active()
+            || self
+                .range
+                .is_some_and(|(low, high)| (low..=high).contains(&frequency))
+            || self.allowed().any(|&allowed| allowed == frequency)
+    }
+
+    /// Frequency to tune when stepping from `from` to `target`: `target` if
+    /// permitted, otherwise the nearest permitted frequency beyond `from` in
+    /// the direction of the step, if any.
+    pub fn constrain(&self, from: Frequency, target: Frequency) -> Option<Frequency> {
+        if self.permits(target) {
+            return Some(target);
+        }
+        // The nearest permitted frequency is a station or an end of the
+        // sub-band.
+        let ends = self.range.map(|(low, high)| [low, high]);
+        let candidates = self.allowed().copied().chain(ends.into_iter().flatten());
+        if target > from {
+            candidates.filter(|&c| c > from).min()
+        } else {
+            candidates.filter(|&c| c < from).max()
+        }
+    }
+
+    /// Add `frequency` to the allow-list.
+    pub fn allow(&mut self, frequency: Frequency) -> Result<(), Error> {
+        if self.allowed().any(|&allowed| allowed == frequency) {
+            return Ok(());
+        }
+        let slot = self
+            .allowed
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(Error::InvalidArgument)?;
+        *slot = Some(frequency);
+        Ok(())
+    }
+
+    /// Remove `frequency` from the allow-list, returning whether it was on it.
+    pub fn remove(&mut self, frequency: Frequency) -> bool {
+        let slot = self
+            .allowed
+            .iter_mut()
+            .find(|slot| **slot == Some(frequency));
+        slot.map(|slot| slot.take()).is_some()
+    }
+
+    /// Lift every restriction; the PIN is kept.
+    pub fn clear(&mut self) {
+        self.range = None;
+        self.allowed = [None; MAX_ALLOWED];
+    }
+
+    pub fn has_pin(&self) -> bool {
+        self.pin.is_some()
+    }
+
+    /// Set the PIN needed for changes, or remove it with `None`.
+    pub fn set_pin(&mut self, pin: Option<u32>) {
+        self.pin = pin;
+    }
+
+    /// Whether the lock may be changed: no PIN is set, or it was unlocked.
+    pub fn is_modifiable(&self) -> bool {
+        self.pin.is_none() || UNLOCKED.load(Ordering::Relaxed)
+    }
+
+    /// Allow changes until [`relock`] if `pin` is right. Returns whether it
+    /// was.
+    pub fn unlock(&self, pin: u32) -> bool {
+        let right = self.pin == Some(pin);
+        if right {
+            UNLOCKED.store(true, Ordering::Relaxed);
+        }
+        right
+    }
+}
+
+impl Default for TuneLock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Require the PIN again for changes.
+pub fn relock() {
+    UNLOCKED.store(false, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn khz(khz: u32) -> Frequency {
+        Frequency::from_khz(khz)
+    }
+
+    /// Sub-band 95.0 to 100.0 MHz plus two stations either side of it.
+    fn lock() -> TuneLock {
+        let mut lock = TuneLock::new();
+        lock.range = Some((khz(95_000), khz(100_000)));
+        lock.allow(khz(88_100)).unwrap();
+        lock.allow(khz(104_300)).unwrap();
+        lock
+    }
+
+    #[test]
+    fn unrestricted_lock_keeps_the_target() {
+        let lock = TuneLock::new();
+        assert_eq!(
+            lock.constrain(khz(87_500), khz(108_000)),
+            Some(khz(108_000))
+        );
+    }
+
+    #[test]
+    fn permitted_target_is_kept() {
+        let lock = lock();
+        assert_eq!(lock.constrain(khz(95_000), khz(95_100)), Some(khz(95_100)));
+        assert_eq!(
+            lock.constrain(khz(100_100), khz(104_300)),
+            Some(khz(104_300))
+        );
+    }
+
+    #[test]
+    fn step_up_skips_to_next_permitted() {
+        let lock = lock();
+        // Into the sub-band from a station below it.
+        assert_eq!(lock.constrain(khz(88_100), khz(88_200)), Some(khz(95_000)));
+        // Past the top of the sub-band to the next station.
+        assert_eq!(
+            lock.constrain(khz(100_000), khz(100_100)),
+            Some(khz(104_300))
+        );
+        // Nothing is permitted above the last station.
+        assert_eq!(lock.constrain(khz(104_300), khz(104_400)), None);
+    }
+
+    #[test]
+    fn step_down_skips_to_next_permitted() {
+        let lock = lock();
+        assert_eq!(
+            lock.constrain(khz(104_300), khz(104_200)),
+            Some(khz(100_000))
+        );
+        assert_eq!(lock.constrain(khz(95_000), khz(94_900)), Some(khz(88_100)));
+        assert_eq!(lock.constrain(khz(88_100), khz(88_000)), None);
+    }
+
+    #[test]
+    fn large_step_lands_on_nearest_permitted() {
+        let lock = lock();
+        // A step from outside everything stops at the first permitted
+        // frequency in its direction, not at the target's neighbour.
+        assert_eq!(lock.constrain(khz(87_500), khz(101_000)), Some(khz(88_100)));
+        assert_eq!(
+            lock.constrain(khz(108_000), khz(90_000)),
+            Some(khz(104_300))
+        );
+    }
+
+    #[test]
+    fn allow_list_only() {
+        let mut lock = TuneLock::new();
+        lock.allow(khz(99_000)).unwrap();
+        assert!(!lock.permits(khz(99_100)));
+        assert_eq!(lock.constrain(khz(87_500), khz(87_600)), Some(khz(99_000)));
+        assert!(lock.remove(khz(99_000)));
+        assert!(!lock.is_active());
+        assert_eq!(lock.constrain(khz(87_500), khz(87_600)), Some(khz(87_600)));
+    }
+}
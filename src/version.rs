@@ -0,0 +1,52 @@
+//! Firmware version and image size.
+//!
+//! `version` prints the package version and the Cargo profile the firmware
+//! was built with; `version --size` adds how much of the flash and RAM the
+//! image takes, to check that a build still fits a part with less memory.
+//! The profile and the memory sizes are generated by build.rs from the Cargo
+//! profile and `memory.x`. The amounts used come from the linker symbols
+//! bounding the sections, so they describe the image actually running.
+//!
+//! `cargo build --profile size` builds the smallest image.
+
+use core::ptr::addr_of;
+
+/// A memory region from `memory.x`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Memory {
+    pub origin: u32,
+    /// Size in bytes.
+    pub length: u32,
+}
+
+include!(concat!(env!("OUT_DIR"), "/build_info.rs"));
+
+pub const NAME: &str = env!("CARGO_PKG_NAME");
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+unsafe extern "C" {
+    /// Load address of `.data` in flash, the end of code and constants.
+    static __sidata: u32;
+    static __sdata: u32;
+    static __edata: u32;
+    /// End of the statics in RAM.
+    static __sheap: u32;
+}
+
+/// Bytes of flash and RAM taken by the image.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Size {
+    /// Code, constants and the initial values of `.data`.
+    pub flash: u32,
+    /// Statics; the main stack takes the rest.
+    pub ram: u32,
+}
+
+/// Size of the running image.
+pub fn size() -> Size {
+    let data = addr_of!(__edata) as u32 - addr_of!(__sdata) as u32;
+    Size {
+        flash: addr_of!(__sidata) as u32 - FLASH.origin + data,
+        ram: addr_of!(__sheap) as u32 - RAM.origin,
+    }
+}
@@ -0,0 +1,78 @@
+//! Volume mapping and limit.
+//!
+//! Commands give the volume in percent; the radio loop turns it into an
+//! `RX_VOLUME` step with [`chip_level`], so the curve and the maximum in
+//! [`AudioSettings`](crate::settings::AudioSettings) apply to every caller
+//! alike: CLI, buttons, alarm, sleep timer and host protocol. Relative steps
+//! from buttons and the encoder work on chip steps directly and are held
+//! below [`chip_max`]. Volume changes are reported in percent again, mapped
+//! back with [`percent`].
+
+use serde::{Deserialize, Serialize};
+
+use crate::settings;
+
+/// Highest value accepted by `RX_VOLUME`.
+pub const MAX_STEP: u16 = 63;
+
+/// Mapping from a volume in percent to `RX_VOLUME` steps.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VolumeCurve {
+    /// Steps proportional to the percentage.
+    Linear,
+    /// Audio taper: fine steps at low volume, half the steps at 80 %.
+    Soft,
+}
+
+impl VolumeCurve {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            VolumeCurve::Linear => "linear",
+            VolumeCurve::Soft => "soft",
+        }
+    }
+
+    /// Fraction of full scale in 1/1000 at 0, 10, ... 100 %.
+    const fn points(&self) -> [u32; 11] {
+        match self {
+            VolumeCurve::Linear => [0, 100, 200, 300, 400, 500, 600, 700, 800, 900, 1000],
+            VolumeCurve::Soft => [0, 15, 35, 60, 95, 140, 200, 290, 500, 720, 1000],
+        }
+    }
+
+    /// `RX_VOLUME` step at `percent`, before the maximum applies.
+    fn step(&self, percent: u8) -> u16 {
+        (self.scale(percent) * MAX_STEP as u32).div_ceil(1000) as u16
+    }
+
+    /// Fraction of full scale in 1/1000 at `percent`.
+    fn scale(&self, percent: u8) -> u32 {
+        let percent = percent.min(100) as u32;
+        let points = self.points();
+        let index = (percent / 10) as usize;
+        let Some(&next) = points.get(index + 1) else {
+            return points[index];
+        };
+        points[index] + (next - points[index]) * (percent % 10) / 10
+    }
+}
+
+/// `RX_VOLUME` step for a volume of `percent`, limited to the maximum.
+pub fn chip_level(percent: u8) -> u16 {
+    let audio = settings::get().audio;
+    audio.volume_curve.step(percent.min(audio.max_volume_pct))
+}
+
+/// Volume in percent for the `RX_VOLUME` step `step`: the lowest percentage
+/// that maps to at least that step.
+pub fn percent(step: u16) -> u8 {
+    let curve = settings::get().audio.volume_curve;
+    (0..=100)
+        .find(|&percent| curve.step(percent) >= step)
+        .unwrap_or(100)
+}
+
+/// Highest `RX_VOLUME` step the maximum allows.
+pub fn chip_max() -> u16 {
+    chip_level(100)
+}